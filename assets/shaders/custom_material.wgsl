@@ -2,6 +2,8 @@ struct CustomMaterial {
     color: vec4<f32>,
     tile: f32,
     time: f32,
+    scroll_speed: vec2<f32>,
+    flash: f32,
 };
 
 @group(1) @binding(0)
@@ -20,9 +22,11 @@ fn fragment(
     if(material.tile > 0.0) {
         var tiled_uv_x: f32;
         var tiled_uv_y: f32;
-        tiled_uv_x = fract(uv.x * 10.0);
-        tiled_uv_y = fract(uv.y * 7.0 - material.time);
+        tiled_uv_x = fract(uv.x * 10.0 - material.scroll_speed.x * material.time);
+        tiled_uv_y = fract(uv.y * 7.0 + material.scroll_speed.y * material.time);
         tiled_uv = vec2(tiled_uv_x,tiled_uv_y);
     }
-    return textureSample(base_color_texture, base_color_sampler, tiled_uv);
+    var sprite_color = textureSample(base_color_texture, base_color_sampler, tiled_uv);
+    // Blend towards white when hit (keeping the sprite's shape)
+    return vec4(mix(sprite_color.rgb, vec3(1.0), material.flash), sprite_color.a);
 }
\ No newline at end of file
@@ -0,0 +1,146 @@
+use bevy::{input::InputSystem, prelude::*};
+
+use crate::{projectile::Pooled, settings::PlayerInput, shared::*};
+
+pub struct AttractPlugin;
+
+impl Plugin for AttractPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(AttractTimer(Timer::from_seconds(
+            ATTRACT_IDLE_TIME,
+            TimerMode::Once,
+        )))
+        .insert_resource(DemoTimer(Timer::from_seconds(DEMO_TIME, TimerMode::Once)))
+        .add_system(start_demo)
+        .add_system(end_demo)
+        // (overrides whatever the keyboard said)
+        .add_system_to_stage(
+            CoreStage::PreUpdate,
+            drive_demo_player
+                .after(InputSystem)
+                .after(GameSystem::CollectInput),
+        );
+    }
+}
+
+// Timer used to start the demo after the start screen sits idle
+#[derive(Resource)]
+struct AttractTimer(Timer);
+
+// Timer used to end the demo and go back to the start screen
+#[derive(Resource)]
+struct DemoTimer(Timer);
+
+const ATTRACT_IDLE_TIME: f32 = 20.0; // seconds on the start screen without a key press
+const DEMO_TIME: f32 = 40.0; // seconds (including the intro)
+const DEMO_FIRE_TAP_TIME: f32 = 0.1; // seconds (fire is held for this long, then let go for this long)
+
+// The demo player lines up under the nearest enemy, close enough counts
+const DEMO_AIM_TOLERANCE: f32 = 8.0;
+// Enemy shots closer than this (and above the ship) get dodged
+const DEMO_DODGE_DISTANCE: Vec2 = Vec2::new(40.0, 200.0);
+
+// Run the demo once nobody has touched anything on the start screen for a while
+fn start_demo(
+    time: Res<Time>,
+    game_state: Res<GameState>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut transition: ResMut<Transition>,
+    mut attract_timer: ResMut<AttractTimer>,
+    mut demo_timer: ResMut<DemoTimer>,
+) {
+    let on_start_screen = !game_state.started
+        && !game_state.continue_offer
+        && !game_state.game_over
+        && !game_state.name_entry
+        && !game_state.showing_leaderboard
+        && !game_state.loading
+        && !transition.is_active();
+    if !on_start_screen || keyboard_input.get_just_pressed().next().is_some() {
+        attract_timer.0.reset();
+        return;
+    }
+
+    if attract_timer.0.tick(frame_delta(&time)).just_finished() {
+        println!("[ATTRACT] Starting demo");
+        attract_timer.0.reset();
+        demo_timer.0.reset();
+        transition.start(TransitionAction::StartDemo);
+    }
+}
+
+// Any key press ends the demo, and so does running out of time or losing a ship
+fn end_demo(
+    time: Res<Time>,
+    game_state: Res<GameState>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut transition: ResMut<Transition>,
+    mut demo_timer: ResMut<DemoTimer>,
+    mut player_death_events: EventReader<PlayerDeathEvent>,
+) {
+    let player_died = player_death_events.iter().count() > 0;
+    if !game_state.demo || transition.is_active() {
+        return;
+    }
+
+    let key_pressed = keyboard_input.get_just_pressed().next().is_some();
+    let time_up = demo_timer.0.tick(frame_delta(&time)).finished();
+    if key_pressed || time_up || player_died {
+        println!("[ATTRACT] Ending demo");
+        transition.start(TransitionAction::EndDemo);
+    }
+}
+
+// Play for the player during the demo: line up under the nearest enemy and keep firing,
+// sidestepping any enemy shots coming down on the ship
+fn drive_demo_player(
+    time: Res<Time>,
+    game_state: Res<GameState>,
+    mut player_input: ResMut<PlayerInput>,
+    player_query: Query<&Transform, (With<Player>, Without<Respawning>)>,
+    enemy_query: Query<&Transform, (With<Enemy>, With<Collider>)>,
+    enemy_projectile_query: Query<&Transform, (With<EnemyProjectile>, Without<Pooled>)>,
+) {
+    if !game_state.demo {
+        return;
+    }
+    // (the keyboard is only for leaving the demo)
+    player_input.direction = 0.0;
+    player_input.vertical = 0.0;
+    player_input.fire = false;
+
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+    let player_position = player_transform.translation.truncate();
+
+    // Get out of the way of the closest shot coming down on us
+    let incoming = enemy_projectile_query
+        .iter()
+        .map(|projectile_transform| projectile_transform.translation.truncate() - player_position)
+        .filter(|offset| {
+            offset.y > 0.0
+                && offset.x.abs() < DEMO_DODGE_DISTANCE.x
+                && offset.y < DEMO_DODGE_DISTANCE.y
+        })
+        .min_by(|a, b| a.y.total_cmp(&b.y));
+    if let Some(offset) = incoming {
+        player_input.direction = if offset.x > 0.0 { -1.0 } else { 1.0 };
+        return;
+    }
+
+    // Line up under the closest enemy
+    let target = enemy_query
+        .iter()
+        .map(|enemy_transform| enemy_transform.translation.x - player_position.x)
+        .min_by(|a, b| a.abs().total_cmp(&b.abs()));
+    let Some(target) = target else {
+        return;
+    };
+    if target.abs() > DEMO_AIM_TOLERANCE {
+        player_input.direction = target.signum();
+    }
+
+    // Tap fire (holding it would charge a shot instead)
+    player_input.fire = ((time.elapsed_seconds() / DEMO_FIRE_TAP_TIME) as usize).is_multiple_of(2);
+}
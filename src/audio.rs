@@ -0,0 +1,660 @@
+use std::collections::HashMap;
+
+use bevy::{audio::AudioSink, prelude::*};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    game::is_challenge_stage,
+    settings::{save_settings, GameSettingsState, KeyBindings, PauseMenu},
+    shared::*,
+};
+
+pub struct GameAudioPlugin;
+
+impl Plugin for GameAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MusicSink>()
+            .init_resource::<JingleSink>()
+            .init_resource::<SoundRng>()
+            .add_startup_system(setup_audio)
+            .add_system(copy_audio_fallbacks)
+            .add_system(adjust_volume.after(GameSystem::UpdatePauseMenu))
+            .add_system(update_music_volume.after(adjust_volume))
+            .add_system(fade_volume_indicator.after(adjust_volume))
+            .add_system(play_enemy_death_sounds.before(audio_playback))
+            .add_system(play_power_up_sounds.before(audio_playback))
+            .add_system(audio_playback.after(GameSystem::PlayIntro))
+            .add_system(control_music.after(audio_playback))
+            .add_system(play_level_music.after(GameSystem::CheckLevelComplete));
+    }
+}
+
+// Random numbers for sound pitch variation
+// (kept as a resource so it can be swapped for a seeded one when we need repeatable playback)
+#[derive(Resource)]
+struct SoundRng(StdRng);
+
+impl Default for SoundRng {
+    fn default() -> Self {
+        SoundRng(StdRng::from_entropy())
+    }
+}
+
+// Which volume setting a sound follows
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum AudioChannel {
+    Music,
+    // Short music cues that pause the background music while they play (follows the music volume)
+    Jingle,
+    Sfx,
+}
+
+struct SoundEffect {
+    handle: Handle<AudioSource>,
+    channel: AudioChannel,
+    // Multiplied with the channel volume
+    volume: f32,
+    // Base playback speed (lower sounds deeper)
+    speed: f32,
+    // Playback speed is randomly picked from 1.0 +/- this, so repeated sounds don't feel robotic
+    pitch_variation: f32,
+}
+
+// All the loaded sounds, looked up by SoundId
+#[derive(Resource)]
+struct SoundLibrary(HashMap<SoundId, SoundEffect>);
+
+// Background music that loops during gameplay, picked by level
+#[derive(Resource)]
+struct MusicLibrary {
+    gameplay: Handle<AudioSource>,
+    challenge: Handle<AudioSource>,
+}
+
+// The music currently playing, so we can change it's volume, pause, or stop it while it plays
+#[derive(Resource, Default)]
+struct MusicSink(Option<Handle<AudioSink>>);
+
+// The jingle currently playing, so we can pause or interrupt it
+#[derive(Resource, Default)]
+struct JingleSink(Option<Handle<AudioSink>>);
+
+// Temporary "VOLUME 40%" text shown after changing the volume
+#[derive(Component)]
+struct VolumeIndicatorText(Timer);
+
+const VOLUME_STEP: f32 = 0.1;
+const PLAYER_DEATH_SOUND_SPEED: f32 = 0.6;
+// Enemy shots are a lower, quieter version of the player's, so they don't drown it out
+const ENEMY_PROJECTILE_SOUND_SPEED: f32 = 0.75;
+const ENEMY_PROJECTILE_SOUND_VOLUME: f32 = 0.4;
+// A low, quiet whoosh as bullet time kicks in
+const BULLET_TIME_SOUND_SPEED: f32 = 0.4;
+const BULLET_TIME_SOUND_VOLUME: f32 = 0.3;
+// A tiny "tink" when shots cancel each other out
+const SHOT_CANCELLED_SOUND_SPEED: f32 = 2.5;
+const SHOT_CANCELLED_SOUND_VOLUME: f32 = 0.5;
+const POWER_UP_SOUND_SPEED: f32 = 1.8;
+const SHIELD_BREAK_SOUND_SPEED: f32 = 1.4;
+const COUNTDOWN_TICK_SOUND_SPEED: f32 = 0.5;
+const MAX_SAME_SOUNDS_PER_FRAME: usize = 3;
+const VOLUME_SLIDER_STEPS: usize = 10;
+const VOLUME_INDICATOR_TIME: f32 = 1.5; // seconds
+const VOLUME_INDICATOR_FADE_TIME: f32 = 0.5; // seconds (at the end of the indicator time)
+
+// The web build tries an .ogg version of every sound first (not every browser can decode mp3),
+// and falls back to the original file if there isn't one
+const PREFER_OGG: bool = cfg!(feature = "wasm");
+
+fn setup_audio(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut loading_assets: ResMut<LoadingAssets>,
+) {
+    // Load sound effects
+    let enemy_death_sound: Handle<AudioSource> =
+        load_audio(&mut loading_assets, &asset_server, "sounds/enemy-death.mp3");
+    let projectile_sound: Handle<AudioSource> =
+        load_audio(&mut loading_assets, &asset_server, "sounds/projectile.mp3");
+    commands.insert_resource(SoundLibrary(HashMap::from([
+        (
+            SoundId::ProjectileFired,
+            SoundEffect {
+                handle: projectile_sound.clone(),
+                channel: AudioChannel::Sfx,
+                volume: 1.0,
+                speed: 1.0,
+                pitch_variation: 0.05,
+            },
+        ),
+        (
+            SoundId::EnemyProjectileFired,
+            SoundEffect {
+                handle: projectile_sound.clone(),
+                channel: AudioChannel::Sfx,
+                volume: ENEMY_PROJECTILE_SOUND_VOLUME,
+                speed: ENEMY_PROJECTILE_SOUND_SPEED,
+                pitch_variation: 0.05,
+            },
+        ),
+        (
+            SoundId::EnemyDeath,
+            SoundEffect {
+                handle: enemy_death_sound.clone(),
+                channel: AudioChannel::Sfx,
+                volume: 1.0,
+                speed: 1.0,
+                pitch_variation: 0.1,
+            },
+        ),
+        // No dedicated clip yet, so we slow down the enemy death for something beefier
+        (
+            SoundId::PlayerDeath,
+            SoundEffect {
+                handle: enemy_death_sound.clone(),
+                channel: AudioChannel::Sfx,
+                volume: 1.0,
+                speed: PLAYER_DEATH_SOUND_SPEED,
+                pitch_variation: 0.0,
+            },
+        ),
+        // Same for power-ups - a sped up shot makes a nice chirp
+        (
+            SoundId::PowerUp,
+            SoundEffect {
+                handle: projectile_sound.clone(),
+                channel: AudioChannel::Sfx,
+                volume: 1.0,
+                speed: POWER_UP_SOUND_SPEED,
+                pitch_variation: 0.0,
+            },
+        ),
+        // And a slowed down shot for a low blip as the continue countdown ticks
+        (
+            SoundId::CountdownTick,
+            SoundEffect {
+                handle: projectile_sound.clone(),
+                channel: AudioChannel::Sfx,
+                volume: 1.0,
+                speed: COUNTDOWN_TICK_SOUND_SPEED,
+                pitch_variation: 0.0,
+            },
+        ),
+        (
+            SoundId::ShotCancelled,
+            SoundEffect {
+                handle: projectile_sound.clone(),
+                channel: AudioChannel::Sfx,
+                volume: SHOT_CANCELLED_SOUND_VOLUME,
+                speed: SHOT_CANCELLED_SOUND_SPEED,
+                pitch_variation: 0.1,
+            },
+        ),
+        (
+            SoundId::BulletTime,
+            SoundEffect {
+                handle: projectile_sound,
+                channel: AudioChannel::Sfx,
+                volume: BULLET_TIME_SOUND_VOLUME,
+                speed: BULLET_TIME_SOUND_SPEED,
+                pitch_variation: 0.0,
+            },
+        ),
+        (
+            SoundId::ShieldBreak,
+            SoundEffect {
+                handle: enemy_death_sound,
+                channel: AudioChannel::Sfx,
+                volume: 1.0,
+                speed: SHIELD_BREAK_SOUND_SPEED,
+                pitch_variation: 0.05,
+            },
+        ),
+        (
+            SoundId::Intro,
+            SoundEffect {
+                handle: load_audio(&mut loading_assets, &asset_server, "sounds/intro.mp3"),
+                channel: AudioChannel::Music,
+                volume: 1.0,
+                speed: 1.0,
+                pitch_variation: 0.0,
+            },
+        ),
+        (
+            SoundId::StageClear,
+            SoundEffect {
+                handle: load_audio(&mut loading_assets, &asset_server, "music/stage_clear.wav"),
+                channel: AudioChannel::Jingle,
+                volume: 1.0,
+                speed: 1.0,
+                pitch_variation: 0.0,
+            },
+        ),
+        (
+            SoundId::GameOver,
+            SoundEffect {
+                handle: load_audio(&mut loading_assets, &asset_server, "music/game_over.wav"),
+                channel: AudioChannel::Jingle,
+                volume: 1.0,
+                speed: 1.0,
+                pitch_variation: 0.0,
+            },
+        ),
+    ])));
+
+    // Load music
+    commands.insert_resource(MusicLibrary {
+        gameplay: load_audio(
+            &mut loading_assets,
+            &asset_server,
+            "music/gameplay_loop.wav",
+        ),
+        challenge: load_audio(
+            &mut loading_assets,
+            &asset_server,
+            "music/challenge_stage.wav",
+        ),
+    });
+}
+
+// Load a sound (trying the .ogg version first on the web)
+fn load_audio(
+    loading_assets: &mut LoadingAssets,
+    asset_server: &AssetServer,
+    path: &str,
+) -> Handle<AudioSource> {
+    let ogg_path = path
+        .rsplit_once('.')
+        .filter(|(_, extension)| *extension != "ogg")
+        .map(|(stem, _)| format!("{}.ogg", stem));
+    match ogg_path {
+        Some(ogg_path) if PREFER_OGG => {
+            loading_assets.load_with_fallback(asset_server, &ogg_path, path)
+        }
+        _ => loading_assets.load(asset_server, path),
+    }
+}
+
+// When a sound had to fall back to its original file, put it where the .ogg handle points
+// (that's the handle the sound libraries hold)
+fn copy_audio_fallbacks(
+    loading_assets: Res<LoadingAssets>,
+    mut audio_sources: ResMut<Assets<AudioSource>>,
+) {
+    for fallback in &loading_assets.fallbacks {
+        let Some(fallback_handle) = &fallback.handle else {
+            continue;
+        };
+        let original = Handle::<AudioSource>::weak(fallback.original);
+        if audio_sources.contains(&original) {
+            continue;
+        }
+        if let Some(audio_source) = audio_sources.get(&Handle::weak(fallback_handle.id)) {
+            let audio_source = audio_source.clone();
+            audio_sources.set_untracked(original, audio_source);
+        }
+    }
+}
+
+// One explosion sound per enemy destroyed (audio_playback keeps a big multi-kill from clipping)
+fn play_enemy_death_sounds(
+    mut enemy_death_events: EventReader<EnemyDeathEvent>,
+    mut sound_events: EventWriter<PlaySoundEvent>,
+) {
+    for event in enemy_death_events.iter() {
+        let sound = if event.shot_cancelled {
+            SoundId::ShotCancelled
+        } else {
+            SoundId::EnemyDeath
+        };
+        sound_events.send(PlaySoundEvent(sound));
+    }
+}
+
+// Chirp when the player grabs a power-up, and crack when their shield breaks
+fn play_power_up_sounds(
+    mut power_up_events: EventReader<PowerUpEvent>,
+    mut sound_events: EventWriter<PlaySoundEvent>,
+) {
+    for power_up_event in power_up_events.iter() {
+        match power_up_event {
+            PowerUpEvent::Collected(_) => sound_events.send(PlaySoundEvent(SoundId::PowerUp)),
+            PowerUpEvent::ShieldBroken => sound_events.send(PlaySoundEvent(SoundId::ShieldBreak)),
+            PowerUpEvent::Dropped(_) | PowerUpEvent::Missed(_) | PowerUpEvent::ShieldExpired => {}
+        }
+    }
+}
+
+// Plays every requested sound using the SoundLibrary and the player's volume settings
+fn audio_playback(
+    mut sound_events: EventReader<PlaySoundEvent>,
+    audio: Res<Audio>,
+    sound_library: Res<SoundLibrary>,
+    game_settings: Res<GameSettingsState>,
+    audio_sinks: Res<Assets<AudioSink>>,
+    mut music_sink: ResMut<MusicSink>,
+    mut jingle_sink: ResMut<JingleSink>,
+    mut sound_rng: ResMut<SoundRng>,
+) {
+    // How many times each sound has played this frame
+    let mut played: HashMap<SoundId, usize> = HashMap::new();
+
+    for PlaySoundEvent(sound_id) in sound_events.iter() {
+        if game_settings.muted {
+            continue;
+        }
+        let Some(sound) = sound_library.0.get(sound_id) else {
+            println!("[AUDIO] No sound loaded for {:?}", sound_id);
+            continue;
+        };
+
+        // Stacking too many copies of the same sound just clips (e.g. a lot of enemies dying at once)
+        let play_count = played.entry(*sound_id).or_default();
+        if *play_count >= MAX_SAME_SOUNDS_PER_FRAME {
+            continue;
+        }
+        *play_count += 1;
+        println!("[AUDIO] Playing {:?}", sound_id);
+
+        let channel_volume = match sound.channel {
+            AudioChannel::Music | AudioChannel::Jingle => game_settings.music_volume,
+            AudioChannel::Sfx => game_settings.sfx_volume,
+        };
+        let mut speed = sound.speed;
+        if sound.pitch_variation > 0.0 {
+            speed += sound_rng
+                .0
+                .gen_range(-sound.pitch_variation..=sound.pitch_variation);
+        }
+
+        let sink = audio.play_with_settings(
+            sound.handle.clone(),
+            PlaybackSettings::ONCE
+                .with_volume(channel_volume * sound.volume)
+                .with_speed(speed),
+        );
+
+        match sound.channel {
+            // Hold onto music so volume changes can affect it while it plays
+            AudioChannel::Music => {
+                // Only one song at a time (e.g. restarting the game before the intro finished)
+                if let Some(previous_sink) =
+                    music_sink.0.as_ref().and_then(|sink| audio_sinks.get(sink))
+                {
+                    previous_sink.stop();
+                }
+                music_sink.0 = Some(audio_sinks.get_handle(sink));
+            }
+            AudioChannel::Jingle => {
+                // Duck the background music (it picks back up when the next level starts)
+                if let Some(music) = music_sink.0.as_ref().and_then(|sink| audio_sinks.get(sink)) {
+                    music.pause();
+                }
+                // Newer jingle wins (e.g. game over during the stage clear jingle)
+                if let Some(previous_sink) = jingle_sink
+                    .0
+                    .as_ref()
+                    .and_then(|sink| audio_sinks.get(sink))
+                {
+                    previous_sink.stop();
+                }
+                jingle_sink.0 = Some(audio_sinks.get_handle(sink));
+            }
+            AudioChannel::Sfx => {}
+        }
+    }
+}
+
+// Pause the music along with the game, and stop it when the game ends
+fn control_music(
+    game_state: Res<GameState>,
+    mut music_sink: ResMut<MusicSink>,
+    jingle_sink: Res<JingleSink>,
+    audio_sinks: Res<Assets<AudioSink>>,
+    mut was_started: Local<bool>,
+) {
+    if !game_state.is_changed() {
+        return;
+    }
+    let game_over = *was_started && !game_state.started;
+    *was_started = game_state.started;
+
+    // Jingles pause with the game (the game over jingle keeps playing over the game over screen)
+    if let Some(jingle) = jingle_sink
+        .0
+        .as_ref()
+        .and_then(|sink| audio_sinks.get(sink))
+    {
+        if game_state.paused {
+            jingle.pause();
+        } else {
+            jingle.play();
+        }
+    }
+
+    let Some(sink_handle) = music_sink.0.clone() else {
+        return;
+    };
+    let Some(sink) = audio_sinks.get(&sink_handle) else {
+        return;
+    };
+
+    if game_over {
+        sink.stop();
+        music_sink.0 = None;
+    } else if game_state.paused {
+        sink.pause();
+    } else if !game_state.stage_clear {
+        // (music stays ducked while the stage clear jingle plays)
+        sink.play();
+    }
+}
+
+// Loop the background music when a level starts, switching tracks for challenge stages.
+// Pausing, stopping on game over, and volume are handled through the MusicSink like any other music.
+fn play_level_music(
+    mut new_level_events: EventReader<NewLevelEvent>,
+    game_state: Res<GameState>,
+    game_settings: Res<GameSettingsState>,
+    music_library: Res<MusicLibrary>,
+    audio: Res<Audio>,
+    audio_sinks: Res<Assets<AudioSink>>,
+    mut music_sink: ResMut<MusicSink>,
+    mut now_playing: Local<Option<(Handle<AudioSource>, Handle<AudioSink>)>>,
+) {
+    if new_level_events.iter().count() == 0 {
+        return;
+    }
+
+    let track = if is_challenge_stage(game_state.level) {
+        &music_library.challenge
+    } else {
+        &music_library.gameplay
+    };
+
+    // Same track as last level? Let it keep looping (as long as nothing else took over the music)
+    if let Some((playing_track, playing_sink)) = now_playing.as_ref() {
+        if playing_track == track && music_sink.0.as_ref() == Some(playing_sink) {
+            // Pick back up where it left off (it's paused during the stage clear jingle)
+            if let Some(sink) = audio_sinks.get(playing_sink) {
+                sink.play();
+            }
+            return;
+        }
+    }
+
+    if let Some(previous_sink) = music_sink.0.as_ref().and_then(|sink| audio_sinks.get(sink)) {
+        previous_sink.stop();
+    }
+
+    // Start muted tracks silently, so unmuting brings the music back
+    let volume = if game_settings.muted {
+        0.0
+    } else {
+        game_settings.music_volume
+    };
+    let sink = audio_sinks.get_handle(
+        audio.play_with_settings(track.clone(), PlaybackSettings::LOOP.with_volume(volume)),
+    );
+    println!("[AUDIO] Playing music for level {}", game_state.level);
+
+    music_sink.0 = Some(sink.clone());
+    *now_playing = Some((track.clone(), sink));
+}
+
+// Keep the music that's currently playing in sync with the volume settings
+fn update_music_volume(
+    game_settings: Res<GameSettingsState>,
+    music_sink: Res<MusicSink>,
+    audio_sinks: Res<Assets<AudioSink>>,
+) {
+    if !game_settings.is_changed() {
+        return;
+    }
+
+    if let Some(sink) = music_sink.0.as_ref().and_then(|sink| audio_sinks.get(sink)) {
+        if game_settings.muted {
+            sink.set_volume(0.0);
+        } else {
+            sink.set_volume(game_settings.music_volume);
+        }
+    }
+}
+
+// Volume up/down and mute keys
+fn adjust_volume(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    pause_menu: Res<PauseMenu>,
+    game_fonts: Res<GameFonts>,
+    mut game_settings: ResMut<GameSettingsState>,
+    mut query: Query<(&mut Text, &mut VolumeIndicatorText)>,
+) {
+    // Don't steal the key press if the player is rebinding a key
+    if pause_menu.listening {
+        return;
+    }
+
+    // These change music and sound effects together (use the pause menu to set them separately)
+    let mut steps = 0.0;
+    if keyboard_input.just_pressed(KeyCode::Minus) {
+        steps -= 1.0;
+    }
+    if keyboard_input.just_pressed(KeyCode::Equals) {
+        steps += 1.0;
+    }
+
+    let mute_toggled = keyboard_input.just_pressed(KeyCode::M);
+    if steps == 0.0 && !mute_toggled {
+        return;
+    }
+
+    game_settings.music_volume = step_volume(game_settings.music_volume, steps);
+    game_settings.sfx_volume = step_volume(game_settings.sfx_volume, steps);
+    if mute_toggled {
+        game_settings.muted = !game_settings.muted;
+    }
+    save_settings(&key_bindings, &game_settings);
+
+    let indicator_text = if game_settings.muted {
+        "MUTED".to_string()
+    } else {
+        format!(
+            "MUSIC {}%  SFX {}%",
+            (game_settings.music_volume * 100.0).round(),
+            (game_settings.sfx_volume * 100.0).round()
+        )
+    };
+
+    // Reuse the indicator if it's still on screen
+    if let Ok((mut text, mut indicator)) = query.get_single_mut() {
+        text.sections[0].value = indicator_text;
+        indicator.0.reset();
+        return;
+    }
+
+    commands.spawn((
+        TextBundle::from_section(
+            indicator_text,
+            TextStyle {
+                font: game_fonts.body.clone(),
+                font_size: UI_FONT_MEDIUM,
+                color: UI_COLOR_WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: UI_PADDING_CENTER_TOP,
+                right: UI_PADDING_CENTER_TOP,
+                ..default()
+            },
+            ..default()
+        }),
+        VolumeIndicatorText(Timer::from_seconds(VOLUME_INDICATOR_TIME, TimerMode::Once)),
+    ));
+}
+
+// Move the volume up or down a number of steps, staying between 0 and 1
+pub fn step_volume(volume: f32, steps: f32) -> f32 {
+    // Round to the nearest step so we don't drift (e.g. 0.30000004)
+    (((volume / VOLUME_STEP).round() + steps) * VOLUME_STEP).clamp(0.0, 1.0)
+}
+
+// Text based slider for volume settings (e.g. "[####------] 40%")
+pub fn volume_slider(volume: f32) -> String {
+    let filled = (volume / VOLUME_STEP).round() as usize;
+    format!(
+        "[{}{}] {}%",
+        "#".repeat(filled),
+        "-".repeat(VOLUME_SLIDER_STEPS.saturating_sub(filled)),
+        (volume * 100.0).round()
+    )
+}
+
+// Fade out the volume indicator and remove it when it's done
+fn fade_volume_indicator(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut query: Query<(Entity, &mut Text, &mut VolumeIndicatorText)>,
+) {
+    for (indicator_entity, mut text, mut indicator) in &mut query {
+        if indicator.0.tick(frame_delta(&time)).finished() {
+            commands.entity(indicator_entity).despawn();
+            continue;
+        }
+
+        let remaining = indicator.0.duration().as_secs_f32() - indicator.0.elapsed_secs();
+        let alpha = (remaining / VOLUME_INDICATOR_FADE_TIME).min(1.0);
+        for section in &mut text.sections {
+            section.style.color.set_a(alpha);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_enemy_death_plays_a_sound() {
+        let mut app = App::new();
+        app.add_event::<EnemyDeathEvent>()
+            .add_event::<PlaySoundEvent>()
+            .add_system(play_enemy_death_sounds);
+
+        for _ in 0..3 {
+            app.world.send_event(EnemyDeathEvent::default());
+        }
+        app.update();
+
+        let sound_events = app.world.resource::<Events<PlaySoundEvent>>();
+        let sounds: Vec<SoundId> = sound_events
+            .get_reader()
+            .iter(sound_events)
+            .map(|PlaySoundEvent(sound_id)| *sound_id)
+            .collect();
+        assert_eq!(sounds, [SoundId::EnemyDeath; 3]);
+    }
+}
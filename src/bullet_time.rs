@@ -0,0 +1,156 @@
+use bevy::prelude::*;
+
+use crate::{settings::GameSettingsState, shared::*};
+
+// Slow motion for a moment when an enemy shot only just misses the player (see check_for_collisions).
+// The game drops to BULLET_TIME_SCALE speed and eases back to normal, with the edges of the screen darkened.
+// Can be turned off in the settings.
+pub struct BulletTimePlugin;
+
+impl Plugin for BulletTimePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BulletTime>()
+            .add_startup_system(setup_vignette)
+            .add_system(start_bullet_time.after(GameSystem::Collision))
+            .add_system(update_time_scale.after(start_bullet_time))
+            .add_system(update_vignette.after(update_time_scale));
+    }
+}
+
+// How slow the game gets (times normal speed)
+const BULLET_TIME_SCALE: f32 = 0.3;
+const BULLET_TIME_DURATION: f32 = 0.8; // seconds (real time, not slowed down)
+const VIGNETTE_SIZE: f32 = 8.0; // percent of the screen, in from each edge
+const VIGNETTE_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.6);
+
+// Counts down the slowdown (finished = running at normal speed)
+#[derive(Resource)]
+struct BulletTime(Timer);
+
+impl Default for BulletTime {
+    fn default() -> Self {
+        let mut timer = Timer::from_seconds(BULLET_TIME_DURATION, TimerMode::Once);
+        timer.tick(timer.duration());
+        BulletTime(timer)
+    }
+}
+
+// The darkened edges of the screen during bullet time
+#[derive(Component)]
+struct Vignette;
+
+fn setup_vignette(mut commands: Commands) {
+    let edges = [
+        (
+            UiRect {
+                top: Val::Px(0.0),
+                left: Val::Px(0.0),
+                ..default()
+            },
+            Size::new(Val::Percent(100.0), Val::Percent(VIGNETTE_SIZE)),
+        ),
+        (
+            UiRect {
+                bottom: Val::Px(0.0),
+                left: Val::Px(0.0),
+                ..default()
+            },
+            Size::new(Val::Percent(100.0), Val::Percent(VIGNETTE_SIZE)),
+        ),
+        (
+            UiRect {
+                top: Val::Px(0.0),
+                left: Val::Px(0.0),
+                ..default()
+            },
+            Size::new(Val::Percent(VIGNETTE_SIZE), Val::Percent(100.0)),
+        ),
+        (
+            UiRect {
+                top: Val::Px(0.0),
+                right: Val::Px(0.0),
+                ..default()
+            },
+            Size::new(Val::Percent(VIGNETTE_SIZE), Val::Percent(100.0)),
+        ),
+    ];
+    for (position, size) in edges {
+        commands.spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position,
+                    size,
+                    ..default()
+                },
+                background_color: Color::NONE.into(),
+                ..default()
+            },
+            Vignette,
+        ));
+    }
+}
+
+// Slow things down on a near miss (unless we're already slowed down)
+fn start_bullet_time(
+    mut near_miss_events: EventReader<NearMissEvent>,
+    game_settings: Res<GameSettingsState>,
+    game_state: Res<GameState>,
+    mut bullet_time: ResMut<BulletTime>,
+    mut sound_events: EventWriter<PlaySoundEvent>,
+) {
+    if near_miss_events.iter().count() == 0
+        || !game_settings.bullet_time
+        || game_state.demo
+        || !bullet_time.0.finished()
+    {
+        return;
+    }
+
+    println!("[BULLET TIME] Near miss!");
+    bullet_time.0.reset();
+    sound_events.send(PlaySoundEvent(SoundId::BulletTime));
+}
+
+// Ease the TimeScale from BULLET_TIME_SCALE back up to normal speed
+fn update_time_scale(
+    time: Res<Time>,
+    game_state: Res<GameState>,
+    mut bullet_time: ResMut<BulletTime>,
+    mut time_scale: ResMut<TimeScale>,
+) {
+    // (a game ending mid-slowdown doesn't carry it over to the next one)
+    if !game_state.started {
+        let duration = bullet_time.0.duration();
+        bullet_time.0.tick(duration);
+    }
+    if !game_state.paused {
+        bullet_time.0.tick(frame_delta(&time));
+    }
+
+    let scale = if bullet_time.0.finished() {
+        1.0
+    } else {
+        BULLET_TIME_SCALE + (1.0 - BULLET_TIME_SCALE) * bullet_time.0.percent()
+    };
+    if time_scale.0 != scale {
+        time_scale.0 = scale;
+    }
+}
+
+// Darken the edges of the screen more the slower the game's running
+fn update_vignette(
+    time_scale: Res<TimeScale>,
+    mut query: Query<&mut BackgroundColor, With<Vignette>>,
+) {
+    if !time_scale.is_changed() {
+        return;
+    }
+
+    let strength = (1.0 - time_scale.0) / (1.0 - BULLET_TIME_SCALE);
+    let mut color = VIGNETTE_COLOR;
+    color.set_a(VIGNETTE_COLOR.a() * strength);
+    for mut background_color in &mut query {
+        background_color.0 = color;
+    }
+}
@@ -0,0 +1,235 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    sprite::{collide_aabb::collide, Anchor, MaterialMesh2dBundle},
+    time::FixedTimesteps,
+};
+use rand::seq::IteratorRandom;
+
+use crate::shared::*;
+
+pub struct CapturePlugin;
+
+impl Plugin for CapturePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TractorBeamTimer(Timer::from_seconds(
+            TRACTOR_BEAM_INTERVAL,
+            TimerMode::Repeating,
+        )))
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(FixedStep)
+                .with_system(start_tractor_beam.before(GameSystem::Collision))
+                .with_system(update_tractor_beams.before(GameSystem::Collision))
+                .with_system(move_captured_fighters.before(GameSystem::Collision)),
+        );
+    }
+}
+
+// Timer used to space out bosses trying to capture the player
+#[derive(Resource)]
+struct TractorBeamTimer(Timer);
+
+const TRACTOR_BEAM_INTERVAL: f32 = 8.0; // seconds between attempts
+const TRACTOR_BEAM_TIME: f32 = 3.0; // seconds the beam stays on
+const TRACTOR_BEAM_GROW_TIME: f32 = 1.0; // seconds to reach all the way down
+const TRACTOR_BEAM_WIDTH: f32 = 40.0;
+const TRACTOR_BEAM_COLOR: Color = Color::rgba(0.4, 0.6, 1.0, 0.35);
+// Captured ships sit just above the boss holding them
+const CAPTURED_FIGHTER_OFFSET: f32 = 20.0;
+const CAPTURED_FIGHTER_SPEED: f32 = 150.0;
+const RESCUED_FIGHTER_SPEED: f32 = 300.0;
+
+// Every so often a boss turns on its tractor beam
+// (one at a time, not while it's diving, and not while it's already holding a ship or the player is flying two)
+fn start_tractor_beam(
+    mut commands: Commands,
+    fixed_timesteps: Res<FixedTimesteps>,
+    game_state: Res<GameState>,
+    mut game_rng: ResMut<GameRng>,
+    mut tractor_beam_timer: ResMut<TractorBeamTimer>,
+    boss_query: Query<Entity, (With<Boss>, Without<Sortie>)>,
+    player_query: Query<(), (With<Player>, Without<Respawning>, Without<Invincible>)>,
+    tractor_beam_query: Query<(), With<TractorBeam>>,
+    fighter_query: Query<(), Or<(With<CapturedFighter>, With<DockedFighter>)>>,
+) {
+    if !game_state.started || game_state.intro {
+        return;
+    }
+    if !tractor_beam_timer
+        .0
+        .tick(Duration::from_secs_f32(fixed_step_seconds(
+            &fixed_timesteps,
+        )))
+        .just_finished()
+    {
+        return;
+    }
+    if player_query.is_empty() || !tractor_beam_query.is_empty() || !fighter_query.is_empty() {
+        return;
+    }
+    let Some(boss_entity) = boss_query.iter().choose(&mut *game_rng) else {
+        return;
+    };
+
+    println!("[CAPTURE] Boss turned on its tractor beam");
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: TRACTOR_BEAM_COLOR,
+                custom_size: Some(Vec2::new(TRACTOR_BEAM_WIDTH, 0.0)),
+                anchor: Anchor::TopCenter,
+                ..default()
+            },
+            ..default()
+        },
+        TractorBeam {
+            boss: boss_entity,
+            timer: Timer::from_seconds(TRACTOR_BEAM_TIME, TimerMode::Once),
+        },
+    ));
+}
+
+// Stretch each beam down from its boss, catching the player if they fly into it
+fn update_tractor_beams(
+    mut commands: Commands,
+    fixed_timesteps: Res<FixedTimesteps>,
+    playfield: Res<Playfield>,
+    shared_assets: Res<SharedAssets>,
+    mut beam_query: Query<(Entity, &mut TractorBeam, &mut Transform, &mut Sprite)>,
+    boss_query: Query<&Transform, (With<Boss>, Without<TractorBeam>)>,
+    player_query: Query<
+        &Transform,
+        (
+            With<Player>,
+            Without<Respawning>,
+            Without<Invincible>,
+            Without<TractorBeam>,
+            Without<Boss>,
+        ),
+    >,
+    mut player_death_events: EventWriter<PlayerDeathEvent>,
+) {
+    for (beam_entity, mut beam, mut beam_transform, mut sprite) in &mut beam_query {
+        beam.timer.tick(Duration::from_secs_f32(fixed_step_seconds(
+            &fixed_timesteps,
+        )));
+
+        // Boss was destroyed (or the beam ran out) - turn it off
+        let Ok(boss_transform) = boss_query.get(beam.boss) else {
+            commands.entity(beam_entity).despawn();
+            continue;
+        };
+        if beam.timer.finished() {
+            commands.entity(beam_entity).despawn();
+            continue;
+        }
+
+        // Grow down towards the bottom of the playfield
+        let full_length = boss_transform.translation.y + playfield.edge_vertical();
+        let grow = (beam.timer.elapsed_secs() / TRACTOR_BEAM_GROW_TIME).min(1.0);
+        let length = full_length * grow;
+        beam_transform.translation = boss_transform.translation - Vec3::new(0.0, 0.0, 0.5);
+        sprite.custom_size = Some(Vec2::new(TRACTOR_BEAM_WIDTH, length));
+
+        let Ok(player_transform) = player_query.get_single() else {
+            continue;
+        };
+        let beam_center = boss_transform.translation - Vec3::new(0.0, length / 2.0, 0.0);
+        let caught = collide(
+            beam_center,
+            Vec2::new(TRACTOR_BEAM_WIDTH, length),
+            player_transform.translation,
+            player_transform.scale.truncate(),
+        );
+        if caught.is_none() {
+            continue;
+        }
+
+        // Got them! Lose a life and carry the ship up to the boss
+        println!("[CAPTURE] Player's ship was captured");
+        commands.entity(beam_entity).despawn();
+        player_death_events.send(PlayerDeathEvent { captured: true });
+        commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: shared_assets.quad.clone().into(),
+                transform: Transform {
+                    translation: player_transform.translation,
+                    scale: PLAYER_SIZE,
+                    ..default()
+                },
+                material: shared_assets.player.clone(),
+                ..default()
+            },
+            CapturedFighter {
+                boss: beam.boss,
+                rescued: false,
+            },
+        ));
+    }
+}
+
+// Pull captured ships up to their boss.
+// Once the boss is destroyed the ship is free, and flies down to dock alongside the player.
+fn move_captured_fighters(
+    mut commands: Commands,
+    fixed_timesteps: Res<FixedTimesteps>,
+    shared_assets: Res<SharedAssets>,
+    mut fighter_query: Query<(Entity, &mut CapturedFighter, &mut Transform)>,
+    boss_query: Query<&Transform, (With<Boss>, Without<CapturedFighter>)>,
+    player_query: Query<
+        (Entity, &Transform),
+        (With<Player>, Without<Respawning>, Without<CapturedFighter>),
+    >,
+) {
+    for (fighter_entity, mut fighter, mut fighter_transform) in &mut fighter_query {
+        if !fighter.rescued {
+            let Ok(boss_transform) = boss_query.get(fighter.boss) else {
+                println!("[CAPTURE] Captured ship was rescued");
+                fighter.rescued = true;
+                continue;
+            };
+            let target = boss_transform.translation + Vec3::new(0.0, CAPTURED_FIGHTER_OFFSET, 0.0);
+            move_towards(
+                &mut fighter_transform.translation,
+                target,
+                CAPTURED_FIGHTER_SPEED * fixed_step_seconds(&fixed_timesteps),
+            );
+            continue;
+        }
+
+        // Wait for the player to come back if they're respawning
+        let Ok((player_entity, player_transform)) = player_query.get_single() else {
+            continue;
+        };
+        let target = player_transform.translation + Vec3::new(DUAL_FIGHTER_GAP, 0.0, 0.0);
+        let arrived = move_towards(
+            &mut fighter_transform.translation,
+            target,
+            RESCUED_FIGHTER_SPEED * fixed_step_seconds(&fixed_timesteps),
+        );
+        if !arrived {
+            continue;
+        }
+
+        // Docked - from now on it moves with the player's ship
+        // (it's a child of the player, so the position is in the player's scaled space)
+        println!("[CAPTURE] Rescued ship docked");
+        commands.entity(fighter_entity).despawn();
+        commands
+            .entity(player_entity)
+            .insert(DualFighter)
+            .with_children(|parent| {
+                parent.spawn((
+                    MaterialMesh2dBundle {
+                        mesh: shared_assets.quad.clone().into(),
+                        transform: Transform::from_xyz(DUAL_FIGHTER_GAP / PLAYER_SIZE.x, 0.0, 0.0),
+                        material: shared_assets.player.clone(),
+                        ..default()
+                    },
+                    DockedFighter,
+                ));
+            });
+    }
+}
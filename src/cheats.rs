@@ -0,0 +1,185 @@
+use bevy::prelude::*;
+
+use crate::{collision::destroy_enemy, enemy::spawn_enemy, shared::*, wave::EnemyKind};
+
+// Developer cheats for testing (only built with `--features debug-tools`).
+// Hold Ctrl and press:
+// F1 - Toggle invincibility
+// F2 - Destroy every enemy on screen (they score like normal)
+// F3 - Extra life
+// F4 - Skip to the next level
+// F5 - Spawn a green bug at the mouse cursor (hold Shift for a boss)
+pub struct CheatsPlugin;
+
+impl Plugin for CheatsPlugin {
+    fn build(&self, app: &mut App) {
+        // (after setup_ui has loaded the fonts)
+        app.add_startup_system_to_stage(StartupStage::PostStartup, setup_cheats_overlay)
+            .add_system(use_cheats)
+            .add_system(update_cheats_overlay.after(use_cheats));
+    }
+}
+
+// Lists the cheats in use, so screenshots from testing can't pass as real games
+#[derive(Component)]
+struct CheatsText;
+
+const CHEAT_INVINCIBLE_KEY: KeyCode = KeyCode::F1;
+const CHEAT_DESTROY_ENEMIES_KEY: KeyCode = KeyCode::F2;
+const CHEAT_EXTRA_LIFE_KEY: KeyCode = KeyCode::F3;
+const CHEAT_NEXT_LEVEL_KEY: KeyCode = KeyCode::F4;
+const CHEAT_SPAWN_ENEMY_KEY: KeyCode = KeyCode::F5;
+
+fn setup_cheats_overlay(mut commands: Commands, game_fonts: Res<GameFonts>) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: UI_PADDING_CENTER_TOP,
+                    left: Val::Px(0.0),
+                    ..default()
+                },
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: game_fonts.body.clone(),
+                        font_size: UI_FONT_SMALL,
+                        color: UI_COLOR_RED,
+                    },
+                ),
+                CheatsText,
+            ));
+        });
+}
+
+fn use_cheats(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    windows: Res<Windows>,
+    game_state: Res<GameState>,
+    game_config: Res<GameConfig>,
+    textures: Res<Textures>,
+    mut cheats: ResMut<Cheats>,
+    mut players: ResMut<Players>,
+    mut death_events: EventWriter<EnemyDeathEvent>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    enemy_query: Query<(Entity, &Transform, &EnemyKind, Option<&Boss>), With<Enemy>>,
+    on_screen_query: Query<(), With<Collider>>,
+) {
+    if !keyboard_input.any_pressed([KeyCode::LControl, KeyCode::RControl])
+        || !game_state.started
+        || game_state.demo
+    {
+        return;
+    }
+
+    if keyboard_input.just_pressed(CHEAT_INVINCIBLE_KEY) {
+        cheats.invincible = !cheats.invincible;
+        cheats.used = true;
+        println!("[CHEATS] Invincible: {}", cheats.invincible);
+    }
+
+    // (enemies still waiting to enter aren't on screen yet)
+    if keyboard_input.just_pressed(CHEAT_DESTROY_ENEMIES_KEY) {
+        cheats.used = true;
+        println!("[CHEATS] Destroying every enemy on screen");
+        for (enemy_entity, enemy_transform, kind, boss) in &enemy_query {
+            if !on_screen_query.contains(enemy_entity) {
+                continue;
+            }
+            let points = if boss.is_some() {
+                game_config.boss_points
+            } else {
+                game_config.enemy_points
+            };
+            // (no splitting, so the screen actually clears)
+            death_events.send(EnemyDeathEvent {
+                points,
+                kind: *kind,
+                translation: enemy_transform.translation,
+                splits: None,
+                ..default()
+            });
+            destroy_enemy(
+                &mut commands,
+                &textures,
+                enemy_entity,
+                enemy_transform.translation,
+                boss.is_some(),
+            );
+        }
+    }
+
+    if keyboard_input.just_pressed(CHEAT_EXTRA_LIFE_KEY) {
+        cheats.used = true;
+        players.current_mut().lives += 1;
+        println!("[CHEATS] Extra life, {} lives", players.current().lives);
+    }
+
+    // Clearing out the enemies (without scoring) lets the level end like normal
+    if keyboard_input.just_pressed(CHEAT_NEXT_LEVEL_KEY) {
+        cheats.used = true;
+        println!("[CHEATS] Skipping level {}", game_state.level);
+        for (enemy_entity, _, _, _) in &enemy_query {
+            commands.entity(enemy_entity).despawn();
+        }
+    }
+
+    if keyboard_input.just_pressed(CHEAT_SPAWN_ENEMY_KEY) {
+        let Some(cursor_position) = windows
+            .get_primary()
+            .and_then(|window| window.cursor_position())
+        else {
+            return;
+        };
+        let Some(cursor_translation) =
+            camera_query.iter().find_map(|(camera, camera_transform)| {
+                camera.viewport_to_world(camera_transform, cursor_position)
+            })
+        else {
+            return;
+        };
+
+        let kind = if keyboard_input.any_pressed([KeyCode::LShift, KeyCode::RShift]) {
+            EnemyKind::Boss
+        } else {
+            EnemyKind::GreenBug
+        };
+        cheats.used = true;
+        println!("[CHEATS] Spawned a {:?}", kind);
+        // (it just sits there, to line up shots against its hitbox)
+        spawn_enemy(
+            &mut commands,
+            &textures,
+            kind,
+            cursor_translation.origin.truncate().extend(0.0),
+        )
+        .insert(Collider);
+    }
+}
+
+fn update_cheats_overlay(cheats: Res<Cheats>, mut query: Query<&mut Text, With<CheatsText>>) {
+    if !cheats.is_changed() {
+        return;
+    }
+
+    let text = if cheats.invincible {
+        "CHEATS: INVINCIBLE"
+    } else if cheats.used {
+        "CHEATS USED"
+    } else {
+        ""
+    };
+    for mut cheats_text in &mut query {
+        cheats_text.sections[0].value = text.to_string();
+    }
+}
@@ -0,0 +1,168 @@
+use std::path::PathBuf;
+
+use bevy::{prelude::*, window::WindowMode};
+
+// Options passed on the command line, e.g. `cargo run -- --window 800x600 --level 5 --mute`
+#[derive(Resource, Default, Clone)]
+pub struct LaunchOptions {
+    pub width: Option<f32>,
+    pub height: Option<f32>,
+    pub fullscreen: bool,
+    // Replays the same enemy fire, dives and drops every game
+    pub seed: Option<u64>,
+    // Jump straight into a game at this level (skips the start screen and the intro)
+    pub level: Option<usize>,
+    // Sets the music and sound effect volume (0.0 - 1.0)
+    pub volume: Option<f32>,
+    pub mute: bool,
+    // Go straight to the first level when a game starts
+    pub skip_intro: bool,
+    // Run the game without a window, rendering or audio.
+    // A game starts right away (at `--level`, or level 1) and the app quits at game over.
+    pub headless: bool,
+    // Save every game's input to this file so it can be played back with `--replay`
+    pub record: Option<PathBuf>,
+    // Play back a recorded game instead of reading the keyboard
+    pub replay: Option<PathBuf>,
+}
+
+// The web build draws into this canvas on the page, sized to fit whatever holds it
+const WEB_BUILD: bool = cfg!(feature = "wasm");
+const WEB_CANVAS: &str = "#bevy";
+
+const USAGE: &str = "Usage: bevy-galaga [--window <width>x<height>] [--width <pixels>] [--height <pixels>] [--fullscreen]
+                   [--level <number>] [--skip-intro] [--volume <0.0 - 1.0>] [--mute] [--seed <number>]
+                   [--headless] [--record <file>] [--replay <file>]";
+
+impl LaunchOptions {
+    // Read the options the game was started with.
+    // Anything we don't understand prints the usage and quits.
+    pub fn from_env() -> Self {
+        match Self::parse(std::env::args().skip(1)) {
+            Ok(options) => options,
+            Err(error) => {
+                println!("[CLI] {}", error);
+                println!("{}", USAGE);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = LaunchOptions::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--width" => options.width = Some(parse_size(&arg, args.next())?),
+                "--height" => options.height = Some(parse_size(&arg, args.next())?),
+                "--window" => {
+                    let (width, height) = parse_window(&arg, args.next())?;
+                    options.width = Some(width);
+                    options.height = Some(height);
+                }
+                "--fullscreen" => options.fullscreen = true,
+                "--seed" => options.seed = Some(parse_seed(&arg, args.next())?),
+                "--level" => {
+                    options.level = Some(parse_level(&arg, args.next())?);
+                    options.skip_intro = true;
+                }
+                "--volume" => options.volume = Some(parse_volume(&arg, args.next())?),
+                "--mute" => options.mute = true,
+                "--skip-intro" => options.skip_intro = true,
+                "--headless" => options.headless = true,
+                "--record" => options.record = Some(parse_path(&arg, args.next())?),
+                "--replay" => options.replay = Some(parse_path(&arg, args.next())?),
+                "--help" | "-h" => {
+                    println!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                _ => return Err(format!("Unknown argument {}", arg)),
+            }
+        }
+
+        if options.record.is_some() && options.replay.is_some() {
+            return Err("Can't --record and --replay at the same time".to_string());
+        }
+
+        // Nobody's there to press start
+        if options.headless && options.level.is_none() {
+            options.level = Some(1);
+        }
+
+        Ok(options)
+    }
+
+    // The window the game opens with (anything not passed in keeps Bevy's default)
+    pub fn window_plugin(&self) -> WindowPlugin {
+        let defaults = WindowDescriptor::default();
+        WindowPlugin {
+            window: WindowDescriptor {
+                width: self.width.unwrap_or(defaults.width),
+                height: self.height.unwrap_or(defaults.height),
+                mode: if self.fullscreen {
+                    WindowMode::BorderlessFullscreen
+                } else {
+                    WindowMode::Windowed
+                },
+                canvas: WEB_BUILD.then(|| WEB_CANVAS.to_string()),
+                fit_canvas_to_parent: WEB_BUILD,
+                ..defaults
+            },
+            ..default()
+        }
+    }
+}
+
+// A window dimension in pixels
+fn parse_size(flag: &str, value: Option<String>) -> Result<f32, String> {
+    let value = value.ok_or_else(|| format!("{} needs a size", flag))?;
+    match value.parse::<f32>() {
+        Ok(size) if size > 0.0 => Ok(size),
+        _ => Err(format!("{} {} isn't a valid size", flag, value)),
+    }
+}
+
+// Both window dimensions at once, e.g. "800x600"
+fn parse_window(flag: &str, value: Option<String>) -> Result<(f32, f32), String> {
+    let value = value.ok_or_else(|| format!("{} needs a size like 800x600", flag))?;
+    let Some((width, height)) = value.split_once('x') else {
+        return Err(format!(
+            "{} {} isn't a valid size (try 800x600)",
+            flag, value
+        ));
+    };
+    Ok((
+        parse_size(flag, Some(width.to_string()))?,
+        parse_size(flag, Some(height.to_string()))?,
+    ))
+}
+
+fn parse_path(flag: &str, value: Option<String>) -> Result<PathBuf, String> {
+    value
+        .map(PathBuf::from)
+        .ok_or_else(|| format!("{} needs a file", flag))
+}
+
+fn parse_seed(flag: &str, value: Option<String>) -> Result<u64, String> {
+    let value = value.ok_or_else(|| format!("{} needs a number", flag))?;
+    value
+        .parse::<u64>()
+        .map_err(|_| format!("{} {} isn't a valid seed", flag, value))
+}
+
+// Levels start at 1
+fn parse_level(flag: &str, value: Option<String>) -> Result<usize, String> {
+    let value = value.ok_or_else(|| format!("{} needs a number", flag))?;
+    match value.parse::<usize>() {
+        Ok(level) if level >= 1 => Ok(level),
+        _ => Err(format!("{} {} isn't a valid level", flag, value)),
+    }
+}
+
+fn parse_volume(flag: &str, value: Option<String>) -> Result<f32, String> {
+    let value = value.ok_or_else(|| format!("{} needs a volume", flag))?;
+    match value.parse::<f32>() {
+        Ok(volume) if (0.0..=1.0).contains(&volume) => Ok(volume),
+        _ => Err(format!("{} {} isn't between 0.0 and 1.0", flag, value)),
+    }
+}
@@ -0,0 +1,649 @@
+use std::collections::HashMap;
+
+use bevy::{prelude::*, sprite::collide_aabb::collide};
+
+use crate::{
+    material::HitFlash,
+    powerup::{spawn_power_up, spawn_shield},
+    projectile::{Pooled, ProjectilePool},
+    settings::GameSettingsState,
+    shared::*,
+    wave::EnemyKind,
+};
+
+pub struct CollisionPlugin;
+
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .with_run_criteria(FixedStep)
+                .with_system(check_for_collisions.label(GameSystem::Collision)),
+        );
+    }
+}
+
+const SCORE_POPUP_LIFETIME: f32 = 0.75; // seconds
+const SCORE_POPUP_SPEED: f32 = 60.0;
+const DIVE_POINTS_MULTIPLIER: usize = 2;
+const BOSS_EXPLOSION_SCALE: f32 = 1.5;
+// Points for a diving boss, by how many escorts were still with it
+const ESCORT_BONUS: [usize; 2] = [800, 1600];
+const SCREEN_SHAKE_TRAUMA_PER_KILL: f32 = 0.3;
+const SCREEN_SHAKE_TRAUMA_PER_HIT: f32 = 0.1;
+// Enemies only collide with the player's ship below this height (the middle of the playfield)
+const RAMMING_HEIGHT_LIMIT: f32 = 0.0;
+// Needs to be bigger than an enemy plus a projectile, so checking the neighboring cells finds every hit
+const COLLISION_GRID_CELL_SIZE: f32 = 64.0;
+// An enemy projectile this close to the player's hitbox (without touching it) is a near miss
+const NEAR_MISS_DISTANCE: f32 = 8.0;
+
+// Enemies (or enemy projectiles) bucketed by screen region, so each projectile only tests what's near it
+// (instead of every projectile checking every enemy)
+#[derive(Default)]
+struct CollisionGrid(HashMap<IVec2, Vec<(Entity, Vec3, Vec2)>>);
+
+impl CollisionGrid {
+    fn cell(translation: Vec3) -> IVec2 {
+        (translation.truncate() / COLLISION_GRID_CELL_SIZE)
+            .floor()
+            .as_ivec2()
+    }
+
+    fn insert(&mut self, entity: Entity, translation: Vec3, size: Vec2) {
+        self.0
+            .entry(Self::cell(translation))
+            .or_default()
+            .push((entity, translation, size));
+    }
+
+    // Everything in the same cell as this position, or the cells around it
+    fn nearby(&self, translation: Vec3) -> impl Iterator<Item = &(Entity, Vec3, Vec2)> {
+        let center = Self::cell(translation);
+        (-1..=1)
+            .flat_map(move |x| (-1..=1).map(move |y| center + IVec2::new(x, y)))
+            .filter_map(|cell| self.0.get(&cell))
+            .flatten()
+    }
+
+    fn clear(&mut self) {
+        // Keep the buckets around so we aren't reallocating every step
+        for bucket in self.0.values_mut() {
+            bucket.clear();
+        }
+    }
+}
+
+fn check_for_collisions(
+    mut commands: Commands,
+    (game_config, game_settings, mut game_rng, cheats): (
+        Res<GameConfig>,
+        Res<GameSettingsState>,
+        ResMut<GameRng>,
+        Res<Cheats>,
+    ),
+    mut projectile_pool: ResMut<ProjectilePool>,
+    mut projectiles_query: Query<
+        (
+            Entity,
+            &Transform,
+            Option<&Piercing>,
+            Option<&mut HitEnemies>,
+        ),
+        (With<Projectile>, Without<EnemyProjectile>, Without<Pooled>),
+    >,
+    enemy_projectiles_query: Query<(Entity, &Transform), (With<EnemyProjectile>, Without<Pooled>)>,
+    mut enemy_query: Query<
+        (
+            Entity,
+            &Transform,
+            &mut Health,
+            &EnemyKind,
+            Option<&Boss>,
+            Option<&Splits>,
+            Option<&Sortie>,
+            Option<&Escorted>,
+        ),
+        (With<Enemy>, With<Collider>),
+    >,
+    player_query: Query<
+        (&Transform, Option<&Invincible>, Option<&DualFighter>),
+        (With<Player>, Without<Respawning>),
+    >,
+    mut death_events: EventWriter<EnemyDeathEvent>,
+    mut player_death_events: EventWriter<PlayerDeathEvent>,
+    (mut sound_events, mut near_miss_events): (
+        EventWriter<PlaySoundEvent>,
+        EventWriter<NearMissEvent>,
+    ),
+    mut screen_shake: ResMut<ScreenShake>,
+    mut shot_stats: ResMut<ShotStats>,
+    game_fonts: Res<GameFonts>,
+    textures: Res<Textures>,
+    challenge_stage: Res<ChallengeStage>,
+    // (enemies, enemy projectiles)
+    (mut grid, mut enemy_projectile_grid): (Local<CollisionGrid>, Local<CollisionGrid>),
+) {
+    // Sort the enemies into the grid
+    grid.clear();
+    for (enemy_entity, enemy_transform, _, _, _, _, _, _) in &enemy_query {
+        grid.insert(enemy_entity, enemy_transform.translation, ENEMY_SIZE);
+    }
+
+    // Find every projectile that hit an enemy first, then blow things up.
+    // Each projectile only hits one enemy, and each enemy can only be hit once
+    // (so overlapping enemies or projectiles don't explode or score twice,
+    // and a piercing shot doesn't hit the same enemy again on its way through)
+    let mut hits: Vec<(Entity, Entity, Vec3, bool)> = Vec::new();
+    for (projectile_entity, projectile_transform, piercing, hit_enemies) in &projectiles_query {
+        let hit = grid.nearby(projectile_transform.translation).find(
+            |(enemy_entity, enemy_translation, enemy_size)| {
+                !hits
+                    .iter()
+                    .any(|(_, hit_enemy, _, _)| hit_enemy == enemy_entity)
+                    && !hit_enemies.is_some_and(|hit_enemies| hit_enemies.0.contains(enemy_entity))
+                    && collide(
+                        projectile_transform.translation,
+                        projectile_transform.scale.truncate(),
+                        *enemy_translation,
+                        *enemy_size,
+                    )
+                    .is_some()
+            },
+        );
+
+        if let Some((enemy_entity, enemy_translation, _)) = hit {
+            hits.push((
+                projectile_entity,
+                *enemy_entity,
+                *enemy_translation,
+                piercing.is_some(),
+            ));
+        }
+    }
+
+    // (a shot that hit an enemy can't cancel out an enemy shot too)
+    let hit_projectiles: Vec<Entity> = hits
+        .iter()
+        .map(|(projectile, _, _, _)| *projectile)
+        .collect();
+    let mut destroyed: Vec<Entity> = Vec::new();
+    for (projectile_entity, enemy_entity, enemy_translation, piercing) in hits {
+        println!("Collided!");
+        shot_stats.hits += 1;
+
+        // Projectile disappears too? Prevents "cutting through" a line of enemies all at once
+        // (except charged shots, which are meant to cut through)
+        if !piercing {
+            projectile_pool.release(&mut commands, projectile_entity);
+        } else if let Ok((_, _, _, Some(mut hit_enemies))) =
+            projectiles_query.get_mut(projectile_entity)
+        {
+            hit_enemies.0.push(enemy_entity);
+        }
+
+        let Ok((_, _, mut health, kind, boss, splits, sortie, escorted)) =
+            enemy_query.get_mut(enemy_entity)
+        else {
+            continue;
+        };
+        health.0 = health.0.saturating_sub(1);
+
+        // Still alive? Flash to show it got hit
+        if health.0 > 0 {
+            sound_events.send(PlaySoundEvent(SoundId::EnemyDeath));
+            screen_shake.add_trauma(SCREEN_SHAKE_TRAUMA_PER_HIT);
+            commands.entity(enemy_entity).insert(HitFlash::default());
+            continue;
+        }
+
+        let mut points = if boss.is_some() {
+            game_config.boss_points
+        } else {
+            game_config.enemy_points
+        };
+        // Worth more out of formation (until they're settled back in)
+        if sortie.is_some() {
+            points *= DIVE_POINTS_MULTIPLIER;
+        }
+        // A boss still flying with its escorts is worth a bonus instead (more escorts, bigger bonus)
+        if let Some(escorted) = escorted {
+            let escorts = escorted.0.len().min(ESCORT_BONUS.len());
+            if escorts > 0 {
+                points = ESCORT_BONUS[escorts - 1];
+            }
+        }
+
+        // Fire off a EnemyDeathEvent to notify other systems
+        // death_events.send_default();
+        death_events.send(EnemyDeathEvent {
+            points,
+            kind: *kind,
+            translation: enemy_translation,
+            splits: splits.copied(),
+            ..default()
+        });
+
+        // Shake the screen (more enemies dying = more shake)
+        screen_shake.add_trauma(SCREEN_SHAKE_TRAUMA_PER_KILL);
+
+        // Show the points we got floating where the enemy was
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    points.to_string(),
+                    TextStyle {
+                        font: game_fonts.body.clone(),
+                        font_size: UI_FONT_SMALL,
+                        color: UI_COLOR_WHITE,
+                    },
+                )
+                .with_alignment(TextAlignment::CENTER),
+                transform: Transform::from_translation(enemy_translation + Vec3::Z),
+                ..default()
+            },
+            ScorePopup {
+                lifetime: Timer::from_seconds(SCORE_POPUP_LIFETIME, TimerMode::Once),
+                velocity: Vec2::new(0.0, SCORE_POPUP_SPEED),
+            },
+        ));
+
+        // Enemy is destroyed
+        destroy_enemy(
+            &mut commands,
+            &textures,
+            enemy_entity,
+            enemy_translation,
+            boss.is_some(),
+        );
+        destroyed.push(enemy_entity);
+
+        // Every so often an enemy drops a power-up (and bosses sometimes drop a shield)
+        if boss.is_some() && game_rng.chance(game_config.shield_drop_chance) {
+            spawn_shield(&mut commands, &textures, enemy_translation);
+        } else if game_rng.chance(game_config.power_up_drop_chance) {
+            spawn_power_up(&mut commands, &textures, &mut game_rng, enemy_translation);
+        }
+    }
+
+    // With shot cancelling on, any shot that didn't hit an enemy can knock out an enemy's shot instead
+    // (both disappear, except charged shots which keep going)
+    let mut cancelled: Vec<Entity> = Vec::new();
+    if game_settings.shot_cancelling {
+        enemy_projectile_grid.clear();
+        for (projectile_entity, projectile_transform) in &enemy_projectiles_query {
+            enemy_projectile_grid.insert(
+                projectile_entity,
+                projectile_transform.translation,
+                projectile_transform.scale.truncate(),
+            );
+        }
+
+        for (projectile_entity, projectile_transform, piercing, _) in &projectiles_query {
+            if hit_projectiles.contains(&projectile_entity) {
+                continue;
+            }
+            let cancel = enemy_projectile_grid
+                .nearby(projectile_transform.translation)
+                .find(|(enemy_projectile_entity, enemy_translation, enemy_size)| {
+                    !cancelled.contains(enemy_projectile_entity)
+                        && collide(
+                            projectile_transform.translation,
+                            projectile_transform.scale.truncate(),
+                            *enemy_translation,
+                            *enemy_size,
+                        )
+                        .is_some()
+                });
+
+            if let Some((enemy_projectile_entity, enemy_translation, _)) = cancel {
+                println!("[COLLISION] Shot cancelled");
+                if piercing.is_none() {
+                    projectile_pool.release(&mut commands, projectile_entity);
+                }
+                projectile_pool.release(&mut commands, *enemy_projectile_entity);
+                cancelled.push(*enemy_projectile_entity);
+
+                death_events.send(EnemyDeathEvent {
+                    points: game_config.shot_cancel_points,
+                    translation: (projectile_transform.translation + *enemy_translation) / 2.0,
+                    shot_cancelled: true,
+                    ..default()
+                });
+            }
+        }
+    }
+
+    // Check if any enemy projectiles hit the player
+    // (unless they're already exploding)
+    let Ok((player_transform, invincible, dual_fighter)) = player_query.get_single() else {
+        return;
+    };
+    // Player just respawned (or cheating) - ignore any hits
+    if invincible.is_some() || cheats.invincible {
+        return;
+    }
+    let (player_translation, player_size) = player_hitbox(player_transform, dual_fighter.is_some());
+
+    // Flying into an enemy destroys both of them (but the player doesn't get any points for it).
+    // Only enemies down near the player count, so nothing can clip them while flying in,
+    // and nobody loses a life on a challenge stage.
+    if !challenge_stage.active {
+        let rammed = grid.nearby(player_translation).find(
+            |(enemy_entity, enemy_translation, enemy_size)| {
+                enemy_translation.y < RAMMING_HEIGHT_LIMIT
+                    && !destroyed.contains(enemy_entity)
+                    && collide(
+                        player_translation,
+                        player_size,
+                        *enemy_translation,
+                        *enemy_size,
+                    )
+                    .is_some()
+            },
+        );
+
+        if let Some((enemy_entity, enemy_translation, _)) = rammed {
+            println!("Player rammed an enemy!");
+            sound_events.send(PlaySoundEvent(SoundId::EnemyDeath));
+            let boss = enemy_query
+                .get(*enemy_entity)
+                .is_ok_and(|(_, _, _, _, boss, _, _, _)| boss.is_some());
+            destroy_enemy(
+                &mut commands,
+                &textures,
+                *enemy_entity,
+                *enemy_translation,
+                boss,
+            );
+            player_death_events.send_default();
+            // Already lost a life this frame
+            return;
+        }
+    }
+
+    let mut near_miss = false;
+    for (projectile_entity, projectile_transform) in &enemy_projectiles_query {
+        if cancelled.contains(&projectile_entity) {
+            continue;
+        }
+        let collision = collide(
+            projectile_transform.translation,
+            projectile_transform.scale.truncate(),
+            player_translation,
+            player_size,
+        );
+
+        if collision.is_some() {
+            println!("Player hit!");
+            projectile_pool.release(&mut commands, projectile_entity);
+            player_death_events.send_default();
+
+            // Only lose one life per frame (and getting hit isn't a near miss)
+            return;
+        }
+
+        near_miss |= collide(
+            projectile_transform.translation,
+            projectile_transform.scale.truncate(),
+            player_translation,
+            player_size + Vec2::splat(NEAR_MISS_DISTANCE * 2.0),
+        )
+        .is_some();
+    }
+    if near_miss {
+        near_miss_events.send_default();
+    }
+}
+
+// Remove an enemy and leave an explosion where it was
+pub fn destroy_enemy(
+    commands: &mut Commands,
+    textures: &Textures,
+    enemy_entity: Entity,
+    enemy_translation: Vec3,
+    boss: bool,
+) {
+    commands.entity(enemy_entity).despawn();
+
+    // Bosses go out with a bigger (and longer) bang
+    let (texture_atlas, scale) = if boss {
+        (textures.explosion_boss.clone(), BOSS_EXPLOSION_SCALE)
+    } else {
+        (textures.explosion_enemy.clone(), 1.0)
+    };
+    commands.spawn((
+        SpriteSheetBundle {
+            texture_atlas,
+            transform: Transform {
+                translation: enemy_translation,
+                scale: Vec3::splat(scale),
+                ..default()
+            },
+            ..default()
+        },
+        AnimationTimer(Timer::from_seconds(
+            EXPLOSION_FRAME_TIME,
+            TimerMode::Repeating,
+        )),
+        AnimationFrame(0),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Everything `projectile` overlaps, checking every enemy (what the grid saves us from)
+    fn brute_force_hits(enemies: &[(Entity, Vec3)], projectile: Vec3) -> Vec<Entity> {
+        enemies
+            .iter()
+            .filter(|(_, translation)| {
+                collide(
+                    projectile,
+                    PROJECTILE_SIZE.truncate(),
+                    *translation,
+                    ENEMY_SIZE,
+                )
+                .is_some()
+            })
+            .map(|(entity, _)| *entity)
+            .collect()
+    }
+
+    fn grid_hits(grid: &CollisionGrid, projectile: Vec3) -> Vec<Entity> {
+        grid.nearby(projectile)
+            .filter(|(_, translation, size)| {
+                collide(projectile, PROJECTILE_SIZE.truncate(), *translation, *size).is_some()
+            })
+            .map(|(entity, _, _)| *entity)
+            .collect()
+    }
+
+    #[test]
+    fn nearby_reaches_into_neighboring_cells() {
+        let mut grid = CollisionGrid::default();
+        let enemy = Entity::from_raw(0);
+        // Just over the line into the next cell
+        grid.insert(
+            enemy,
+            Vec3::new(COLLISION_GRID_CELL_SIZE + 1.0, 0.0, 0.0),
+            ENEMY_SIZE,
+        );
+
+        let projectile = Vec3::new(COLLISION_GRID_CELL_SIZE - 1.0, 0.0, 0.0);
+        assert_eq!(grid_hits(&grid, projectile), [enemy]);
+        // Two cells away is too far to bother checking
+        let far_away = Vec3::new(-COLLISION_GRID_CELL_SIZE - 1.0, 0.0, 0.0);
+        assert_eq!(grid.nearby(far_away).count(), 0);
+    }
+
+    #[test]
+    fn grid_finds_the_same_hits_with_fewer_checks() {
+        // A full screen of enemies, 20px apart
+        let mut grid = CollisionGrid::default();
+        let mut enemies = Vec::new();
+        for x in -20..20 {
+            for y in -15..15 {
+                let entity = Entity::from_raw(enemies.len() as u32);
+                let translation = Vec3::new(x as f32 * 20.0, y as f32 * 20.0, 0.0);
+                grid.insert(entity, translation, ENEMY_SIZE);
+                enemies.push((entity, translation));
+            }
+        }
+
+        // Projectiles scattered over the same area, hitting and missing
+        let mut game_rng = GameRng::new(Some(1));
+        let mut checks = 0;
+        for _ in 0..200 {
+            let projectile = Vec3::new(
+                game_rng.range(-400.0..400.0),
+                game_rng.range(-300.0..300.0),
+                0.0,
+            );
+            let mut expected = brute_force_hits(&enemies, projectile);
+            let mut found = grid_hits(&grid, projectile);
+            expected.sort();
+            found.sort();
+            assert_eq!(found, expected);
+            checks += grid.nearby(projectile).count();
+        }
+
+        // Brute force checks every enemy for every projectile
+        let brute_force_checks = 200 * enemies.len();
+        assert!(
+            checks * 10 < brute_force_checks,
+            "{} checks with the grid, {} without",
+            checks,
+            brute_force_checks
+        );
+    }
+
+    // Just check_for_collisions and what it needs (no player unless a test spawns one)
+    fn collision_app() -> App {
+        let mut app = App::new();
+        app.insert_resource(GameConfig::default())
+            .insert_resource(GameRng::new(Some(1)))
+            .init_resource::<GameSettingsState>()
+            .init_resource::<Cheats>()
+            .init_resource::<ProjectilePool>()
+            .init_resource::<ScreenShake>()
+            .init_resource::<ShotStats>()
+            .init_resource::<GameFonts>()
+            .init_resource::<Textures>()
+            .init_resource::<ChallengeStage>()
+            .add_event::<EnemyDeathEvent>()
+            .add_event::<PlayerDeathEvent>()
+            .add_event::<PlaySoundEvent>()
+            .add_event::<NearMissEvent>()
+            .add_system(check_for_collisions);
+        app
+    }
+
+    fn spawn_test_enemy(app: &mut App, translation: Vec3) -> Entity {
+        app.world
+            .spawn((
+                Transform::from_translation(translation),
+                Enemy,
+                Collider,
+                Health(1),
+                EnemyKind::GreenBug,
+            ))
+            .id()
+    }
+
+    fn spawn_test_projectile(app: &mut App, translation: Vec3) -> Entity {
+        app.world
+            .spawn((
+                Transform {
+                    translation,
+                    scale: PROJECTILE_SIZE,
+                    ..default()
+                },
+                Projectile,
+            ))
+            .id()
+    }
+
+    fn count_events<E: bevy::ecs::event::Event>(app: &App) -> usize {
+        let events = app.world.resource::<Events<E>>();
+        events.get_reader().iter(events).count()
+    }
+
+    #[test]
+    fn one_shot_only_kills_one_of_two_overlapping_enemies() {
+        let mut app = collision_app();
+        let first = spawn_test_enemy(&mut app, Vec3::ZERO);
+        let second = spawn_test_enemy(&mut app, Vec3::new(2.0, 0.0, 0.0));
+        spawn_test_projectile(&mut app, Vec3::ZERO);
+        app.update();
+
+        assert_eq!(count_events::<EnemyDeathEvent>(&app), 1);
+        let survivors = [first, second]
+            .iter()
+            .filter(|enemy| app.world.get_entity(**enemy).is_some())
+            .count();
+        assert_eq!(survivors, 1);
+    }
+
+    #[test]
+    fn piercing_shots_only_hit_a_boss_once_on_the_way_through() {
+        let mut app = collision_app();
+        let boss = spawn_test_enemy(&mut app, Vec3::ZERO);
+        app.world
+            .entity_mut(boss)
+            .insert((Boss, Health(2), EnemyKind::Boss));
+        let projectile = spawn_test_projectile(&mut app, Vec3::ZERO);
+        app.world
+            .entity_mut(projectile)
+            .insert((Piercing, HitEnemies::default()));
+        // (overlapping for a few steps in a row)
+        for _ in 0..3 {
+            app.update();
+        }
+
+        assert_eq!(app.world.get::<Health>(boss).unwrap().0, 1);
+        assert_eq!(count_events::<EnemyDeathEvent>(&app), 0);
+        assert_eq!(app.world.resource::<ShotStats>().hits, 1);
+    }
+
+    fn cancelled_shots(shot_cancelling: bool) -> Vec<usize> {
+        let mut app = collision_app();
+        app.world
+            .resource_mut::<GameSettingsState>()
+            .shot_cancelling = shot_cancelling;
+        spawn_test_projectile(&mut app, Vec3::ZERO);
+        let enemy_projectile = spawn_test_projectile(&mut app, Vec3::new(1.0, 0.0, 0.0));
+        app.world
+            .entity_mut(enemy_projectile)
+            .insert(EnemyProjectile);
+        app.update();
+
+        let events = app.world.resource::<Events<EnemyDeathEvent>>();
+        events
+            .get_reader()
+            .iter(events)
+            .map(|event| {
+                assert!(event.shot_cancelled);
+                event.points
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cancelling_a_shot_scores_through_the_death_event() {
+        assert_eq!(
+            cancelled_shots(true),
+            [GameConfig::default().shot_cancel_points]
+        );
+        assert!(cancelled_shots(false).is_empty());
+    }
+
+    #[test]
+    fn clear_empties_every_cell() {
+        let mut grid = CollisionGrid::default();
+        grid.insert(Entity::from_raw(0), Vec3::ZERO, ENEMY_SIZE);
+        grid.clear();
+        assert_eq!(grid.nearby(Vec3::ZERO).count(), 0);
+    }
+}
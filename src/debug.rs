@@ -0,0 +1,131 @@
+use bevy::{
+    diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
+    ecs::entity::Entities,
+    prelude::*,
+};
+
+use crate::{projectile::Pooled, shared::*, stats::SessionStats, wave::EnemyKind};
+
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(FrameTimeDiagnosticsPlugin)
+            .add_startup_system(setup_debug_overlay)
+            .add_system(toggle_debug_overlay)
+            .add_system(update_debug_overlay.after(toggle_debug_overlay))
+            .add_system(log_power_up_events);
+    }
+}
+
+// Developer overlay with FPS, entity counts, and the session stats (hidden until toggled)
+#[derive(Component)]
+struct DebugText;
+
+const DEBUG_OVERLAY_KEY: KeyCode = KeyCode::F3;
+const DEBUG_OVERLAY_PADDING: Val = Val::Px(8.0);
+// Seconds between refreshes (so the numbers are readable, and the text isn't rebuilt every frame)
+const DEBUG_OVERLAY_REFRESH_TIME: f32 = 0.5;
+
+fn setup_debug_overlay(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle {
+            visibility: Visibility::INVISIBLE,
+            ..TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/VT323-Regular.ttf"),
+                    font_size: UI_FONT_SMALL,
+                    color: UI_COLOR_WHITE,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: DEBUG_OVERLAY_PADDING,
+                    left: DEBUG_OVERLAY_PADDING,
+                    ..default()
+                },
+                ..default()
+            })
+        },
+        DebugText,
+    ));
+}
+
+fn toggle_debug_overlay(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut query: Query<&mut Visibility, With<DebugText>>,
+) {
+    // (Ctrl+F3 is a cheat when built with debug-tools)
+    if keyboard_input.just_pressed(DEBUG_OVERLAY_KEY)
+        && !keyboard_input.any_pressed([KeyCode::LControl, KeyCode::RControl])
+    {
+        for mut visibility in &mut query {
+            visibility.is_visible = !visibility.is_visible;
+        }
+    }
+}
+
+fn update_debug_overlay(
+    time: Res<Time>,
+    diagnostics: Res<Diagnostics>,
+    entities: &Entities,
+    game_state: Res<GameState>,
+    session_stats: Res<SessionStats>,
+    projectile_query: Query<(), (With<Projectile>, Without<Pooled>)>,
+    enemy_query: Query<(), With<Enemy>>,
+    mut query: Query<(&mut Text, ChangeTrackers<Visibility>, &Visibility), With<DebugText>>,
+    mut refresh_timer: Local<Option<Timer>>,
+) {
+    let refresh_timer = refresh_timer.get_or_insert_with(|| {
+        Timer::from_seconds(DEBUG_OVERLAY_REFRESH_TIME, TimerMode::Repeating)
+    });
+    let refresh = refresh_timer.tick(frame_delta(&time)).just_finished();
+
+    for (mut text, visibility_tracker, visibility) in &mut query {
+        // Nothing to do while it's hidden (but fill it in straight away when it's shown)
+        if !visibility.is_visible || !(refresh || visibility_tracker.is_changed()) {
+            continue;
+        }
+
+        let fps = diagnostics
+            .get(FrameTimeDiagnosticsPlugin::FPS)
+            .and_then(|fps| fps.smoothed())
+            .unwrap_or(0.0);
+
+        let destroyed = |kind| session_stats.destroyed.get(&kind).copied().unwrap_or(0);
+        text.sections[0].value = format!(
+            "FPS: {:.0}\nENTITIES: {}\nPROJECTILES: {}\nENEMIES: {}\nLEVEL: {}\n\n\
+             SHOTS: {}  HITS: {} ({:.1}%)\nDESTROYED: {} BUGS  {} BOSSES\n\
+             DIVES SURVIVED: {}\nPOWER-UPS: {}\nPEAK COMBO: x{}\nTIME: {}",
+            fps,
+            entities.len(),
+            projectile_query.iter().count(),
+            enemy_query.iter().count(),
+            game_state.level,
+            session_stats.shots_fired,
+            session_stats.hits,
+            session_stats.hit_ratio(),
+            destroyed(EnemyKind::GreenBug),
+            destroyed(EnemyKind::Boss),
+            session_stats.dives_survived,
+            session_stats.power_ups_collected,
+            session_stats.peak_combo,
+            session_stats.time_played_text(),
+        );
+    }
+}
+
+// Log everything that happens to power-ups
+fn log_power_up_events(mut power_up_events: EventReader<PowerUpEvent>) {
+    for power_up_event in power_up_events.iter() {
+        match power_up_event {
+            PowerUpEvent::Dropped(kind) => println!("[POWER UP] Dropped {:?}", kind),
+            PowerUpEvent::Missed(kind) => println!("[POWER UP] Missed {:?}", kind),
+            PowerUpEvent::Collected(kind) => println!("[POWER UP] Collected {:?}", kind),
+            PowerUpEvent::ShieldBroken => println!("[POWER UP] Shield broke"),
+            PowerUpEvent::ShieldExpired => println!("[POWER UP] Shield expired"),
+        }
+    }
+}
@@ -0,0 +1,429 @@
+use crate::{projectile::Pooled, settings::GameSettingsState, shared::*};
+use bevy::prelude::*;
+
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScreenShake>()
+            .init_resource::<CameraFollow>()
+            .add_startup_system(setup_textures)
+            .add_system(animate_explosion.label(GameSystem::AnimateExplosion))
+            .add_system(animate_score_popups)
+            .add_system(spawn_muzzle_flash)
+            .add_system(spawn_sparks.after(GameSystem::Collision))
+            .add_system(spawn_projectile_trails)
+            .add_system(fade_afterimages)
+            .add_system(follow_player.before(shake_camera))
+            .add_system(shake_camera);
+    }
+}
+
+const EXPLOSION_FRAMES: usize = 4;
+const EXPLOSION_ENEMY_TILE_SIZE: Vec2 = Vec2::new(30.0, 32.0);
+const EXPLOSION_BOSS_FRAMES: usize = 6;
+const EXPLOSION_BOSS_TILE_SIZE: Vec2 = Vec2::new(48.0, 48.0);
+const EXPLOSION_PLAYER_TILE_SIZE: Vec2 = Vec2::new(40.0, 40.0);
+const ENEMY_FRAMES: usize = 2;
+const MUZZLE_FLASH_FRAMES: usize = 3;
+const MUZZLE_FLASH_TILE_SIZE: Vec2 = Vec2::new(9.0, 9.0);
+const MUZZLE_FLASH_FRAME_TIME: f32 = 0.03; // seconds
+const SPARK_FRAMES: usize = 2;
+const SPARK_TILE_SIZE: Vec2 = Vec2::new(7.0, 7.0);
+const SPARK_FRAME_TIME: f32 = 0.05; // seconds
+const TRAIL_SPAWN_TIME: f32 = 0.02; // seconds between afterimages
+const TRAIL_LIFETIME: f32 = 0.2; // seconds
+const TRAIL_COLOR: Color = Color::rgba(1.0, 0.9, 0.6, 0.5);
+const SCREEN_SHAKE_DECAY: f32 = 2.5; // trauma per second (a full shake settles in 0.4 seconds)
+const SCREEN_SHAKE_FREQUENCY: f32 = 25.0;
+const SCREEN_SHAKE_MAX_OFFSET: f32 = 12.0;
+// The camera never pans further than this, so the whole formation stays in view
+const CAMERA_FOLLOW_MAX_OFFSET: f32 = 16.0;
+const CAMERA_FOLLOW_SPEED: f32 = 3.0; // how quickly the camera catches up (higher = tighter)
+
+// Where the camera sits before any screen shake (panned a little towards the player)
+#[derive(Resource, Default)]
+struct CameraFollow {
+    offset: f32,
+}
+
+fn setup_textures(
+    mut commands: Commands,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    asset_server: Res<AssetServer>,
+    mut loading_assets: ResMut<LoadingAssets>,
+) {
+    // Explosion and enemy sprite sheets
+    commands.insert_resource(Textures {
+        explosion_enemy: texture_atlases.add(TextureAtlas::from_grid(
+            loading_assets.load(&asset_server, "sprites/explosion_enemy.png"),
+            EXPLOSION_ENEMY_TILE_SIZE,
+            EXPLOSION_FRAMES,
+            1,
+            None,
+            None,
+        )),
+        explosion_boss: texture_atlases.add(TextureAtlas::from_grid(
+            loading_assets.load(&asset_server, "sprites/explosion_boss.png"),
+            EXPLOSION_BOSS_TILE_SIZE,
+            EXPLOSION_BOSS_FRAMES,
+            1,
+            None,
+            None,
+        )),
+        explosion_player: texture_atlases.add(TextureAtlas::from_grid(
+            loading_assets.load(&asset_server, "sprites/explosion_player.png"),
+            EXPLOSION_PLAYER_TILE_SIZE,
+            EXPLOSION_FRAMES,
+            1,
+            None,
+            None,
+        )),
+        enemy_green_bug: texture_atlases.add(TextureAtlas::from_grid(
+            loading_assets.load(&asset_server, "sprites/enemy_green_bug_sheet.png"),
+            ENEMY_SIZE,
+            ENEMY_FRAMES,
+            1,
+            None,
+            None,
+        )),
+        enemy_boss: texture_atlases.add(TextureAtlas::from_grid(
+            loading_assets.load(&asset_server, "sprites/enemy_boss_sheet.png"),
+            ENEMY_SIZE,
+            ENEMY_FRAMES,
+            1,
+            None,
+            None,
+        )),
+        power_up: loading_assets.load(&asset_server, "sprites/power_up.png"),
+        shield: loading_assets.load(&asset_server, "sprites/shield.png"),
+        muzzle_flash: texture_atlases.add(TextureAtlas::from_grid(
+            loading_assets.load(&asset_server, "sprites/muzzle_flash.png"),
+            MUZZLE_FLASH_TILE_SIZE,
+            MUZZLE_FLASH_FRAMES,
+            1,
+            None,
+            None,
+        )),
+        spark: texture_atlases.add(TextureAtlas::from_grid(
+            loading_assets.load(&asset_server, "sprites/spark.png"),
+            SPARK_TILE_SIZE,
+            SPARK_FRAMES,
+            1,
+            None,
+            None,
+        )),
+        enemy_projectile: loading_assets.load(&asset_server, "sprites/enemy_projectile.png"),
+    });
+}
+
+// Step through each explosion's sprite sheet, removing it after the last frame
+fn animate_explosion(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    texture_atlases: Res<Assets<TextureAtlas>>,
+    mut query: Query<(
+        Entity,
+        &mut AnimationTimer,
+        &mut AnimationFrame,
+        &mut TextureAtlasSprite,
+        &Handle<TextureAtlas>,
+    )>,
+) {
+    for (explosion_entity, mut timer, mut frame, mut sprite, texture_atlas_handle) in &mut query {
+        if !timer.tick(scaled_delta(&time, &time_scale)).just_finished() {
+            continue;
+        }
+
+        let frame_count = texture_atlases
+            .get(texture_atlas_handle)
+            .map_or(1, |texture_atlas| texture_atlas.textures.len());
+
+        // Last frame finished? Explosion is done.
+        if frame.0 + 1 >= frame_count {
+            commands.entity(explosion_entity).despawn();
+            continue;
+        }
+
+        frame.0 += 1;
+        sprite.index = frame.0;
+    }
+}
+
+// Flash at the front of the ship whenever the player shoots
+// (animate_explosion plays it and cleans it up)
+fn spawn_muzzle_flash(
+    mut commands: Commands,
+    mut projectile_events: EventReader<ProjectileEvent>,
+    textures: Res<Textures>,
+    query: Query<&Transform, (With<Player>, Without<Respawning>)>,
+) {
+    if projectile_events.iter().count() == 0 {
+        return;
+    }
+    let Ok(player_transform) = query.get_single() else {
+        return;
+    };
+
+    commands.spawn((
+        SpriteSheetBundle {
+            texture_atlas: textures.muzzle_flash.clone(),
+            transform: Transform::from_translation(
+                player_transform.translation + Vec3::new(0.0, PLAYER_SIZE.y / 2.0, 1.0),
+            ),
+            ..default()
+        },
+        AnimationTimer(Timer::from_seconds(
+            MUZZLE_FLASH_FRAME_TIME,
+            TimerMode::Repeating,
+        )),
+        AnimationFrame(0),
+    ));
+}
+
+// Spark where a player's shot knocked out an enemy's
+// (animate_explosion plays it and cleans it up)
+fn spawn_sparks(
+    mut commands: Commands,
+    mut enemy_death_events: EventReader<EnemyDeathEvent>,
+    textures: Res<Textures>,
+) {
+    for event in enemy_death_events
+        .iter()
+        .filter(|event| event.shot_cancelled)
+    {
+        commands.spawn((
+            SpriteSheetBundle {
+                texture_atlas: textures.spark.clone(),
+                transform: Transform::from_translation(event.translation + Vec3::Z),
+                ..default()
+            },
+            AnimationTimer(Timer::from_seconds(SPARK_FRAME_TIME, TimerMode::Repeating)),
+            AnimationFrame(0),
+        ));
+    }
+}
+
+// Leave a trail of afterimages behind the player's projectiles
+fn spawn_projectile_trails(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    game_state: Res<GameState>,
+    query: Query<&Transform, (With<Projectile>, Without<EnemyProjectile>, Without<Pooled>)>,
+    mut since_last_spawn: Local<f32>,
+) {
+    if game_state.paused {
+        return;
+    }
+
+    *since_last_spawn += scaled_delta(&time, &time_scale).as_secs_f32();
+    if *since_last_spawn < TRAIL_SPAWN_TIME {
+        return;
+    }
+    *since_last_spawn = 0.0;
+
+    for projectile_transform in &query {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: TRAIL_COLOR,
+                    custom_size: Some(projectile_transform.scale.truncate()),
+                    ..default()
+                },
+                // Just behind the projectile
+                transform: Transform::from_translation(
+                    projectile_transform.translation - Vec3::Z * 0.1,
+                ),
+                ..default()
+            },
+            Afterimage(Timer::from_seconds(TRAIL_LIFETIME, TimerMode::Once)),
+        ));
+    }
+}
+
+// Shrink and fade afterimages, removing them once they're gone
+fn fade_afterimages(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    game_state: Res<GameState>,
+    mut query: Query<(Entity, &mut Afterimage, &mut Transform, &mut Sprite)>,
+) {
+    if game_state.paused {
+        return;
+    }
+
+    for (afterimage_entity, mut afterimage, mut afterimage_transform, mut sprite) in &mut query {
+        if afterimage
+            .0
+            .tick(scaled_delta(&time, &time_scale))
+            .finished()
+        {
+            commands.entity(afterimage_entity).despawn();
+            continue;
+        }
+
+        let left = afterimage.0.percent_left();
+        afterimage_transform.scale = Vec3::splat(left);
+        sprite.color.set_a(TRAIL_COLOR.a() * left);
+    }
+}
+
+// Ease the camera a little towards the player's side of the screen (and back to the middle between games)
+fn follow_player(
+    time: Res<Time>,
+    game_config: Res<GameConfig>,
+    game_state: Res<GameState>,
+    mut camera_follow: ResMut<CameraFollow>,
+    player_query: Query<&Transform, (With<Player>, Without<Respawning>)>,
+) {
+    let target = match player_query.get_single() {
+        Ok(player_transform) if game_state.started => (player_transform.translation.x
+            * game_config.camera_follow_strength)
+            .clamp(-CAMERA_FOLLOW_MAX_OFFSET, CAMERA_FOLLOW_MAX_OFFSET),
+        // (stay put while the player is respawning)
+        Err(_) if game_state.started => camera_follow.offset,
+        _ => 0.0,
+    };
+    let amount = (CAMERA_FOLLOW_SPEED * frame_delta(&time).as_secs_f32()).min(1.0);
+    camera_follow.offset += (target - camera_follow.offset) * amount;
+}
+
+// Offset the camera based on the screen shake trauma, decaying it over time.
+// Only the world shakes - the UI is drawn in screen space, so the score stays put.
+// The shake goes on top of the camera following the player.
+fn shake_camera(
+    time: Res<Time>,
+    game_settings: Res<GameSettingsState>,
+    camera_follow: Res<CameraFollow>,
+    mut screen_shake: ResMut<ScreenShake>,
+    mut query: Query<&mut Transform, With<Camera2d>>,
+) {
+    let mut camera_transform = query.single_mut();
+
+    // Turned off in the settings
+    if !game_settings.screen_shake {
+        screen_shake.trauma = 0.0;
+    }
+
+    if screen_shake.trauma <= 0.0 {
+        // Make sure we always end up back where we started
+        camera_transform.translation.x = camera_follow.offset;
+        camera_transform.translation.y = 0.0;
+        return;
+    }
+
+    // Squaring the trauma makes small shakes subtle and big shakes violent
+    let shake = screen_shake.trauma * screen_shake.trauma;
+    let t = time.elapsed_seconds() * SCREEN_SHAKE_FREQUENCY;
+    camera_transform.translation.x =
+        camera_follow.offset + SCREEN_SHAKE_MAX_OFFSET * shake * shake_noise(t, 0.0);
+    camera_transform.translation.y = SCREEN_SHAKE_MAX_OFFSET * shake * shake_noise(t, 10.0);
+
+    screen_shake.trauma =
+        (screen_shake.trauma - SCREEN_SHAKE_DECAY * frame_delta(&time).as_secs_f32()).max(0.0);
+}
+
+// Smooth wobble between -1.0 and 1.0 (a few sine waves that don't line up, so it doesn't look like it repeats).
+// Different seeds give different wobbles for each axis.
+fn shake_noise(t: f32, seed: f32) -> f32 {
+    ((t + seed).sin() + (t * 1.7 + seed * 2.3).sin() * 0.5 + (t * 3.1 + seed * 0.7).sin() * 0.25)
+        / 1.75
+}
+
+// Float score popups upwards and fade them out until their time is up
+fn animate_score_popups(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    game_state: Res<GameState>,
+    mut query: Query<(Entity, &mut ScorePopup, &mut Transform, &mut Text)>,
+) {
+    if game_state.paused {
+        return;
+    }
+
+    for (popup_entity, mut popup, mut popup_transform, mut text) in &mut query {
+        if popup
+            .lifetime
+            .tick(scaled_delta(&time, &time_scale))
+            .finished()
+        {
+            commands.entity(popup_entity).despawn();
+            continue;
+        }
+
+        popup_transform.translation +=
+            (popup.velocity * scaled_delta(&time, &time_scale).as_secs_f32()).extend(0.0);
+
+        // Fade out as the popup reaches the end of its life
+        let alpha = 1.0 - popup.lifetime.percent();
+        for section in &mut text.sections {
+            section.style.color.set_a(alpha);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use bevy::core::CorePlugin;
+
+    use super::*;
+
+    #[test]
+    fn explosions_finishing_together_all_despawn() {
+        let mut app = App::new();
+        app.add_plugin(CorePlugin::default())
+            .add_plugin(AssetPlugin::default())
+            .add_asset::<TextureAtlas>()
+            .init_resource::<Time>()
+            .init_resource::<TimeScale>()
+            .add_system(animate_explosion);
+
+        let texture_atlas =
+            app.world
+                .resource_mut::<Assets<TextureAtlas>>()
+                .add(TextureAtlas::from_grid(
+                    Handle::default(),
+                    EXPLOSION_ENEMY_TILE_SIZE,
+                    EXPLOSION_FRAMES,
+                    1,
+                    None,
+                    None,
+                ));
+        // All three on their last frame, due to move on this update
+        let last_frame = EXPLOSION_FRAMES - 1;
+        let explosions: Vec<Entity> = (0..3)
+            .map(|_| {
+                app.world
+                    .spawn((
+                        SpriteSheetBundle {
+                            texture_atlas: texture_atlas.clone(),
+                            sprite: TextureAtlasSprite::new(last_frame),
+                            ..default()
+                        },
+                        AnimationTimer(Timer::from_seconds(
+                            EXPLOSION_FRAME_TIME,
+                            TimerMode::Repeating,
+                        )),
+                        AnimationFrame(last_frame),
+                    ))
+                    .id()
+            })
+            .collect();
+
+        let start = Instant::now();
+        let mut time = app.world.resource_mut::<Time>();
+        time.update_with_instant(start);
+        time.update_with_instant(start + Duration::from_secs_f32(EXPLOSION_FRAME_TIME));
+        app.update();
+
+        for explosion in explosions {
+            assert!(app.world.get_entity(explosion).is_none());
+        }
+    }
+}
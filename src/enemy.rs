@@ -0,0 +1,1151 @@
+use std::time::Duration;
+
+use bevy::{ecs::system::EntityCommands, prelude::*, time::FixedTimesteps};
+use rand::seq::IteratorRandom;
+
+use crate::{
+    formation::{FormationGrid, InFormation},
+    projectile::ProjectilePool,
+    shared::*,
+    wave::{EnemyKind, EntrancePath, WaveDefinition, WaveLibrary},
+};
+
+pub struct EnemyPlugin;
+
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut App) {
+        let difficulty = DifficultyCurve::for_level(1, &GameConfig::default(), GameMode::default());
+        app.insert_resource(EnemyProjectileTimer(Timer::from_seconds(
+            difficulty.fire_interval,
+            TimerMode::Repeating,
+        )))
+        .insert_resource(difficulty)
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(FixedStep)
+                .with_system(enemy_shoot_projectile.before(GameSystem::Collision))
+                .with_system(reveal_delayed_enemies.before(GameSystem::Collision))
+                .with_system(fly_challenge_paths.before(GameSystem::Collision))
+                .with_system(dive_split_enemies.before(GameSystem::Collision))
+                .with_system(start_dives.before(GameSystem::Collision))
+                .with_system(fly_sorties.before(GameSystem::Collision))
+                .with_system(fly_escorts.after(fly_sorties).before(GameSystem::Collision))
+                .with_system(
+                    update_escort_groups
+                        .after(fly_escorts)
+                        .before(GameSystem::Collision),
+                ),
+        )
+        .insert_resource(DiveTimer(Timer::from_seconds(
+            DIVE_INTERVAL,
+            TimerMode::Repeating,
+        )))
+        .insert_resource(EnemyAnimationTimer(Timer::from_seconds(
+            ENEMY_FLAP_TIME,
+            TimerMode::Repeating,
+        )))
+        .add_system(animate_enemies)
+        .add_system(count_challenge_hits.before(GameSystem::CheckLevelComplete))
+        // (the new enemies need to be spawned in the same frame the old one is despawned,
+        // or the level could look clear in between)
+        .add_system(
+            split_enemies
+                .after(GameSystem::Collision)
+                .before(GameSystem::CheckLevelComplete),
+        )
+        .add_system(
+            spawn_enemies
+                .label(GameSystem::SpawnEnemies)
+                .after(GameSystem::PlayIntro),
+        );
+    }
+}
+
+// Timer used to space out enemies firing
+#[derive(Resource)]
+pub struct EnemyProjectileTimer(pub Timer);
+
+// How tough the current level is (recalculated at the start of every level)
+#[derive(Resource)]
+struct DifficultyCurve {
+    // Seconds between enemy shots
+    fire_interval: f32,
+    projectile_speed: f32,
+    // Multiplies the wave's entrance delays (smaller = enemies show up sooner)
+    spawn_delay_scale: f32,
+    // How fast enemies fly their paths
+    flight_speed: f32,
+    // How fast enemies dive at the player
+    dive_speed: f32,
+    // Chance an enemy shot steers towards the player
+    homing_chance: f64,
+    // Chance an enemy shot is aimed at the player (the rest fall straight down)
+    aimed_chance: f64,
+    // Seconds of the player's movement to lead aimed shots by
+    lead_factor: f32,
+    // Chances an enemy fires a spread or a sine wave instead of a single shot
+    spread_chance: f64,
+    sine_wave_chance: f64,
+}
+
+impl DifficultyCurve {
+    fn for_level(level: usize, game_config: &GameConfig, game_mode: GameMode) -> Self {
+        let level = level.max(1) as f32 - 1.0;
+        let flight_speed = CHALLENGE_SPEED
+            * (1.0 + ENEMY_FLIGHT_SPEED_PER_LEVEL * level).min(ENEMY_FLIGHT_SPEED_MAX);
+
+        DifficultyCurve {
+            // Starts slow and closes in on the fastest fire rate
+            fire_interval: game_config.enemy_fire_interval_min
+                + (game_config.enemy_fire_interval_start - game_config.enemy_fire_interval_min)
+                    * ENEMY_FIRE_INTERVAL_FALLOFF.powf(level),
+            projectile_speed: game_config.projectile_speed
+                * ENEMY_PROJECTILE_SPEED_SCALE
+                * (1.0 + ENEMY_PROJECTILE_SPEED_PER_LEVEL * level).min(ENEMY_PROJECTILE_SPEED_MAX),
+            spawn_delay_scale: (1.0 - ENEMY_SPAWN_DELAY_PER_LEVEL * level)
+                .max(ENEMY_SPAWN_DELAY_SCALE_MIN),
+            flight_speed,
+            // Slower in modern mode, where the player can move up into a dive's path
+            dive_speed: match game_mode {
+                GameMode::Classic => flight_speed,
+                GameMode::Modern => flight_speed * MODERN_DIVE_SPEED_SCALE,
+            },
+            // None at first, then more and more of them
+            homing_chance: ((level + 1.0 - HOMING_START_LEVEL) * HOMING_CHANCE_PER_LEVEL)
+                .clamp(0.0, HOMING_CHANCE_MAX) as f64,
+            aimed_chance: (ENEMY_AIMED_CHANCE_START + ENEMY_AIMED_CHANCE_PER_LEVEL * level)
+                .min(ENEMY_AIMED_CHANCE_MAX) as f64,
+            lead_factor: game_config.enemy_aim_lead,
+            // Harder patterns show up later on
+            spread_chance: ((level + 1.0 - SPREAD_START_LEVEL) * SPREAD_CHANCE_PER_LEVEL)
+                .clamp(0.0, SPREAD_CHANCE_MAX) as f64,
+            sine_wave_chance: ((level + 1.0 - SINE_WAVE_START_LEVEL) * SINE_WAVE_CHANCE_PER_LEVEL)
+                .clamp(0.0, SINE_WAVE_CHANCE_MAX) as f64,
+        }
+    }
+}
+
+// The shapes enemy fire comes in (harder ones unlock at higher levels, see DifficultyCurve)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum BulletPattern {
+    // One shot, straight down or aimed at the player
+    Single,
+    // Three shots fanned out around the aim
+    Spread3,
+    // One shot that weaves side to side on the way down
+    SineWave,
+}
+
+impl BulletPattern {
+    // Bosses fire spreads twice as often as everyone else
+    fn pick(difficulty: &DifficultyCurve, boss: bool, game_rng: &mut GameRng) -> Self {
+        let spread_chance = if boss {
+            difficulty.spread_chance * 2.0
+        } else {
+            difficulty.spread_chance
+        };
+        if game_rng.chance(spread_chance) {
+            BulletPattern::Spread3
+        } else if game_rng.chance(difficulty.sine_wave_chance) {
+            BulletPattern::SineWave
+        } else {
+            BulletPattern::Single
+        }
+    }
+
+    // The velocity of every projectile in the pattern, fired in the aimed direction
+    fn velocities(&self, direction: Vec2, speed: f32) -> Vec<Vec2> {
+        match self {
+            BulletPattern::Single => vec![direction * speed],
+            BulletPattern::Spread3 => {
+                let angle = SPREAD_ANGLE.to_radians();
+                [-angle, 0.0, angle]
+                    .iter()
+                    .map(|angle| Vec2::from_angle(*angle).rotate(direction) * speed)
+                    .collect()
+            }
+            // (the weave does the aiming)
+            BulletPattern::SineWave => vec![ENEMY_PROJECTILE_DIRECTION * speed],
+        }
+    }
+}
+
+// An enemy waiting for its turn to enter (seconds left)
+#[derive(Component)]
+struct SpawnDelay(f32);
+
+// An enemy flying a scripted loop through a challenge stage.
+// It dives in, loops once, then leaves out the bottom.
+#[derive(Component)]
+struct FlightPath {
+    // Seconds since it started flying
+    age: f32,
+    // 1 = loops counter-clockwise, -1 = clockwise
+    turn: f32,
+}
+
+// Everything an enemy is made of, on top of however it's drawn
+// (the Collider gets added separately, since enemies waiting to enter can't be hit yet)
+#[derive(Bundle)]
+struct EnemyBundle<T: Bundle> {
+    #[bundle]
+    sprite: T,
+    enemy: Enemy,
+    kind: EnemyKind,
+    health: Health,
+}
+
+// A piece of an enemy that split apart.
+// It bursts outwards, then falls down the screen towards the player.
+#[derive(Component)]
+struct Diving;
+
+// Timer used to space out enemies diving out of the formation
+#[derive(Resource)]
+struct DiveTimer(Timer);
+
+// Timer used to flap every enemy's wings at the same time (like the arcade)
+#[derive(Resource)]
+struct EnemyAnimationTimer(Timer);
+
+const ENEMY_FLAP_TIME: f32 = 0.5; // seconds per frame
+const ENEMY_FIRE_INTERVAL_FALLOFF: f32 = 0.9; // how much of the gap to the minimum is left after each level
+const ENEMY_PROJECTILE_SPEED_PER_LEVEL: f32 = 0.05;
+const ENEMY_PROJECTILE_SPEED_MAX: f32 = 2.0; // times the starting speed
+
+// Enemy shots start a little slower than the player's
+const ENEMY_PROJECTILE_SPEED_SCALE: f32 = 0.85;
+const ENEMY_SPAWN_DELAY_PER_LEVEL: f32 = 0.05;
+const ENEMY_SPAWN_DELAY_SCALE_MIN: f32 = 0.5; // enemies enter at most twice as fast
+const ENEMY_FLIGHT_SPEED_PER_LEVEL: f32 = 0.03;
+const ENEMY_FLIGHT_SPEED_MAX: f32 = 1.5; // times the starting speed
+const MODERN_DIVE_SPEED_SCALE: f32 = 0.75; // times the flight speed
+const ENEMY_PROJECTILE_DIRECTION: Vec2 = Vec2::NEG_Y;
+const ENEMY_AIMED_CHANCE_START: f32 = 0.3;
+const ENEMY_AIMED_CHANCE_PER_LEVEL: f32 = 0.05;
+const ENEMY_AIMED_CHANCE_MAX: f32 = 0.7;
+// Aimed shots never go further than this off straight down, so they stay dodgeable
+const ENEMY_AIM_MAX_ANGLE: f32 = 30.0; // degrees
+const HOMING_START_LEVEL: f32 = 5.0; // homing shots show up from the level after this
+const HOMING_CHANCE_PER_LEVEL: f32 = 0.1;
+const HOMING_CHANCE_MAX: f32 = 0.5;
+const HOMING_TURN_RATE: f32 = 1.0; // radians per second
+
+// Spreads fan out this far either side of the aim
+const SPREAD_ANGLE: f32 = 15.0; // degrees
+const SPREAD_START_LEVEL: f32 = 3.0; // spreads show up from the level after this
+const SPREAD_CHANCE_PER_LEVEL: f32 = 0.1;
+const SPREAD_CHANCE_MAX: f32 = 0.3;
+const SINE_WAVE_START_LEVEL: f32 = 6.0; // sine waves show up from the level after this
+const SINE_WAVE_CHANCE_PER_LEVEL: f32 = 0.08;
+const SINE_WAVE_CHANCE_MAX: f32 = 0.25;
+const SINE_WAVE_AMPLITUDE: f32 = 40.0;
+const SINE_WAVE_FREQUENCY: f32 = 1.0; // swings per second
+
+const BOSS_HEALTH: usize = 2;
+// Formation enemies diving at the player
+const DIVE_INTERVAL: f32 = 3.0; // seconds between dives
+const DIVE_TURN_RATE: f32 = 2.0; // radians per second
+const DIVE_SWERVE: f32 = 0.6; // radians off straight down they leave the formation at
+
+// Diving bosses bring along up to this many of their neighbors from the formation
+const MAX_ESCORTS: usize = 2;
+const ESCORT_RANGE: f32 = 1.5; // formation gaps
+
+// Challenge stage loops
+const CHALLENGE_SPEED: f32 = 250.0;
+const CHALLENGE_DIVE_TIME: f32 = 1.2; // seconds flying straight down before looping
+const CHALLENGE_LOOP_TIME: f32 = 2.0; // seconds to fly all the way around the loop
+
+// Enemies that split apart
+const SPLIT_SPEED: f32 = 250.0; // how fast the pieces burst out
+const SPLIT_SPREAD: f32 = 1.2; // radians between the outermost pieces
+const SPLIT_GRAVITY: f32 = 400.0; // how quickly the pieces curve down into a dive
+
+// Spawn an enemy of a certain kind (the caller adds how it moves and when it can be hit)
+pub fn spawn_enemy<'w, 's, 'a>(
+    commands: &'a mut Commands<'w, 's>,
+    textures: &Textures,
+    kind: EnemyKind,
+    translation: Vec3,
+) -> EntityCommands<'w, 's, 'a> {
+    match kind {
+        EnemyKind::GreenBug => commands.spawn(EnemyBundle {
+            sprite: SpriteSheetBundle {
+                texture_atlas: textures.enemy_green_bug.clone(),
+                transform: Transform::from_translation(translation),
+                ..default()
+            },
+            enemy: Enemy,
+            kind,
+            health: Health::default(),
+        }),
+        EnemyKind::Boss => commands.spawn((
+            EnemyBundle {
+                sprite: SpriteSheetBundle {
+                    texture_atlas: textures.enemy_boss.clone(),
+                    transform: Transform::from_translation(translation),
+                    ..default()
+                },
+                enemy: Enemy,
+                kind,
+                health: Health(BOSS_HEALTH),
+            },
+            Boss,
+        )),
+    }
+}
+
+// Spawn the current level's wave of enemies at the start of each level
+fn spawn_enemies(
+    mut commands: Commands,
+    textures: Res<Textures>,
+    mut new_level_events: EventReader<NewLevelEvent>,
+    game_state: Res<GameState>,
+    playfield: Res<Playfield>,
+    wave_library: Res<WaveLibrary>,
+    waves: Res<Assets<WaveDefinition>>,
+    mut challenge_stage: ResMut<ChallengeStage>,
+    mut formation_grid: ResMut<FormationGrid>,
+    mut difficulty: ResMut<DifficultyCurve>,
+    mut enemy_projectile_timer: ResMut<EnemyProjectileTimer>,
+    game_config: Res<GameConfig>,
+    game_mode: Res<GameMode>,
+) {
+    if new_level_events.iter().count() == 0 {
+        return;
+    }
+    println!("[LEVEL] Starting level {}", game_state.level);
+
+    // Every level gets a little harder
+    *difficulty = DifficultyCurve::for_level(game_state.level, &game_config, *game_mode);
+    enemy_projectile_timer
+        .0
+        .set_duration(Duration::from_secs_f32(difficulty.fire_interval));
+
+    *challenge_stage = ChallengeStage::default();
+    let Some(wave) = wave_library.for_level(game_state.level, &waves) else {
+        println!("[LEVEL] No wave to play for level {}", game_state.level);
+        return;
+    };
+    challenge_stage.active = wave.challenge;
+    *formation_grid = FormationGrid::for_wave(wave, &playfield, game_config.formation_gap);
+
+    for group in &wave.groups {
+        for (index, wave_enemy) in group.enemies.iter().enumerate() {
+            let translation = match group.path {
+                EntrancePath::Formation => formation_grid
+                    .slot_world_position(wave_enemy.row, wave_enemy.column)
+                    .unwrap_or_default(),
+                // Just above the top of the screen
+                EntrancePath::LoopLeft | EntrancePath::LoopRight => Vec3::new(
+                    group.entry * game_config.formation_gap,
+                    playfield.edge_vertical() + ENEMY_SIZE.y,
+                    1.0,
+                ),
+            };
+
+            let mut enemy = spawn_enemy(&mut commands, &textures, wave_enemy.kind, translation);
+            if let Some(splits) = wave_enemy.splits {
+                enemy.insert(splits);
+            }
+
+            match group.path {
+                EntrancePath::Formation => {
+                    if !formation_grid.occupy(wave_enemy.row, wave_enemy.column, enemy.id()) {
+                        println!(
+                            "[LEVEL] Formation slot {}, {} is already taken",
+                            wave_enemy.row, wave_enemy.column
+                        );
+                    }
+                    enemy.insert(InFormation);
+                }
+                EntrancePath::LoopLeft | EntrancePath::LoopRight => {
+                    enemy.insert((
+                        Velocity(Vec2::NEG_Y * difficulty.flight_speed),
+                        FlightPath {
+                            age: 0.0,
+                            turn: if group.path == EntrancePath::LoopRight {
+                                1.0
+                            } else {
+                                -1.0
+                            },
+                        },
+                    ));
+                }
+            }
+
+            // Wait (hidden, and can't be hit) until it's this enemy's turn to show up
+            let delay = (group.delay + index as f32 * group.spacing) * difficulty.spawn_delay_scale;
+            if delay > 0.0 {
+                enemy.insert((SpawnDelay(delay), Visibility::INVISIBLE));
+            } else {
+                enemy.insert(Collider);
+            }
+
+            challenge_stage.total += 1;
+        }
+    }
+}
+
+// Count down each waiting enemy's delay, then bring them on screen
+fn reveal_delayed_enemies(
+    mut commands: Commands,
+    fixed_timesteps: Res<FixedTimesteps>,
+    game_state: Res<GameState>,
+    mut query: Query<(Entity, &mut SpawnDelay, &mut Visibility)>,
+) {
+    if !game_state.started || game_state.stage_clear {
+        return;
+    }
+
+    for (enemy_entity, mut spawn_delay, mut visibility) in &mut query {
+        spawn_delay.0 -= fixed_step_seconds(&fixed_timesteps);
+        if spawn_delay.0 > 0.0 {
+            continue;
+        }
+        visibility.is_visible = true;
+        commands
+            .entity(enemy_entity)
+            .remove::<SpawnDelay>()
+            .insert(Collider);
+    }
+}
+
+// Fly challenge stage enemies along their loop, removing them once they leave the screen
+fn fly_challenge_paths(
+    mut commands: Commands,
+    fixed_timesteps: Res<FixedTimesteps>,
+    game_state: Res<GameState>,
+    playfield: Res<Playfield>,
+    mut query: Query<(Entity, &mut FlightPath, &mut Velocity, &mut Transform), Without<SpawnDelay>>,
+) {
+    if !game_state.started || game_state.stage_clear {
+        return;
+    }
+    let delta = fixed_step_seconds(&fixed_timesteps);
+
+    for (enemy_entity, mut flight_path, mut velocity, mut enemy_transform) in &mut query {
+        flight_path.age += delta;
+
+        // Steer around the loop, then carry on straight
+        let looping = flight_path.age > CHALLENGE_DIVE_TIME
+            && flight_path.age < CHALLENGE_DIVE_TIME + CHALLENGE_LOOP_TIME;
+        if looping {
+            let turn_speed = std::f32::consts::TAU / CHALLENGE_LOOP_TIME;
+            velocity.0 = Vec2::from_angle(flight_path.turn * turn_speed * delta).rotate(velocity.0);
+        }
+        enemy_transform.translation += (velocity.0 * delta).extend(0.0);
+
+        // Flew out the bottom - it got away
+        if enemy_transform.translation.y < -playfield.edge_vertical() - ENEMY_SIZE.y {
+            commands.entity(enemy_entity).despawn();
+        }
+    }
+}
+
+// Break destroyed enemies apart into smaller ships, fanned out around straight up
+fn split_enemies(
+    mut commands: Commands,
+    textures: Res<Textures>,
+    mut challenge_stage: ResMut<ChallengeStage>,
+    mut enemy_death_events: EventReader<EnemyDeathEvent>,
+) {
+    for event in enemy_death_events.iter() {
+        let Some(Splits(kind, count)) = event.splits else {
+            continue;
+        };
+        println!("[ENEMY] Enemy split into {}", count);
+
+        for piece in 0..count {
+            let spread = if count > 1 {
+                piece as f32 / (count - 1) as f32 - 0.5
+            } else {
+                0.0
+            };
+            let direction = Vec2::from_angle(spread * SPLIT_SPREAD).rotate(Vec2::Y);
+            spawn_enemy(&mut commands, &textures, kind, event.translation).insert((
+                Velocity(direction * SPLIT_SPEED),
+                Diving,
+                Collider,
+            ));
+        }
+
+        // The pieces need to be hit too for a perfect challenge stage
+        if challenge_stage.active {
+            challenge_stage.total += count as usize;
+        }
+    }
+}
+
+// Arc split pieces down the screen, removing them once they leave out the bottom
+fn dive_split_enemies(
+    mut commands: Commands,
+    fixed_timesteps: Res<FixedTimesteps>,
+    game_state: Res<GameState>,
+    playfield: Res<Playfield>,
+    mut query: Query<(Entity, &mut Velocity, &mut Transform), With<Diving>>,
+) {
+    if !game_state.started || game_state.stage_clear {
+        return;
+    }
+    let delta = fixed_step_seconds(&fixed_timesteps);
+
+    for (enemy_entity, mut velocity, mut enemy_transform) in &mut query {
+        velocity.0.y -= SPLIT_GRAVITY * delta;
+        enemy_transform.translation += (velocity.0 * delta).extend(0.0);
+
+        if enemy_transform.translation.y < -playfield.edge_vertical() - ENEMY_SIZE.y {
+            commands.entity(enemy_entity).despawn();
+        }
+    }
+}
+
+// Every so often an enemy leaves the formation and dives at the player
+fn start_dives(
+    mut commands: Commands,
+    fixed_timesteps: Res<FixedTimesteps>,
+    game_state: Res<GameState>,
+    challenge_stage: Res<ChallengeStage>,
+    difficulty: Res<DifficultyCurve>,
+    game_config: Res<GameConfig>,
+    mut game_rng: ResMut<GameRng>,
+    mut dive_timer: ResMut<DiveTimer>,
+    // (enemies still waiting to enter can't dive)
+    query: Query<
+        (Entity, &Transform, Option<&Boss>),
+        (With<InFormation>, With<Collider>, Without<Sortie>),
+    >,
+    player_query: Query<&Transform, (With<Player>, Without<Respawning>)>,
+    tractor_beam_query: Query<&TractorBeam>,
+) {
+    if !game_state.started || game_state.intro || game_state.stage_clear || challenge_stage.active {
+        return;
+    }
+    let step = Duration::from_secs_f32(fixed_step_seconds(&fixed_timesteps));
+    if !dive_timer.0.tick(step).just_finished() {
+        return;
+    }
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+
+    // (bosses stay put while their tractor beam is on)
+    let Some((enemy_entity, enemy_transform, boss)) = query
+        .iter()
+        .filter(|(enemy_entity, _, _)| {
+            !tractor_beam_query
+                .iter()
+                .any(|beam| beam.boss == *enemy_entity)
+        })
+        .choose(&mut *game_rng)
+    else {
+        return;
+    };
+
+    // Peel off towards the player's side of the screen
+    let swerve = if player_transform.translation.x < enemy_transform.translation.x {
+        -DIVE_SWERVE
+    } else {
+        DIVE_SWERVE
+    };
+    let velocity = Vec2::from_angle(swerve).rotate(Vec2::NEG_Y) * difficulty.dive_speed;
+    println!("[ENEMY] Enemy dove out of formation");
+    commands
+        .entity(enemy_entity)
+        .insert((Sortie::Diving, Velocity(velocity)));
+
+    // Bosses take their closest neighbors with them
+    if boss.is_none() {
+        return;
+    }
+    let boss_translation = enemy_transform.translation;
+    let escort_range = ESCORT_RANGE * game_config.formation_gap;
+    let mut neighbors: Vec<(Entity, Vec3)> = query
+        .iter()
+        .filter(|(_, escort_transform, escort_boss)| {
+            escort_boss.is_none()
+                && escort_transform.translation.distance(boss_translation) <= escort_range
+        })
+        .map(|(escort_entity, escort_transform, _)| (escort_entity, escort_transform.translation))
+        .collect();
+    neighbors.sort_by(|(_, a), (_, b)| {
+        a.distance_squared(boss_translation)
+            .total_cmp(&b.distance_squared(boss_translation))
+    });
+    neighbors.truncate(MAX_ESCORTS);
+    if neighbors.is_empty() {
+        return;
+    }
+
+    println!("[ENEMY] Boss is diving with {} escorts", neighbors.len());
+    for (escort_entity, escort_translation) in &neighbors {
+        commands.entity(*escort_entity).insert((
+            Sortie::Diving,
+            Velocity(velocity),
+            Escort {
+                boss: enemy_entity,
+                offset: (*escort_translation - boss_translation).truncate(),
+            },
+        ));
+    }
+    commands.entity(enemy_entity).insert(Escorted(
+        neighbors
+            .iter()
+            .map(|(escort_entity, _)| *escort_entity)
+            .collect(),
+    ));
+}
+
+// Steer diving enemies at the player. Once they fly out the bottom they loop around to the top
+// and head back to their formation slot.
+fn fly_sorties(
+    mut commands: Commands,
+    fixed_timesteps: Res<FixedTimesteps>,
+    game_state: Res<GameState>,
+    playfield: Res<Playfield>,
+    difficulty: Res<DifficultyCurve>,
+    mut formation_grid: ResMut<FormationGrid>,
+    // (escorts are flown by fly_escorts while they're with their boss)
+    mut query: Query<(Entity, &mut Sortie, &mut Velocity, &mut Transform), Without<Escort>>,
+    player_query: Query<&Transform, (With<Player>, Without<Respawning>, Without<Sortie>)>,
+) {
+    if !game_state.started || game_state.stage_clear {
+        return;
+    }
+    let delta = fixed_step_seconds(&fixed_timesteps);
+
+    for (enemy_entity, mut sortie, mut velocity, mut enemy_transform) in &mut query {
+        match *sortie {
+            Sortie::Diving => {
+                // Home in until it's level with the player, then carry on past them
+                if let Ok(player_transform) = player_query.get_single() {
+                    let to_player =
+                        (player_transform.translation - enemy_transform.translation).truncate();
+                    if to_player.y < 0.0 {
+                        let max_turn = DIVE_TURN_RATE * delta;
+                        let turn = velocity
+                            .0
+                            .angle_between(to_player)
+                            .clamp(-max_turn, max_turn);
+                        velocity.0 = Vec2::from_angle(turn).rotate(velocity.0);
+                    }
+                }
+                enemy_transform.translation += (velocity.0 * delta).extend(0.0);
+
+                if enemy_transform.translation.y < -playfield.edge_vertical() - ENEMY_SIZE.y {
+                    enemy_transform.translation.y = playfield.edge_vertical() + ENEMY_SIZE.y;
+                    *sortie = Sortie::Returning;
+                }
+            }
+            Sortie::Returning => {
+                // Its slot should still be waiting, but take the closest empty one if not
+                let slot = formation_grid.slot_of(enemy_entity).or_else(|| {
+                    let (row, column) =
+                        formation_grid.nearest_free_slot(enemy_transform.translation)?;
+                    formation_grid
+                        .occupy(row, column, enemy_entity)
+                        .then_some((row, column))
+                });
+                let Some(target) =
+                    slot.and_then(|(row, column)| formation_grid.slot_world_position(row, column))
+                else {
+                    // Nowhere to go - it got away
+                    commands.entity(enemy_entity).despawn_recursive();
+                    continue;
+                };
+
+                let arrived = move_towards(
+                    &mut enemy_transform.translation,
+                    target,
+                    difficulty.flight_speed * delta,
+                );
+                if arrived {
+                    commands
+                        .entity(enemy_entity)
+                        .remove::<Sortie>()
+                        .remove::<Velocity>();
+                }
+            }
+        }
+    }
+}
+
+// Keep escorts in formation with their boss.
+// Once the boss is destroyed (or loops back around) they head back to the formation on their own.
+fn fly_escorts(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    playfield: Res<Playfield>,
+    mut query: Query<(Entity, &Escort, &mut Sortie, &mut Velocity, &mut Transform)>,
+    boss_query: Query<(&Sortie, &Velocity, &Transform), Without<Escort>>,
+) {
+    if !game_state.started || game_state.stage_clear {
+        return;
+    }
+
+    for (escort_entity, escort, mut sortie, mut velocity, mut escort_transform) in &mut query {
+        match boss_query.get(escort.boss) {
+            Ok((Sortie::Diving, boss_velocity, boss_transform)) => {
+                escort_transform.translation =
+                    boss_transform.translation + escort.offset.extend(0.0);
+                velocity.0 = boss_velocity.0;
+            }
+            _ => {
+                // (coming back in from the top if it's already off the bottom of the screen)
+                if escort_transform.translation.y < -playfield.edge_vertical() - ENEMY_SIZE.y {
+                    escort_transform.translation.y = playfield.edge_vertical() + ENEMY_SIZE.y;
+                }
+                *sortie = Sortie::Returning;
+                commands.entity(escort_entity).remove::<Escort>();
+            }
+        }
+    }
+}
+
+// Drop escorts that were destroyed (or left) from their boss's group
+fn update_escort_groups(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Escorted)>,
+    escort_query: Query<&Escort>,
+) {
+    for (boss_entity, mut escorted) in &mut query {
+        escorted.0.retain(|escort_entity| {
+            escort_query
+                .get(*escort_entity)
+                .is_ok_and(|escort| escort.boss == boss_entity)
+        });
+        if escorted.0.is_empty() {
+            commands.entity(boss_entity).remove::<Escorted>();
+        }
+    }
+}
+
+// Keep track of how many enemies were hit during a challenge stage
+fn count_challenge_hits(
+    mut challenge_stage: ResMut<ChallengeStage>,
+    mut enemy_death_events: EventReader<EnemyDeathEvent>,
+) {
+    let hits = enemy_death_events
+        .iter()
+        .filter(|event| !event.shot_cancelled)
+        .count();
+    if challenge_stage.active && hits > 0 {
+        challenge_stage.hits += hits;
+    }
+}
+
+// Flip every enemy between their 2 frames together
+fn animate_enemies(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    game_state: Res<GameState>,
+    mut enemy_animation_timer: ResMut<EnemyAnimationTimer>,
+    mut query: Query<&mut TextureAtlasSprite, With<Enemy>>,
+) {
+    if game_state.paused
+        || !enemy_animation_timer
+            .0
+            .tick(scaled_delta(&time, &time_scale))
+            .just_finished()
+    {
+        return;
+    }
+
+    for mut sprite in &mut query {
+        sprite.index = 1 - sprite.index;
+    }
+}
+
+fn enemy_shoot_projectile(
+    fixed_timesteps: Res<FixedTimesteps>,
+    mut enemy_projectile_timer: ResMut<EnemyProjectileTimer>,
+    mut commands: Commands,
+    shared_assets: Res<SharedAssets>,
+    textures: Res<Textures>,
+    mut projectile_pool: ResMut<ProjectilePool>,
+    mut game_rng: ResMut<GameRng>,
+    // (enemies still waiting to enter can't shoot)
+    query: Query<(&Transform, Option<&Boss>), (With<Enemy>, With<Collider>)>,
+    player_query: Query<(&Transform, &Velocity), (With<Player>, Without<Respawning>)>,
+    player_exploding_query: Query<(), With<PlayerExploding>>,
+    game_state: Res<GameState>,
+    challenge_stage: Res<ChallengeStage>,
+    difficulty: Res<DifficultyCurve>,
+    mut sound_events: EventWriter<PlaySoundEvent>,
+) {
+    // Enemies hold their fire while the player's ship is blowing up,
+    // and never shoot during challenge stages
+    if !player_exploding_query.is_empty() || challenge_stage.active {
+        return;
+    }
+
+    if game_state.started && !game_state.intro {
+        // A random enemy fires when the timer repeats
+        if enemy_projectile_timer
+            .0
+            .tick(Duration::from_secs_f32(fixed_step_seconds(
+                &fixed_timesteps,
+            )))
+            .just_finished()
+        {
+            let enemy_count = query.iter().count();
+            if enemy_count == 0 {
+                return;
+            }
+            let shooter = game_rng.range(0..enemy_count);
+            if let Some((enemy_transform, boss)) = query.iter().nth(shooter) {
+                let pattern = BulletPattern::pick(&difficulty, boss.is_some(), &mut game_rng);
+                // (one sound for the whole volley, and audio_playback caps how many play at once)
+                sound_events.send(PlaySoundEvent(SoundId::EnemyProjectileFired));
+
+                // Some shots go for the player, the rest fall straight down
+                let direction = match player_query.get_single() {
+                    Ok((player_transform, player_velocity))
+                        if game_rng.chance(difficulty.aimed_chance) =>
+                    {
+                        aim_at_player(
+                            enemy_transform.translation.truncate(),
+                            player_transform.translation.truncate(),
+                            player_velocity.0.x,
+                            difficulty.lead_factor,
+                        )
+                    }
+                    _ => ENEMY_PROJECTILE_DIRECTION,
+                };
+                for velocity in pattern.velocities(direction, difficulty.projectile_speed) {
+                    let projectile = projectile_pool.fire_enemy(
+                        &mut commands,
+                        &shared_assets,
+                        &textures,
+                        enemy_transform.translation,
+                        velocity,
+                    );
+                    match pattern {
+                        BulletPattern::Single if game_rng.chance(difficulty.homing_chance) => {
+                            commands.entity(projectile).insert(Homing {
+                                turn_rate: HOMING_TURN_RATE,
+                            });
+                        }
+                        BulletPattern::SineWave => {
+                            commands.entity(projectile).insert(Wiggle {
+                                amplitude: SINE_WAVE_AMPLITUDE,
+                                frequency: SINE_WAVE_FREQUENCY,
+                                age: 0.0,
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Which way to shoot to hit the player, leading them by how fast they're moving sideways.
+// Kept within ENEMY_AIM_MAX_ANGLE of straight down.
+fn aim_at_player(from: Vec2, player: Vec2, player_velocity_x: f32, lead_factor: f32) -> Vec2 {
+    let target = player + Vec2::new(player_velocity_x * lead_factor, 0.0);
+    let to_target = target - from;
+    if to_target.length_squared() == 0.0 {
+        return ENEMY_PROJECTILE_DIRECTION;
+    }
+
+    let max_angle = ENEMY_AIM_MAX_ANGLE.to_radians();
+    let angle = ENEMY_PROJECTILE_DIRECTION
+        .angle_between(to_target)
+        .clamp(-max_angle, max_angle);
+    Vec2::from_angle(angle).rotate(ENEMY_PROJECTILE_DIRECTION)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use bevy::time::FixedTimestep;
+
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 0.001,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    fn classic_level(level: usize) -> DifficultyCurve {
+        DifficultyCurve::for_level(level, &GameConfig::default(), GameMode::Classic)
+    }
+
+    #[test]
+    fn level_1_starts_at_the_base_values() {
+        let difficulty = classic_level(1);
+        assert_close(difficulty.fire_interval, ENEMY_FIRE_INTERVAL_START);
+        assert_close(
+            difficulty.projectile_speed,
+            PROJECTILE_SPEED * ENEMY_PROJECTILE_SPEED_SCALE,
+        );
+        assert_close(difficulty.spawn_delay_scale, 1.0);
+        assert_close(difficulty.flight_speed, CHALLENGE_SPEED);
+        assert_eq!(difficulty.aimed_chance, ENEMY_AIMED_CHANCE_START as f64);
+        // Only plain shots this early
+        assert_eq!(difficulty.homing_chance, 0.0);
+        assert_eq!(difficulty.spread_chance, 0.0);
+        assert_eq!(difficulty.sine_wave_chance, 0.0);
+    }
+
+    #[test]
+    fn level_10_is_part_way_up_the_curve() {
+        let difficulty = classic_level(10);
+        assert_close(
+            difficulty.fire_interval,
+            ENEMY_FIRE_INTERVAL_MIN
+                + (ENEMY_FIRE_INTERVAL_START - ENEMY_FIRE_INTERVAL_MIN)
+                    * ENEMY_FIRE_INTERVAL_FALLOFF.powi(9),
+        );
+        assert_close(
+            difficulty.projectile_speed,
+            PROJECTILE_SPEED * ENEMY_PROJECTILE_SPEED_SCALE * 1.45,
+        );
+        assert_close(difficulty.spawn_delay_scale, 0.55);
+        assert_close(difficulty.flight_speed, CHALLENGE_SPEED * 1.27);
+        // Every shot pattern is in play by now
+        assert!(difficulty.homing_chance > 0.0);
+        assert!(difficulty.spread_chance > 0.0);
+        assert!(difficulty.sine_wave_chance > 0.0);
+    }
+
+    #[test]
+    fn level_50_is_capped() {
+        let difficulty = classic_level(50);
+        // (closes in on the fastest fire rate without ever reaching it)
+        assert!(difficulty.fire_interval > ENEMY_FIRE_INTERVAL_MIN);
+        assert!(difficulty.fire_interval < ENEMY_FIRE_INTERVAL_MIN + 0.01);
+        assert_close(
+            difficulty.projectile_speed,
+            PROJECTILE_SPEED * ENEMY_PROJECTILE_SPEED_SCALE * ENEMY_PROJECTILE_SPEED_MAX,
+        );
+        assert_close(difficulty.spawn_delay_scale, ENEMY_SPAWN_DELAY_SCALE_MIN);
+        assert_close(
+            difficulty.flight_speed,
+            CHALLENGE_SPEED * ENEMY_FLIGHT_SPEED_MAX,
+        );
+        assert_eq!(difficulty.aimed_chance, ENEMY_AIMED_CHANCE_MAX as f64);
+        assert_eq!(difficulty.homing_chance, HOMING_CHANCE_MAX as f64);
+        assert_eq!(difficulty.spread_chance, SPREAD_CHANCE_MAX as f64);
+        assert_eq!(difficulty.sine_wave_chance, SINE_WAVE_CHANCE_MAX as f64);
+    }
+
+    #[test]
+    fn every_level_stays_within_sane_bounds() {
+        let mut previous = classic_level(1);
+        for level in 1..=200 {
+            let difficulty = classic_level(level);
+            assert!(difficulty.fire_interval >= ENEMY_FIRE_INTERVAL_MIN);
+            assert!(difficulty.fire_interval <= ENEMY_FIRE_INTERVAL_START);
+            assert!(difficulty.spawn_delay_scale > 0.0);
+            for chance in [
+                difficulty.homing_chance,
+                difficulty.aimed_chance,
+                difficulty.spread_chance,
+                difficulty.sine_wave_chance,
+            ] {
+                assert!((0.0..=1.0).contains(&chance));
+            }
+            // Never gets easier
+            assert!(difficulty.fire_interval <= previous.fire_interval);
+            assert!(difficulty.projectile_speed >= previous.projectile_speed);
+            assert!(difficulty.flight_speed >= previous.flight_speed);
+            previous = difficulty;
+        }
+    }
+
+    #[test]
+    fn modern_mode_dives_slower() {
+        let classic = classic_level(10);
+        let modern = DifficultyCurve::for_level(10, &GameConfig::default(), GameMode::Modern);
+        assert_close(classic.dive_speed, classic.flight_speed);
+        assert_close(
+            modern.dive_speed,
+            classic.flight_speed * MODERN_DIVE_SPEED_SCALE,
+        );
+    }
+
+    fn assert_direction(actual: Vec2, expected: Vec2) {
+        assert!(
+            actual.abs_diff_eq(expected, 0.001),
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn aims_straight_at_a_stationary_player() {
+        let from = Vec2::new(0.0, 100.0);
+        let player = Vec2::new(20.0, -100.0);
+        assert_direction(
+            aim_at_player(from, player, 0.0, 0.5),
+            (player - from).normalize(),
+        );
+    }
+
+    #[test]
+    fn leads_a_moving_player() {
+        let from = Vec2::new(0.0, 100.0);
+        let player = Vec2::new(20.0, -100.0);
+        // Half a second ahead of a player moving right at 100
+        assert_direction(
+            aim_at_player(from, player, 100.0, 0.5),
+            (Vec2::new(70.0, -100.0) - from).normalize(),
+        );
+    }
+
+    #[test]
+    fn aim_is_clamped_to_the_max_angle() {
+        let max_angle = ENEMY_AIM_MAX_ANGLE.to_radians();
+        let from = Vec2::new(0.0, 100.0);
+        assert_direction(
+            aim_at_player(from, Vec2::new(1000.0, 90.0), 0.0, 0.5),
+            Vec2::new(max_angle.sin(), -max_angle.cos()),
+        );
+        assert_direction(
+            aim_at_player(from, Vec2::new(-1000.0, 90.0), 0.0, 0.5),
+            Vec2::new(-max_angle.sin(), -max_angle.cos()),
+        );
+        // (even when the player is above the enemy)
+        assert_direction(
+            aim_at_player(from, Vec2::new(10.0, 300.0), 0.0, 0.5),
+            Vec2::new(max_angle.sin(), -max_angle.cos()),
+        );
+    }
+
+    #[test]
+    fn shoots_straight_down_when_right_on_top_of_the_player() {
+        let from = Vec2::new(0.0, 100.0);
+        assert_direction(
+            aim_at_player(from, from, 0.0, 0.5),
+            ENEMY_PROJECTILE_DIRECTION,
+        );
+    }
+
+    #[test]
+    fn single_shots_go_where_theyre_aimed() {
+        let direction = Vec2::new(0.5, -1.0).normalize();
+        assert_eq!(
+            BulletPattern::Single.velocities(direction, 200.0),
+            [direction * 200.0]
+        );
+    }
+
+    #[test]
+    fn spreads_fan_out_around_the_aim() {
+        let direction = Vec2::new(0.5, -1.0).normalize();
+        let velocities = BulletPattern::Spread3.velocities(direction, 200.0);
+        assert_eq!(velocities.len(), 3);
+        assert_direction(velocities[1], direction * 200.0);
+        for velocity in &velocities {
+            assert_close(velocity.length(), 200.0);
+        }
+        let spread = SPREAD_ANGLE.to_radians();
+        assert_close(direction.angle_between(velocities[0]), -spread);
+        assert_close(direction.angle_between(velocities[2]), spread);
+    }
+
+    #[test]
+    fn sine_waves_start_straight_down() {
+        let direction = Vec2::new(0.5, -1.0).normalize();
+        assert_eq!(
+            BulletPattern::SineWave.velocities(direction, 200.0),
+            [ENEMY_PROJECTILE_DIRECTION * 200.0]
+        );
+    }
+
+    // Which enemies (by spawn order) dove on each of 1000 fixed steps
+    fn dive_sequence(seed: u64) -> Vec<Vec<u32>> {
+        let step = Duration::from_secs_f64(TIME_STEP as f64);
+
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<FixedTimesteps>()
+            .init_resource::<ChallengeStage>()
+            .insert_resource(GameState {
+                started: true,
+                paused: false,
+                intro: false,
+                stage_clear: false,
+                game_over: false,
+                turn_change: false,
+                continue_offer: false,
+                name_entry: false,
+                showing_leaderboard: false,
+                demo: false,
+                level: 1,
+                loading: false,
+            })
+            .insert_resource(classic_level(1))
+            .insert_resource(GameConfig::default())
+            .insert_resource(GameRng::new(Some(seed)))
+            // (a dive every 10 steps, so there's plenty of them to compare)
+            .insert_resource(DiveTimer(Timer::from_seconds(
+                TIME_STEP * 10.0,
+                TimerMode::Repeating,
+            )))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(
+                        FixedTimestep::step(TIME_STEP as f64).with_label(FIXED_STEP_NAME),
+                    )
+                    .with_system(start_dives),
+            );
+        app.world.spawn((Transform::default(), Player));
+        for index in 0..40 {
+            let translation =
+                Vec3::new((index % 10) as f32 * 40.0, (index / 10) as f32 * 40.0, 0.0);
+            let mut enemy = app.world.spawn((
+                Transform::from_translation(translation),
+                InFormation,
+                Collider,
+            ));
+            if index % 10 == 4 {
+                enemy.insert(Boss);
+            }
+        }
+
+        let start = Instant::now();
+        app.world.resource_mut::<Time>().update_with_instant(start);
+        let mut sequence = Vec::new();
+        for frame in 1..=1000 {
+            app.world
+                .resource_mut::<Time>()
+                .update_with_instant(start + step * frame);
+            app.update();
+
+            // Send everyone back to the formation so they can be picked again
+            let divers: Vec<Entity> = app
+                .world
+                .query_filtered::<Entity, With<Sortie>>()
+                .iter(&app.world)
+                .collect();
+            for diver in &divers {
+                app.world
+                    .entity_mut(*diver)
+                    .remove::<(Sortie, Velocity, Escort, Escorted)>();
+            }
+            let mut indices: Vec<u32> = divers.iter().map(|diver| diver.index()).collect();
+            indices.sort();
+            sequence.push(indices);
+        }
+        sequence
+    }
+
+    #[test]
+    fn same_seed_dives_the_same_way() {
+        let first = dive_sequence(7);
+        assert_eq!(first, dive_sequence(7));
+        assert!(first.iter().filter(|divers| !divers.is_empty()).count() >= 90);
+        assert_ne!(first, dive_sequence(8));
+    }
+}
@@ -0,0 +1,232 @@
+use bevy::prelude::*;
+
+use crate::{
+    shared::*,
+    wave::{EntrancePath, WaveDefinition},
+};
+
+pub struct FormationPlugin;
+
+impl Plugin for FormationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FormationGrid>()
+            .add_system(free_formation_slots);
+    }
+}
+
+const FORMATION_TOP_OFFSET: f32 = 200.0; // distance from the top of the playfield to the bottom row
+
+// An enemy holding a slot in the FormationGrid (the slot is freed when it's destroyed)
+#[derive(Component)]
+pub struct InFormation;
+
+// Every spot in the current level's formation, and which enemy is sitting in it.
+// Rows are centered on screen, so shorter rows sit in the middle of longer ones.
+#[derive(Resource, Default)]
+pub struct FormationGrid {
+    // Where the middle of the bottom row is
+    origin: Vec2,
+    // Distance between slots
+    gap: f32,
+    // rows[row][column]
+    slots: Vec<Vec<Option<Entity>>>,
+}
+
+impl FormationGrid {
+    // Build an empty formation with the rows a wave needs
+    // (each row is as long as the furthest column used in it)
+    pub fn for_wave(wave: &WaveDefinition, playfield: &Playfield, gap: f32) -> Self {
+        let mut row_lengths: Vec<usize> = Vec::new();
+        for group in &wave.groups {
+            if group.path != EntrancePath::Formation {
+                continue;
+            }
+            for wave_enemy in &group.enemies {
+                if row_lengths.len() <= wave_enemy.row {
+                    row_lengths.resize(wave_enemy.row + 1, 0);
+                }
+                row_lengths[wave_enemy.row] =
+                    row_lengths[wave_enemy.row].max(wave_enemy.column + 1);
+            }
+        }
+
+        FormationGrid {
+            origin: Vec2::new(0.0, playfield.edge_vertical() - FORMATION_TOP_OFFSET),
+            gap,
+            slots: row_lengths
+                .iter()
+                .map(|row_length| vec![None; *row_length])
+                .collect(),
+        }
+    }
+
+    // Where a slot is on screen (None if the formation doesn't have it)
+    pub fn slot_world_position(&self, row: usize, column: usize) -> Option<Vec3> {
+        let row_length = self.slots.get(row)?.len();
+        if column >= row_length {
+            return None;
+        }
+        let x = (column as f32 - (row_length - 1) as f32 / 2.0) * self.gap;
+        let y = row as f32 * self.gap;
+        Some((self.origin + Vec2::new(x, y)).extend(1.0))
+    }
+
+    // Put an enemy in a slot, returning false if it's taken (or doesn't exist)
+    pub fn occupy(&mut self, row: usize, column: usize, entity: Entity) -> bool {
+        match self
+            .slots
+            .get_mut(row)
+            .and_then(|slots| slots.get_mut(column))
+        {
+            Some(slot @ None) => {
+                *slot = Some(entity);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // The row and column of the slot an enemy holds
+    pub fn slot_of(&self, entity: Entity) -> Option<(usize, usize)> {
+        self.slots.iter().enumerate().find_map(|(row, slots)| {
+            slots
+                .iter()
+                .position(|slot| *slot == Some(entity))
+                .map(|column| (row, column))
+        })
+    }
+
+    // The empty slot closest to a position
+    pub fn nearest_free_slot(&self, translation: Vec3) -> Option<(usize, usize)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .flat_map(|(row, slots)| {
+                slots
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, slot)| slot.is_none())
+                    .map(move |(column, _)| (row, column))
+            })
+            .min_by(|a, b| {
+                let distance = |(row, column)| {
+                    self.slot_world_position(row, column)
+                        .unwrap_or_default()
+                        .distance_squared(translation)
+                };
+                distance(*a).total_cmp(&distance(*b))
+            })
+    }
+
+    // Empty whichever slot this enemy was in
+    pub fn free(&mut self, entity: Entity) {
+        for slot in self.slots.iter_mut().flatten() {
+            if *slot == Some(entity) {
+                *slot = None;
+            }
+        }
+    }
+}
+
+// Give up the slots of enemies that were destroyed
+fn free_formation_slots(
+    mut formation_grid: ResMut<FormationGrid>,
+    removed_slots: RemovedComponents<InFormation>,
+) {
+    for entity in removed_slots.iter() {
+        formation_grid.free(entity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAP: f32 = 50.0;
+
+    // A bottom row of 4 with a row of 2 above it (plus a group flying in that doesn't get slots)
+    fn test_grid() -> FormationGrid {
+        let wave: WaveDefinition = ron::from_str(
+            "(groups: [
+                (enemies: [
+                    (kind: GreenBug, row: 0, column: 0),
+                    (kind: GreenBug, row: 0, column: 3),
+                    (kind: Boss, row: 1, column: 1),
+                ]),
+                (path: LoopLeft, enemies: [(kind: GreenBug, row: 5, column: 9)]),
+            ])",
+        )
+        .unwrap();
+        FormationGrid::for_wave(&wave, &Playfield::default(), GAP)
+    }
+
+    fn bottom_row_y() -> f32 {
+        Playfield::default().edge_vertical() - FORMATION_TOP_OFFSET
+    }
+
+    #[test]
+    fn rows_are_centered_on_screen() {
+        let grid = test_grid();
+        let bottom = bottom_row_y();
+        let xs: Vec<f32> = (0..4)
+            .map(|column| grid.slot_world_position(0, column).unwrap().x)
+            .collect();
+        assert_eq!(xs, [-75.0, -25.0, 25.0, 75.0]);
+        assert_eq!(grid.slot_world_position(0, 0).unwrap().y, bottom);
+
+        // The shorter row sits in the middle of the longer one
+        assert_eq!(
+            grid.slot_world_position(1, 0),
+            Some(Vec3::new(-25.0, bottom + GAP, 1.0))
+        );
+        assert_eq!(
+            grid.slot_world_position(1, 1),
+            Some(Vec3::new(25.0, bottom + GAP, 1.0))
+        );
+    }
+
+    #[test]
+    fn slots_outside_the_formation_have_no_position() {
+        let grid = test_grid();
+        assert_eq!(grid.slot_world_position(0, 4), None);
+        assert_eq!(grid.slot_world_position(1, 2), None);
+        // (only enemies entering in formation get rows)
+        assert_eq!(grid.slot_world_position(5, 9), None);
+    }
+
+    #[test]
+    fn taken_slots_cant_be_occupied_until_theyre_freed() {
+        let mut grid = test_grid();
+        let first = Entity::from_raw(1);
+        let second = Entity::from_raw(2);
+
+        assert!(grid.occupy(0, 2, first));
+        assert!(!grid.occupy(0, 2, second));
+        assert_eq!(grid.slot_of(first), Some((0, 2)));
+        assert_eq!(grid.slot_of(second), None);
+
+        grid.free(first);
+        assert_eq!(grid.slot_of(first), None);
+        assert!(grid.occupy(0, 2, second));
+        assert!(!grid.occupy(3, 0, first));
+    }
+
+    #[test]
+    fn nearest_free_slot_skips_taken_ones() {
+        let mut grid = test_grid();
+        let bottom = bottom_row_y();
+        let near_the_left = Vec3::new(-80.0, bottom, 1.0);
+        assert_eq!(grid.nearest_free_slot(near_the_left), Some((0, 0)));
+
+        grid.occupy(0, 0, Entity::from_raw(1));
+        assert_eq!(grid.nearest_free_slot(near_the_left), Some((0, 1)));
+
+        // Nowhere left to go once the formation is full
+        for (row, columns) in [(0, 4), (1, 2)] {
+            for column in 0..columns {
+                grid.occupy(row, column, Entity::from_raw(10 + column as u32));
+            }
+        }
+        assert_eq!(grid.nearest_free_slot(near_the_left), None);
+    }
+}
@@ -0,0 +1,51 @@
+use bevy::{input::InputSystem, prelude::*};
+
+use crate::{settings::PauseMenu, shared::*};
+
+// Frame-stepping for debugging movement and collisions (only built with `--features debug-tools`).
+// While paused:
+// . - Run exactly one fixed step
+// , - Hold to run in slow motion (10% speed)
+pub struct FrameStepPlugin;
+
+impl Plugin for FrameStepPlugin {
+    fn build(&self, app: &mut App) {
+        // (before the fixed step in Update decides whether to run)
+        app.add_system_to_stage(CoreStage::PreUpdate, step_frames.after(InputSystem));
+    }
+}
+
+const FRAME_STEP_KEY: KeyCode = KeyCode::Period;
+const SLOW_MOTION_KEY: KeyCode = KeyCode::Comma;
+// Slow motion runs one step every this many frames
+const SLOW_MOTION_FRAMES: usize = 10;
+
+// Let a single fixed step through this frame, while staying paused
+// (limit_fixed_steps in game.rs is what lets it through, everything else stays frozen)
+fn step_frames(
+    keyboard_input: Res<Input<KeyCode>>,
+    pause_menu: Res<PauseMenu>,
+    game_state: Res<GameState>,
+    mut frame_step: ResMut<FrameStep>,
+    mut slow_motion_frames: Local<usize>,
+) {
+    frame_step.step_taken = false;
+    frame_step.stepping = false;
+
+    // (rebinding a key takes any key, including these)
+    if !game_state.paused || !game_state.started || game_state.demo || pause_menu.listening {
+        *slow_motion_frames = 0;
+        return;
+    }
+
+    let step = if keyboard_input.just_pressed(FRAME_STEP_KEY) {
+        true
+    } else if keyboard_input.pressed(SLOW_MOTION_KEY) {
+        *slow_motion_frames += 1;
+        slow_motion_frames.is_multiple_of(SLOW_MOTION_FRAMES)
+    } else {
+        *slow_motion_frames = 0;
+        false
+    };
+    frame_step.stepping = step;
+}
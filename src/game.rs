@@ -0,0 +1,1073 @@
+use bevy::{
+    app::AppExit,
+    asset::{HandleId, LoadState},
+    ecs::schedule::ShouldRun,
+    prelude::*,
+    render::camera::ScalingMode,
+    sprite::MaterialMesh2dBundle,
+    time::{FixedTimestep, FixedTimesteps},
+    window::{WindowFocused, WindowMode},
+};
+
+use crate::{
+    cli::LaunchOptions,
+    enemy::EnemyProjectileTimer,
+    material::{BackgroundLayer, BackgroundScroll, CustomMaterial, SCROLL_SPEED_DEFAULT},
+    player::ProjectileTimer,
+    projectile::{Pooled, ProjectilePool},
+    settings::{GameSettingsState, KeyBindings, PauseMenu},
+    shared::*,
+};
+
+pub struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(IntroTimer(Timer::from_seconds(
+            INTRO_TIME_LIMIT,
+            TimerMode::Once,
+        )))
+        .insert_resource(StageClearTimer(Timer::from_seconds(
+            STAGE_CLEAR_TIME,
+            TimerMode::Once,
+        )))
+        .init_resource::<Playfield>()
+        .insert_resource(TurnChangeTimer(Timer::from_seconds(
+            TURN_CHANGE_TIME,
+            TimerMode::Once,
+        )))
+        .init_resource::<Players>()
+        .init_resource::<Credits>()
+        .init_resource::<GameMode>()
+        .init_resource::<ContinueCountdown>()
+        .init_resource::<ComboState>()
+        .init_resource::<Transition>()
+        .init_resource::<Cheats>()
+        .init_resource::<FrameStep>()
+        .init_resource::<TimeScale>()
+        .insert_resource(GameState {
+            started: false,
+            paused: false,
+            intro: false,
+            stage_clear: false,
+            game_over: false,
+            turn_change: false,
+            continue_offer: false,
+            name_entry: false,
+            showing_leaderboard: false,
+            demo: false,
+            level: 1,
+            loading: true,
+        })
+        .init_resource::<LoadingAssets>()
+        .init_resource::<ChallengeStage>()
+        .init_resource::<ShotStats>()
+        .add_event::<GameStartEvent>()
+        .add_event::<DemoEndEvent>()
+        .add_event::<ResetGameEvent>()
+        .add_event::<EnemyDeathEvent>()
+        .add_event::<ProjectileEvent>()
+        .add_event::<PlayerDeathEvent>()
+        .add_event::<NearMissEvent>()
+        .add_event::<PlaySoundEvent>()
+        .add_event::<NewLevelEvent>()
+        .add_event::<ScoreChangedEvent>()
+        .add_event::<PowerUpEvent>()
+        .add_startup_system_to_stage(StartupStage::PreStartup, load_shared_assets)
+        .add_startup_system(setup_game)
+        // The other plugins run their gameplay systems on this fixed timestep
+        // (kept from running away after a long frame, see limit_fixed_steps)
+        .add_system_set(
+            SystemSet::new().with_run_criteria(
+                FixedTimestep::step(TIME_STEP as f64)
+                    .with_label(FIXED_STEP_NAME)
+                    .label(FixedStepTimer),
+            ),
+        )
+        .add_system_set(SystemSet::new().with_run_criteria(
+            RunCriteria::pipe(FixedStepTimer, limit_fixed_steps).label(FixedStep),
+        ))
+        .add_system(check_assets_loaded)
+        .add_system(select_player_count)
+        .add_system(select_game_mode)
+        .add_system(insert_coin)
+        .add_system(start_game.after(select_player_count).after(insert_coin))
+        .add_system(start_at_level.before(reset_game))
+        .add_system(quit_headless_at_game_over.after(count_down_continue))
+        .add_system(run_transition.after(start_game))
+        .add_system(
+            reset_game
+                .after(run_transition)
+                .before(GameSystem::PlayIntro),
+        )
+        .add_system(pause_game.label(GameSystem::PauseGame))
+        .add_system(
+            pause_on_focus_lost
+                .after(GameSystem::PauseGame)
+                .before(GameSystem::UpdatePauseMenu),
+        )
+        .add_system(play_intro.label(GameSystem::PlayIntro))
+        .add_system(
+            check_level_complete
+                .label(GameSystem::CheckLevelComplete)
+                .after(GameSystem::SpawnEnemies)
+                .after(GameSystem::KillPlayer),
+        )
+        .add_system(
+            update_player_score
+                .after(GameSystem::Collision)
+                .after(GameSystem::CheckLevelComplete)
+                .after(reset_game),
+        )
+        .add_system(
+            play_stage_clear
+                .label(GameSystem::PlayStageClear)
+                .after(GameSystem::CheckLevelComplete),
+        )
+        .add_system(change_turns.after(GameSystem::KillPlayer))
+        .add_system(
+            count_down_continue
+                .after(GameSystem::KillPlayer)
+                .after(start_game),
+        )
+        .add_system(
+            cleanup_entities
+                .after(reset_game)
+                .after(change_turns)
+                .after(count_down_continue)
+                .after(GameSystem::PlayIntro)
+                .after(GameSystem::PlayStageClear),
+        )
+        .add_system(update_background_scroll)
+        .add_system(toggle_fullscreen)
+        .add_system(bevy::window::close_on_esc);
+    }
+}
+
+// The fixed timestep itself, before limit_fixed_steps gets a say (systems use FixedStep)
+#[derive(RunCriteriaLabel, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+struct FixedStepTimer;
+
+// Timer used to track playback of intro
+#[derive(Resource)]
+struct IntroTimer(Timer);
+
+// Timer used to hold on the "STAGE N" screen before the next level starts
+#[derive(Resource)]
+struct StageClearTimer(Timer);
+
+// Timer used to hold on the "PLAYER N" screen before the next player's turn starts
+#[derive(Resource)]
+struct TurnChangeTimer(Timer);
+
+const INTRO_TIME_LIMIT: f32 = 6.0; // seconds
+const STAGE_CLEAR_TIME: f32 = 3.0; // seconds (long enough for the stage clear jingle)
+const TURN_CHANGE_TIME: f32 = 2.5; // seconds
+const MAX_CREDITS: usize = 99;
+// Picking 1 or 2 players on the start screen
+const ONE_PLAYER_KEY: KeyCode = KeyCode::Up;
+const TWO_PLAYERS_KEY: KeyCode = KeyCode::Down;
+// Picking classic or modern mode on the start screen
+const CLASSIC_MODE_KEY: KeyCode = KeyCode::Left;
+const MODERN_MODE_KEY: KeyCode = KeyCode::Right;
+const FULLSCREEN_KEY: KeyCode = KeyCode::F11;
+// Big enough to cover the rest of any window
+const LETTERBOX_SIZE: f32 = 10000.0;
+// In front of everything in the game (but behind the UI)
+const LETTERBOX_LAYER: f32 = 100.0;
+// Background layers, back to front: texture, scroll speed (times the default speed), and depth.
+// Closer layers scroll faster for a parallax effect.
+const BACKGROUND_LAYERS: [(&str, f32, f32); 2] = [
+    ("textures/space/space.png", 1.0, 0.0),
+    ("textures/space/stars_near.png", 2.5, 0.1),
+];
+// Flying in during the intro
+const INTRO_SCROLL_SPEED: f32 = 4.0;
+// Challenge stages are levels 3, 7, 11, etc (like the arcade)
+const CHALLENGE_STAGE_FIRST: usize = 3;
+const CHALLENGE_STAGE_INTERVAL: usize = 4;
+const CHALLENGE_POINTS_PER_HIT: usize = 100;
+const CHALLENGE_PERFECT_BONUS: usize = 10000;
+const ACCURACY_POINTS_PER_PERCENT: f32 = 10.0;
+
+// Create the mesh and materials every sprite shares
+// (runs before the other startup systems so they can spawn things with them)
+fn load_shared_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<CustomMaterial>>,
+    asset_server: Res<AssetServer>,
+    mut loading_assets: ResMut<LoadingAssets>,
+) {
+    let mut sprite_material = |path: &str| {
+        materials.add(CustomMaterial {
+            color_texture: Some(loading_assets.load(&asset_server, path)),
+            ..default()
+        })
+    };
+
+    commands.insert_resource(SharedAssets {
+        quad: meshes.add(Mesh::from(shape::Quad::default())),
+        player: sprite_material("sprites/player_default.png"),
+        projectile: sprite_material("sprites/player_projectile.png"),
+    });
+}
+
+fn setup_game(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<CustomMaterial>>,
+    asset_server: Res<AssetServer>,
+    mut loading_assets: ResMut<LoadingAssets>,
+    shared_assets: Res<SharedAssets>,
+    playfield: Res<Playfield>,
+) {
+    // Camera
+    // Always shows the whole playfield, scaled to fit the window
+    let mut camera = Camera2dBundle::default();
+    camera.projection.scaling_mode = ScalingMode::Auto {
+        min_width: playfield.width,
+        min_height: playfield.height,
+    };
+    commands.spawn(camera);
+
+    // Letterbox
+    // Black bars covering everything outside the playfield (when the window is a different shape)
+    for side in [Vec2::X, Vec2::NEG_X, Vec2::Y, Vec2::NEG_Y] {
+        let playfield_size = Vec2::new(playfield.width, playfield.height);
+        commands.spawn(SpriteBundle {
+            sprite: Sprite {
+                color: Color::BLACK,
+                custom_size: Some(Vec2::splat(LETTERBOX_SIZE)),
+                ..default()
+            },
+            transform: Transform::from_translation(
+                (side * (playfield_size + LETTERBOX_SIZE) / 2.0).extend(LETTERBOX_LAYER),
+            ),
+            ..default()
+        });
+    }
+
+    // Background
+    // Each layer needs its own material so it can scroll at its own speed
+    for (texture, scroll_speed, depth) in BACKGROUND_LAYERS {
+        commands.spawn((
+            MaterialMesh2dBundle {
+                // mesh: meshes.add(shape::Plane { size: 3.0 }.into()).into(),
+                mesh: shared_assets.quad.clone().into(),
+                transform: Transform::from_xyz(0.0, 0.0, depth).with_scale(Vec3::new(
+                    playfield.width,
+                    playfield.height,
+                    0.0,
+                )),
+                // material: materials.add(ColorMaterial::from(Color::TURQUOISE)),
+                material: materials.add(CustomMaterial {
+                    color_texture: Some(loading_assets.load(&asset_server, texture)),
+                    tile: 1.0,
+                    scroll_speed: SCROLL_SPEED_DEFAULT * scroll_speed,
+                    ..default()
+                }),
+                ..default()
+            },
+            BackgroundLayer,
+        ));
+    }
+}
+
+// Wait for every asset to finish loading before letting the game start.
+// If any fail, hold on the loading screen and list them (instead of showing broken sprites).
+fn check_assets_loaded(
+    asset_server: Res<AssetServer>,
+    mut game_state: ResMut<GameState>,
+    mut loading_assets: ResMut<LoadingAssets>,
+) {
+    if !game_state.loading || !loading_assets.failed.is_empty() {
+        return;
+    }
+
+    // Try the fallback for anything that failed and has one
+    for fallback in &mut loading_assets.fallbacks {
+        if fallback.handle.is_none()
+            && asset_server.get_load_state(fallback.original) == LoadState::Failed
+        {
+            println!("[LOADING] Falling back to {}", fallback.path);
+            fallback.handle = Some(asset_server.load_untyped(fallback.path.as_str()));
+        }
+    }
+
+    let load_states: Vec<(HandleId, LoadState)> = loading_assets
+        .handles
+        .iter()
+        .map(|handle| {
+            (
+                handle.id,
+                loading_assets.load_state(&asset_server, handle.id),
+            )
+        })
+        .collect();
+    let loaded = load_states
+        .iter()
+        .filter(|(_, load_state)| *load_state == LoadState::Loaded)
+        .count();
+    // (only touch it when it changes, so the loading screen knows when to update)
+    if loaded != loading_assets.loaded {
+        loading_assets.loaded = loaded;
+    }
+
+    if loaded == load_states.len() {
+        println!("[LOADING] Loaded {} assets", loading_assets.handles.len());
+        game_state.loading = false;
+        return;
+    }
+
+    let failed: Vec<String> = load_states
+        .iter()
+        .filter(|(_, load_state)| *load_state == LoadState::Failed)
+        .map(|(id, _)| {
+            // (a fallback failing too reports the fallback)
+            let fallback = loading_assets
+                .fallbacks
+                .iter()
+                .find(|fallback| fallback.original == *id);
+            let id = fallback
+                .and_then(|fallback| fallback.handle.as_ref())
+                .map_or(*id, |handle| handle.id);
+            asset_server.get_handle_path(id).map_or_else(
+                || "unknown".to_string(),
+                |path| path.path().display().to_string(),
+            )
+        })
+        .collect();
+    for path in &failed {
+        println!("[LOADING] Failed to load {}", path);
+    }
+    if !failed.is_empty() {
+        loading_assets.failed = failed;
+    }
+}
+
+// Choose between a 1 or 2 player game on the start screen
+fn select_player_count(
+    game_state: Res<GameState>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut players: ResMut<Players>,
+) {
+    if game_state.started
+        || game_state.continue_offer
+        || game_state.game_over
+        || game_state.name_entry
+        || game_state.showing_leaderboard
+        || game_state.loading
+    {
+        return;
+    }
+
+    let count = if keyboard_input.just_pressed(ONE_PLAYER_KEY) {
+        1
+    } else if keyboard_input.just_pressed(TWO_PLAYERS_KEY) {
+        2
+    } else {
+        return;
+    };
+    if players.count != count {
+        println!("[INPUT] {} player game selected", count);
+        players.count = count;
+    }
+}
+
+fn select_game_mode(
+    game_state: Res<GameState>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut game_mode: ResMut<GameMode>,
+) {
+    if game_state.started
+        || game_state.continue_offer
+        || game_state.game_over
+        || game_state.name_entry
+        || game_state.showing_leaderboard
+        || game_state.loading
+    {
+        return;
+    }
+
+    let mode = if keyboard_input.just_pressed(CLASSIC_MODE_KEY) {
+        GameMode::Classic
+    } else if keyboard_input.just_pressed(MODERN_MODE_KEY) {
+        GameMode::Modern
+    } else {
+        return;
+    };
+    if *game_mode != mode {
+        println!("[INPUT] {} mode selected", mode.label().to_lowercase());
+        *game_mode = mode;
+    }
+}
+
+// Add a credit when a coin goes in (only counts when free play is off)
+fn insert_coin(
+    keyboard_input: Res<Input<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    game_settings: Res<GameSettingsState>,
+    pause_menu: Res<PauseMenu>,
+    mut credits: ResMut<Credits>,
+) {
+    if game_settings.freeplay || pause_menu.listening {
+        return;
+    }
+
+    if keyboard_input.just_pressed(key_bindings.coin) {
+        credits.0 = (credits.0 + 1).min(MAX_CREDITS);
+        println!("[INPUT] Coin inserted, {} credits", credits.0);
+    }
+}
+
+fn start_game(
+    game_state: Res<GameState>,
+    mut transition: ResMut<Transition>,
+    keyboard_input: Res<Input<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    game_settings: Res<GameSettingsState>,
+    pause_menu: Res<PauseMenu>,
+    players: Res<Players>,
+    mut credits: ResMut<Credits>,
+    mut reset_events: EventWriter<ResetGameEvent>,
+) {
+    // If game hasn't started, detect fire/start key to start game
+    // (after the continue countdown and game over screen finish)
+    // (fading out from the start screen first)
+    if !game_state.started
+        && !game_state.continue_offer
+        && !game_state.game_over
+        && !game_state.name_entry
+        && !game_state.showing_leaderboard
+        && !game_state.loading
+        && !transition.is_active()
+        && (keyboard_input.just_pressed(key_bindings.fire)
+            | keyboard_input.just_pressed(key_bindings.start))
+        && pay_for_game(&game_settings, &mut credits, &players)
+    {
+        println!("[INPUT] Game Started");
+        transition.start(TransitionAction::StartGame);
+    }
+
+    // Restart the current run from scratch
+    // (unless we're rebinding a key - then the press belongs to the rebind menu)
+    if game_state.started
+        && !game_state.demo
+        && !pause_menu.listening
+        && keyboard_input.just_pressed(key_bindings.restart)
+        && pay_for_game(&game_settings, &mut credits, &players)
+    {
+        println!("[INPUT] Game Restarted");
+        reset_events.send_default();
+    }
+}
+
+// Started with `--level`? Jump straight into a game once everything's loaded
+fn start_at_level(
+    game_state: Res<GameState>,
+    launch_options: Res<LaunchOptions>,
+    mut reset_events: EventWriter<ResetGameEvent>,
+    mut started: Local<bool>,
+) {
+    if *started || game_state.loading {
+        return;
+    }
+    let Some(level) = launch_options.level else {
+        return;
+    };
+
+    println!("[GAME] Starting at level {}", level);
+    *started = true;
+    reset_events.send_default();
+}
+
+// Headless runs play a single game, then print how it went
+fn quit_headless_at_game_over(
+    game_state: Res<GameState>,
+    launch_options: Res<LaunchOptions>,
+    players: Res<Players>,
+    mut app_exit_events: EventWriter<AppExit>,
+) {
+    if !launch_options.headless || !game_state.game_over {
+        return;
+    }
+
+    println!("[GAME] Reached level {}", game_state.level);
+    for (player, progress) in players.progress.iter().take(players.count).enumerate() {
+        println!("[GAME] Player {} scored {}", player + 1, progress.score);
+    }
+    app_exit_events.send(AppExit);
+}
+
+// Fade out, do whatever the transition was for while the screen is black, then fade back in
+fn run_transition(
+    time: Res<Time>,
+    mut game_state: ResMut<GameState>,
+    mut transition: ResMut<Transition>,
+    mut reset_events: EventWriter<ResetGameEvent>,
+    mut demo_end_events: EventWriter<DemoEndEvent>,
+) {
+    if !transition.is_active() {
+        return;
+    }
+    transition.timer.tick(frame_delta(&time));
+
+    if !transition.is_black() {
+        return;
+    }
+    if let Some(action) = transition.action.take() {
+        match action {
+            TransitionAction::StartGame => reset_events.send_default(),
+            TransitionAction::StartDemo => {
+                game_state.demo = true;
+                reset_events.send_default();
+            }
+            // Back to the start screen
+            TransitionAction::EndDemo => {
+                game_state.demo = false;
+                game_state.started = false;
+                game_state.intro = false;
+                game_state.stage_clear = false;
+                game_state.turn_change = false;
+                game_state.continue_offer = false;
+                demo_end_events.send_default();
+            }
+        }
+    }
+}
+
+// Unless it's free play, every player needs a credit to play (or continue)
+fn pay_for_game(
+    game_settings: &GameSettingsState,
+    credits: &mut Credits,
+    players: &Players,
+) -> bool {
+    if game_settings.freeplay {
+        return true;
+    }
+    if credits.0 < players.count {
+        println!("[INPUT] Not enough credits for {} players", players.count);
+        return false;
+    }
+    credits.0 -= players.count;
+    true
+}
+
+// Set up a fresh run (coming from the title screen after a game over, or restarting mid-game)
+// then kick off the intro. Leftover enemies and projectiles are swept up by cleanup_entities.
+fn reset_game(
+    mut commands: Commands,
+    game_config: Res<GameConfig>,
+    game_settings: Res<GameSettingsState>,
+    launch_options: Res<LaunchOptions>,
+    mut combo: ResMut<ComboState>,
+    mut game_rng: ResMut<GameRng>,
+    mut reset_events: EventReader<ResetGameEvent>,
+    mut game_state: ResMut<GameState>,
+    mut players: ResMut<Players>,
+    mut weapon_mode: ResMut<WeaponMode>,
+    mut challenge_stage: ResMut<ChallengeStage>,
+    mut start_events: EventWriter<GameStartEvent>,
+    playfield: Res<Playfield>,
+    mut player_query: Query<(Entity, &mut Transform, &mut Visibility), With<Player>>,
+    player_explosion_query: Query<Entity, With<PlayerExploding>>,
+    shield_ring_query: Query<Entity, With<ShieldRing>>,
+) {
+    if reset_events.iter().count() == 0 {
+        return;
+    }
+
+    // Print the seed so a game can be replayed with `--seed`
+    game_rng.restart();
+    println!("[GAME] Seed {}", game_rng.seed);
+
+    game_state.started = true;
+    game_state.paused = false;
+    game_state.stage_clear = false;
+    game_state.game_over = false;
+    game_state.turn_change = false;
+    game_state.continue_offer = false;
+    // (`--level` skips ahead)
+    let starting_level = launch_options.level.unwrap_or(1);
+    game_state.level = starting_level;
+    // Everyone starts fresh (player 1 goes first)
+    players.active = 0;
+    for progress in &mut players.progress {
+        *progress = PlayerProgress {
+            score: 0,
+            lives: game_config.player_lives,
+            level: starting_level,
+        };
+    }
+    *weapon_mode = WeaponMode::Single;
+    *challenge_stage = ChallengeStage::default();
+    *combo = ComboState {
+        enabled: game_settings.combo_scoring,
+        ..default()
+    };
+
+    // Bring back the ship if it blew up
+    for (player_entity, mut player_transform, mut visibility) in &mut player_query {
+        player_transform.translation = playfield.player_starting_position();
+        player_transform.rotation = Quat::IDENTITY;
+        visibility.is_visible = true;
+        commands
+            .entity(player_entity)
+            .remove::<Respawning>()
+            .remove::<Invincible>()
+            .remove::<DualFighter>()
+            .remove::<Shield>()
+            .remove::<RapidFire>()
+            .insert(Banking::default());
+    }
+    for explosion_entity in &player_explosion_query {
+        commands.entity(explosion_entity).despawn();
+    }
+    for shield_ring_entity in &shield_ring_query {
+        commands.entity(shield_ring_entity).despawn_recursive();
+    }
+
+    // Let other systems know we started (like intro sequence)
+    start_events.send_default();
+}
+
+// Add up the points for every enemy destroyed (and enemy shot cancelled out), and let everyone know when a score changes.
+// With combo scoring, each kill is multiplied by how long the current chain of kills is.
+fn update_player_score(
+    time: Res<Time>,
+    game_state: Res<GameState>,
+    mut players: ResMut<Players>,
+    mut combo: ResMut<ComboState>,
+    mut enemy_death_events: EventReader<EnemyDeathEvent>,
+    mut player_death_events: EventReader<PlayerDeathEvent>,
+    mut score_changed_events: EventWriter<ScoreChangedEvent>,
+    // The scores we last sent out
+    mut last_scores: Local<Option<[usize; MAX_PLAYERS]>>,
+) {
+    // The chain breaks when the window runs out, or the player gets hit
+    let player_hit = player_death_events.iter().count() > 0;
+    if (!game_state.paused && combo.timer.tick(frame_delta(&time)).just_finished()) || player_hit {
+        combo.multiplier = 0;
+    }
+
+    for event in enemy_death_events.iter() {
+        let mut points = event.points;
+        // (cancelled shots are too small to count towards a combo)
+        if combo.enabled && !event.shot_cancelled {
+            combo.multiplier = (combo.multiplier + 1).min(COMBO_MULTIPLIER_MAX);
+            combo.timer.reset();
+            points *= combo.multiplier;
+        }
+        players.current_mut().score += points;
+    }
+
+    // Points can be awarded without an event too (like challenge stage bonuses, or resetting to 0)
+    for (player, progress) in players.progress.iter().enumerate() {
+        let changed = match *last_scores {
+            Some(scores) => scores[player] != progress.score,
+            None => true,
+        };
+        if changed {
+            score_changed_events.send(ScoreChangedEvent {
+                player,
+                score: progress.score,
+            });
+        }
+    }
+    *last_scores = Some(players.progress.map(|progress| progress.score));
+}
+
+// Once the last player's explosion is over, clear the screen and show whose turn it is.
+// Their level starts over from the beginning.
+fn change_turns(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut game_state: ResMut<GameState>,
+    mut turn_change_timer: ResMut<TurnChangeTimer>,
+    mut new_level_events: EventWriter<NewLevelEvent>,
+    explosion_query: Query<(), With<PlayerExploding>>,
+    enemy_query: Query<Entity, Or<(With<Enemy>, With<CapturedFighter>)>>,
+) {
+    if !game_state.turn_change || game_state.paused || !explosion_query.is_empty() {
+        return;
+    }
+
+    if turn_change_timer.0.elapsed().is_zero() {
+        for entity in &enemy_query {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    if turn_change_timer.0.tick(frame_delta(&time)).just_finished() {
+        game_state.turn_change = false;
+        turn_change_timer.0.reset();
+        new_level_events.send_default();
+    }
+}
+
+// After the last ship is lost, count down from 9 - pressing start before it runs out keeps going
+// from the same stage with fresh ships, but the score goes back to 0 (like the arcade).
+// Letting it run out moves on to the game over screen.
+fn count_down_continue(
+    mut commands: Commands,
+    time: Res<Time>,
+    game_config: Res<GameConfig>,
+    keyboard_input: Res<Input<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    game_settings: Res<GameSettingsState>,
+    mut game_state: ResMut<GameState>,
+    mut players: ResMut<Players>,
+    mut credits: ResMut<Credits>,
+    mut countdown: ResMut<ContinueCountdown>,
+    mut sound_events: EventWriter<PlaySoundEvent>,
+    mut new_level_events: EventWriter<NewLevelEvent>,
+    enemy_query: Query<Entity, Or<(With<Enemy>, With<CapturedFighter>)>>,
+) {
+    if !game_state.continue_offer {
+        return;
+    }
+
+    if (keyboard_input.just_pressed(key_bindings.fire)
+        | keyboard_input.just_pressed(key_bindings.start))
+        && pay_for_game(&game_settings, &mut credits, &players)
+    {
+        println!("[GAME] Continue on stage {}", game_state.level);
+        for progress in &mut players.progress {
+            progress.score = 0;
+            progress.lives = game_config.player_lives;
+        }
+        game_state.continue_offer = false;
+        game_state.started = true;
+        *countdown = ContinueCountdown::default();
+
+        // The stage starts over from the beginning
+        for entity in &enemy_query {
+            commands.entity(entity).despawn_recursive();
+        }
+        new_level_events.send_default();
+        return;
+    }
+
+    if !countdown.timer.tick(frame_delta(&time)).just_finished() {
+        return;
+    }
+    if countdown.seconds_left == 0 {
+        println!("[GAME] Game Over");
+        game_state.continue_offer = false;
+        game_state.game_over = true;
+        *countdown = ContinueCountdown::default();
+        return;
+    }
+    countdown.seconds_left -= 1;
+    sound_events.send(PlaySoundEvent(SoundId::CountdownTick));
+}
+
+// A long frame (like a browser tab coming back from the background) only plays through
+// MAX_FRAME_TIME worth of fixed steps, and the rest of the built up time is skipped.
+// Frame-stepping lets exactly one fixed step through while paused.
+// Bullet time slows the game down by skipping steps (the ones that do run play out like always).
+fn limit_fixed_steps(
+    In(should_run): In<ShouldRun>,
+    time: Res<Time>,
+    fixed_timesteps: Res<FixedTimesteps>,
+    time_scale: Res<TimeScale>,
+    game_state: Res<GameState>,
+    mut frame_step: ResMut<FrameStep>,
+    // (when the current frame started, and the steps run so far this frame)
+    mut frame_steps: Local<(f64, usize)>,
+    // How much of a step the TimeScale has let through so far
+    mut step_credit: Local<f32>,
+) -> ShouldRun {
+    if frame_steps.0 != time.elapsed_seconds_f64() {
+        *frame_steps = (time.elapsed_seconds_f64(), 0);
+    }
+
+    if frame_step.stepping {
+        if frame_step.step_taken {
+            return ShouldRun::No;
+        }
+        frame_step.step_taken = true;
+        return ShouldRun::Yes;
+    }
+
+    // Nothing moves while paused (the steps that come due are dropped, so there's no catching up after)
+    if game_state.paused {
+        return match should_run {
+            ShouldRun::Yes | ShouldRun::YesAndCheckAgain => ShouldRun::NoAndCheckAgain,
+            _ => should_run,
+        };
+    }
+
+    let max_steps = (MAX_FRAME_TIME / fixed_step_seconds(&fixed_timesteps)).ceil() as usize;
+    match should_run {
+        // (checking again lets the timestep keep counting down the skipped steps)
+        ShouldRun::Yes | ShouldRun::YesAndCheckAgain if frame_steps.1 >= max_steps => {
+            ShouldRun::NoAndCheckAgain
+        }
+        ShouldRun::Yes | ShouldRun::YesAndCheckAgain => {
+            *step_credit = (*step_credit + time_scale.0).min(1.0);
+            if *step_credit < 1.0 {
+                return ShouldRun::NoAndCheckAgain;
+            }
+            *step_credit -= 1.0;
+            frame_steps.1 += 1;
+            should_run
+        }
+        _ => should_run,
+    }
+}
+
+fn pause_game(
+    mut game_state: ResMut<GameState>,
+    keyboard_input: Res<Input<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    pause_menu: Res<PauseMenu>,
+) {
+    // If game has started, check for pause key to pause game
+    // (unless we're rebinding a key - then the press belongs to the rebind menu,
+    // or it's the demo - then any key ends it)
+    if game_state.started
+        && !game_state.demo
+        && !pause_menu.listening
+        && keyboard_input.just_pressed(key_bindings.pause)
+    {
+        game_state.paused = !game_state.paused;
+    }
+}
+
+// Pause when the window loses focus (like alt-tabbing away), so nobody dies while they're gone.
+// Coming back doesn't unpause - that's still up to the pause key.
+fn pause_on_focus_lost(
+    mut game_state: ResMut<GameState>,
+    mut focus_events: EventReader<WindowFocused>,
+) {
+    let focus_lost = focus_events.iter().any(|event| !event.focused);
+    if focus_lost && game_state.started && !game_state.demo && !game_state.paused {
+        println!("[GAME] Window lost focus, pausing");
+        game_state.paused = true;
+    }
+}
+
+fn play_intro(
+    time: Res<Time>,
+    mut game_state: ResMut<GameState>,
+    launch_options: Res<LaunchOptions>,
+    mut start_events: EventReader<GameStartEvent>,
+    mut intro_timer: ResMut<IntroTimer>,
+    mut sound_events: EventWriter<PlaySoundEvent>,
+    mut new_level_events: EventWriter<NewLevelEvent>,
+) {
+    // Did the game just start? Play the intro music and reset timer.
+    if start_events.iter().count() > 0 {
+        // Started with `--skip-intro`? Go right to the first level
+        if launch_options.skip_intro {
+            new_level_events.send_default();
+            return;
+        }
+
+        // Let the app know we're in an intro sequence - doesn't have to be event
+        game_state.intro = true;
+
+        // Play the intro song
+        sound_events.send(PlaySoundEvent(SoundId::Intro));
+
+        intro_timer.0.reset();
+    }
+
+    // If the intro is playing, we increment it's timer to know if it's done or not
+    if game_state.intro && intro_timer.0.tick(frame_delta(&time)).just_finished() {
+        game_state.intro = false;
+
+        // Intro's over, kick off the first level
+        new_level_events.send_default();
+    }
+}
+
+// Once every enemy is destroyed, play the stage clear jingle and get ready for the next level
+fn check_level_complete(
+    mut game_state: ResMut<GameState>,
+    mut players: ResMut<Players>,
+    mut challenge_stage: ResMut<ChallengeStage>,
+    mut shot_stats: ResMut<ShotStats>,
+    mut stage_clear_timer: ResMut<StageClearTimer>,
+    mut sound_events: EventWriter<PlaySoundEvent>,
+    // Checking the events directly so we can tell if a level was just started
+    // (the new enemies won't exist until the spawn commands run)
+    new_level_events: Res<Events<NewLevelEvent>>,
+    enemy_query: Query<(), With<Enemy>>,
+) {
+    if !game_state.started
+        || game_state.intro
+        || game_state.stage_clear
+        || game_state.turn_change
+        || !new_level_events.is_empty()
+    {
+        return;
+    }
+
+    if enemy_query.is_empty() {
+        println!("[LEVEL] Level {} clear", game_state.level);
+
+        // Bonus points for everything hit during a challenge stage (a lot more if it was all of them)
+        if challenge_stage.active {
+            challenge_stage.bonus = if challenge_stage.perfect() {
+                CHALLENGE_PERFECT_BONUS
+            } else {
+                challenge_stage.hits * CHALLENGE_POINTS_PER_HIT
+            };
+            players.current_mut().score += challenge_stage.bonus;
+            println!(
+                "[LEVEL] Challenge stage: {}/{} hits, {} bonus",
+                challenge_stage.hits, challenge_stage.total, challenge_stage.bonus
+            );
+        }
+
+        // Bonus points for accuracy
+        shot_stats.bonus = (shot_stats.hit_ratio() * ACCURACY_POINTS_PER_PERCENT) as usize;
+        players.current_mut().score += shot_stats.bonus;
+        println!(
+            "[LEVEL] {} shots fired, {} hits, {} bonus",
+            shot_stats.shots_fired, shot_stats.hits, shot_stats.bonus
+        );
+
+        game_state.level += 1;
+        game_state.stage_clear = true;
+        stage_clear_timer.0.reset();
+        sound_events.send(PlaySoundEvent(SoundId::StageClear));
+    }
+}
+
+// Wait for the stage clear jingle to finish before starting the next level
+fn play_stage_clear(
+    time: Res<Time>,
+    mut game_state: ResMut<GameState>,
+    mut stage_clear_timer: ResMut<StageClearTimer>,
+    mut new_level_events: EventWriter<NewLevelEvent>,
+) {
+    if !game_state.stage_clear || game_state.paused {
+        return;
+    }
+
+    if stage_clear_timer.0.tick(frame_delta(&time)).just_finished() {
+        game_state.stage_clear = false;
+        new_level_events.send_default();
+    }
+}
+
+// Sweep away whatever the last game (or level) left on screen.
+// A new game also clears out the enemies left over from the game over (and so does the demo ending).
+fn cleanup_entities(
+    mut commands: Commands,
+    mut start_events: EventReader<GameStartEvent>,
+    mut new_level_events: EventReader<NewLevelEvent>,
+    mut demo_end_events: EventReader<DemoEndEvent>,
+    mut projectile_pool: ResMut<ProjectilePool>,
+    mut projectile_timer: ResMut<ProjectileTimer>,
+    mut enemy_projectile_timer: ResMut<EnemyProjectileTimer>,
+    mut shot_stats: ResMut<ShotStats>,
+    projectile_query: Query<Entity, (With<Projectile>, Without<Pooled>)>,
+    // The player's explosion is left alone, it decides when they respawn
+    explosion_query: Query<
+        Entity,
+        (
+            With<AnimationTimer>,
+            With<AnimationFrame>,
+            Without<PlayerExploding>,
+        ),
+    >,
+    effect_query: Query<
+        Entity,
+        Or<(
+            With<ScorePopup>,
+            With<PowerUp>,
+            With<Afterimage>,
+            With<TractorBeam>,
+        )>,
+    >,
+    captured_fighter_query: Query<Entity, With<CapturedFighter>>,
+    docked_fighter_query: Query<Entity, With<DockedFighter>>,
+    enemy_query: Query<Entity, With<Enemy>>,
+) {
+    let demo_ended = demo_end_events.iter().count() > 0;
+    let game_started = start_events.iter().count() > 0 || demo_ended;
+    let level_started = new_level_events.iter().count() > 0;
+    if !game_started && !level_started {
+        return;
+    }
+
+    for projectile_entity in &projectile_query {
+        projectile_pool.release(&mut commands, projectile_entity);
+    }
+    for entity in explosion_query.iter().chain(effect_query.iter()) {
+        commands.entity(entity).despawn();
+    }
+    projectile_timer.0.reset();
+    enemy_projectile_timer.0.reset();
+    *shot_stats = ShotStats::default();
+
+    if game_started {
+        for entity in enemy_query
+            .iter()
+            .chain(captured_fighter_query.iter())
+            .chain(docked_fighter_query.iter())
+        {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+// Challenge stages get their own music (and eventually their own enemies)
+pub fn is_challenge_stage(level: usize) -> bool {
+    level >= CHALLENGE_STAGE_FIRST
+        && level % CHALLENGE_STAGE_INTERVAL == CHALLENGE_STAGE_FIRST % CHALLENGE_STAGE_INTERVAL
+}
+
+// Freeze the background while paused, and speed it up while we fly in during the intro
+fn update_background_scroll(
+    game_state: Res<GameState>,
+    mut background_scroll: ResMut<BackgroundScroll>,
+) {
+    if !game_state.is_changed() {
+        return;
+    }
+
+    background_scroll.speed = if game_state.paused {
+        0.0
+    } else if game_state.intro {
+        INTRO_SCROLL_SPEED
+    } else {
+        1.0
+    };
+}
+
+// Switch between windowed and borderless fullscreen (F11 or Alt+Enter).
+// Going back to a window restores the size it was before.
+fn toggle_fullscreen(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut windows: ResMut<Windows>,
+    mut windowed_size: Local<Option<Vec2>>,
+) {
+    let alt_enter = keyboard_input.any_pressed([KeyCode::LAlt, KeyCode::RAlt])
+        && keyboard_input.just_pressed(KeyCode::Return);
+    if !keyboard_input.just_pressed(FULLSCREEN_KEY) && !alt_enter {
+        return;
+    }
+
+    let Some(window) = windows.get_primary_mut() else {
+        return;
+    };
+    let mode = match window.mode() {
+        WindowMode::Windowed => {
+            *windowed_size = Some(Vec2::new(window.width(), window.height()));
+            WindowMode::BorderlessFullscreen
+        }
+        _ => WindowMode::Windowed,
+    };
+    println!("[WINDOW] Switching to {:?}", mode);
+    window.set_mode(mode);
+    if mode == WindowMode::Windowed {
+        if let Some(size) = windowed_size.take() {
+            window.set_resolution(size.x, size.y);
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use std::fs;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    settings::{save_file_path, KeyBindings},
+    shared::*,
+};
+
+// The top scores, with initials entered arcade style after the game over screen.
+// Up/Down changes the letter, Left/Right picks which letter, fire or start saves it.
+// The leaderboard can be looked at from the start screen too (L opens it, L/fire/start closes it).
+pub struct LeaderboardPlugin;
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_leaderboard())
+            .init_resource::<HighScoreEntry>()
+            .add_system(queue_high_score_entries)
+            .add_system(enter_initials.label(GameSystem::EnterInitials))
+            .add_system(toggle_leaderboard.label(GameSystem::ToggleLeaderboard));
+    }
+}
+
+// Lives next to the executable
+const LEADERBOARD_FILE: &str = "leaderboard.ron";
+pub const LEADERBOARD_SIZE: usize = 10;
+pub const INITIALS_LENGTH: usize = 3;
+const LETTER_COUNT: u8 = 26;
+const NEXT_LETTER_KEY: KeyCode = KeyCode::Up;
+const PREVIOUS_LETTER_KEY: KeyCode = KeyCode::Down;
+pub const LEADERBOARD_KEY: KeyCode = KeyCode::L;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub score: usize,
+}
+
+// Best scores first. Classic and combo scoring keep separate boards, since combo scores run much higher,
+// and so do classic and modern mode (`classic`/`combo` are the classic mode boards).
+#[derive(Resource, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Leaderboard {
+    pub classic: Vec<LeaderboardEntry>,
+    pub combo: Vec<LeaderboardEntry>,
+    pub modern: Vec<LeaderboardEntry>,
+    pub modern_combo: Vec<LeaderboardEntry>,
+}
+
+impl Leaderboard {
+    pub fn entries(&self, mode: GameMode, combo: bool) -> &[LeaderboardEntry] {
+        match (mode, combo) {
+            (GameMode::Classic, false) => &self.classic,
+            (GameMode::Classic, true) => &self.combo,
+            (GameMode::Modern, false) => &self.modern,
+            (GameMode::Modern, true) => &self.modern_combo,
+        }
+    }
+
+    pub fn best(&self, mode: GameMode, combo: bool) -> usize {
+        self.entries(mode, combo)
+            .first()
+            .map_or(0, |entry| entry.score)
+    }
+
+    // Would this score make it onto the board?
+    fn qualifies(&self, mode: GameMode, combo: bool, score: usize) -> bool {
+        let entries = self.entries(mode, combo);
+        score > 0
+            && (entries.len() < LEADERBOARD_SIZE
+                || entries.last().is_some_and(|entry| score > entry.score))
+    }
+
+    // (ties go below the scores already there)
+    fn add(&mut self, mode: GameMode, combo: bool, new_entry: LeaderboardEntry) {
+        let entries = match (mode, combo) {
+            (GameMode::Classic, false) => &mut self.classic,
+            (GameMode::Classic, true) => &mut self.combo,
+            (GameMode::Modern, false) => &mut self.modern,
+            (GameMode::Modern, true) => &mut self.modern_combo,
+        };
+        let index = entries
+            .iter()
+            .position(|entry| new_entry.score > entry.score)
+            .unwrap_or(entries.len());
+        entries.insert(index, new_entry);
+        entries.truncate(LEADERBOARD_SIZE);
+    }
+}
+
+// Initials being entered for a new high score
+#[derive(Resource, Default)]
+pub struct HighScoreEntry {
+    // (player number, score) for everyone who made the board, the first one is entering their initials now
+    pub pending: Vec<(usize, usize)>,
+    // Each letter as 0-25 (A-Z)
+    pub letters: [u8; INITIALS_LENGTH],
+    // Which letter is being changed
+    pub cursor: usize,
+}
+
+impl HighScoreEntry {
+    pub fn initials(&self) -> String {
+        self.letters
+            .iter()
+            .map(|letter| (b'A' + letter) as char)
+            .collect()
+    }
+}
+
+// Load the leaderboard from its file, starting empty if it's missing or malformed
+fn load_leaderboard() -> Leaderboard {
+    let path = save_file_path(LEADERBOARD_FILE);
+    match fs::read_to_string(&path) {
+        Ok(contents) => ron::from_str::<Leaderboard>(&contents).unwrap_or_else(|error| {
+            println!("[LEADERBOARD] Couldn't parse {}: {}", path.display(), error);
+            Leaderboard::default()
+        }),
+        Err(_) => Leaderboard::default(),
+    }
+}
+
+fn save_leaderboard(leaderboard: &Leaderboard) {
+    let path = save_file_path(LEADERBOARD_FILE);
+    match ron::ser::to_string_pretty(leaderboard, ron::ser::PrettyConfig::default()) {
+        Ok(contents) => {
+            if let Err(error) = fs::write(&path, contents) {
+                println!("[LEADERBOARD] Couldn't save {}: {}", path.display(), error);
+            }
+        }
+        Err(error) => println!("[LEADERBOARD] Couldn't serialize leaderboard: {}", error),
+    }
+}
+
+// Once the game is over, line up everyone who made the board
+// (the game over screen starts the entry when it's done, see display_game_over).
+// Scores from the demo (or with cheats) don't count.
+fn queue_high_score_entries(
+    game_state: Res<GameState>,
+    cheats: Res<Cheats>,
+    combo: Res<ComboState>,
+    game_mode: Res<GameMode>,
+    players: Res<Players>,
+    leaderboard: Res<Leaderboard>,
+    mut high_score_entry: ResMut<HighScoreEntry>,
+    mut was_game_over: Local<bool>,
+) {
+    let game_over_started = game_state.game_over && !*was_game_over;
+    *was_game_over = game_state.game_over;
+    if !game_over_started || game_state.demo || cheats.used {
+        return;
+    }
+
+    *high_score_entry = HighScoreEntry::default();
+    for (player, progress) in players.progress[..players.count].iter().enumerate() {
+        if leaderboard.qualifies(*game_mode, combo.enabled, progress.score) {
+            println!("[LEADERBOARD] Player {} made the leaderboard", player + 1);
+            high_score_entry.pending.push((player, progress.score));
+        }
+    }
+}
+
+fn enter_initials(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    combo: Res<ComboState>,
+    game_mode: Res<GameMode>,
+    mut game_state: ResMut<GameState>,
+    mut leaderboard: ResMut<Leaderboard>,
+    mut high_score_entry: ResMut<HighScoreEntry>,
+) {
+    if !game_state.name_entry {
+        return;
+    }
+    let Some(&(player, score)) = high_score_entry.pending.first() else {
+        game_state.name_entry = false;
+        return;
+    };
+
+    let cursor = high_score_entry.cursor;
+    if keyboard_input.just_pressed(key_bindings.move_left) {
+        high_score_entry.cursor = cursor.saturating_sub(1);
+    }
+    if keyboard_input.just_pressed(key_bindings.move_right) {
+        high_score_entry.cursor = (cursor + 1).min(INITIALS_LENGTH - 1);
+    }
+    let letter = &mut high_score_entry.letters[cursor];
+    if keyboard_input.just_pressed(NEXT_LETTER_KEY) {
+        *letter = (*letter + 1) % LETTER_COUNT;
+    }
+    if keyboard_input.just_pressed(PREVIOUS_LETTER_KEY) {
+        *letter = (*letter + LETTER_COUNT - 1) % LETTER_COUNT;
+    }
+
+    if keyboard_input.just_pressed(key_bindings.fire)
+        || keyboard_input.just_pressed(key_bindings.start)
+    {
+        let name = high_score_entry.initials();
+        println!("[LEADERBOARD] Player {} saved as {}", player + 1, name);
+        leaderboard.add(*game_mode, combo.enabled, LeaderboardEntry { name, score });
+        save_leaderboard(&leaderboard);
+
+        // Next player's turn (or back to the start screen)
+        high_score_entry.pending.remove(0);
+        high_score_entry.letters = default();
+        high_score_entry.cursor = 0;
+        game_state.name_entry = !high_score_entry.pending.is_empty();
+
+        // (so the same press doesn't start a new game)
+        keyboard_input.clear_just_pressed(key_bindings.fire);
+        keyboard_input.clear_just_pressed(key_bindings.start);
+    }
+}
+
+fn toggle_leaderboard(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    transition: Res<Transition>,
+    mut game_state: ResMut<GameState>,
+) {
+    if game_state.showing_leaderboard {
+        let close_keys = [LEADERBOARD_KEY, key_bindings.fire, key_bindings.start];
+        if keyboard_input.any_just_pressed(close_keys) {
+            game_state.showing_leaderboard = false;
+            // (so the same press doesn't start a new game)
+            for key in close_keys {
+                keyboard_input.clear_just_pressed(key);
+            }
+        }
+        return;
+    }
+
+    let on_start_screen = !game_state.started
+        && !game_state.continue_offer
+        && !game_state.game_over
+        && !game_state.name_entry
+        && !game_state.loading
+        && !transition.is_active();
+    if on_start_screen && keyboard_input.just_pressed(LEADERBOARD_KEY) {
+        game_state.showing_leaderboard = true;
+    }
+}
@@ -1,645 +1,284 @@
+// Bevy systems take their dependencies as arguments, so these lints are just noise
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+
+mod attract;
+mod audio;
+mod bullet_time;
+mod capture;
+#[cfg(feature = "debug-tools")]
+mod cheats;
+mod cli;
+mod collision;
+mod debug;
+mod effects;
+mod enemy;
+mod formation;
+#[cfg(feature = "debug-tools")]
+mod frame_step;
+mod game;
+mod leaderboard;
+mod material;
+mod player;
+mod powerup;
+mod projectile;
+mod replay;
+mod rumble;
+mod settings;
+mod shared;
+mod stats;
+mod tuning;
+mod ui;
+mod wave;
+
 use std::time::Duration;
 
 use bevy::{
+    app::{ScheduleRunnerPlugin, ScheduleRunnerSettings},
+    asset::AssetPlugin,
+    audio::AudioPlugin,
+    gilrs::GilrsPlugin,
     prelude::*,
-    reflect::TypeUuid,
-    render::render_resource::{AsBindGroup, ShaderRef},
-    sprite::{
-        collide_aabb::collide, Material2d, Material2dPlugin, MaterialMesh2dBundle, Mesh2dHandle,
-    },
-    time::FixedTimestep,
+    render::settings::WgpuSettings,
+    winit::WinitPlugin,
 };
+use shared::{GameConfig, GameRng};
+
+use attract::AttractPlugin;
+use audio::GameAudioPlugin;
+use bullet_time::BulletTimePlugin;
+use capture::CapturePlugin;
+use cli::LaunchOptions;
+use collision::CollisionPlugin;
+use debug::DebugPlugin;
+use effects::EffectsPlugin;
+use enemy::EnemyPlugin;
+use formation::FormationPlugin;
+use game::GamePlugin;
+use leaderboard::LeaderboardPlugin;
+use material::CustomMaterialPlugin;
+use player::PlayerPlugin;
+use powerup::PowerUpPlugin;
+use projectile::ProjectilePlugin;
+use replay::ReplayPlugin;
+use rumble::RumblePlugin;
+use settings::SettingsPlugin;
+use stats::StatsPlugin;
+use tuning::TuningPlugin;
+use ui::UiPlugin;
+use wave::WavePlugin;
+
+// How often a headless run updates (the same as a 60Hz screen)
+const HEADLESS_FRAME_TIME: f64 = 1.0 / 60.0; // seconds
+
+// Debug builds reload assets when they change on disk (like tuning.ron and the wave files)
+const WATCH_ASSETS: bool = cfg!(debug_assertions);
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins)
-        .add_plugin(Material2dPlugin::<CustomMaterial>::default())
-        .insert_resource(ProjectileTimer(Timer::from_seconds(
-            PROJECTILE_TIME_LIMIT,
-            TimerMode::Once,
-        )))
-        .insert_resource(IntroTimer(Timer::from_seconds(
-            INTRO_TIME_LIMIT,
-            TimerMode::Once,
-        )))
-        .add_startup_system(setup_game)
-        .add_system(update_material_time)
-        .insert_resource(PlayerScore { score: 0 })
-        .insert_resource(GameState {
-            started: false,
-            paused: false,
-            intro: false,
-            level: 1,
-        })
-        .add_event::<GameStartEvent>()
-        .add_event::<EnemyDeathEvent>()
-        .add_event::<ProjectileEvent>()
-        .add_system_set(
-            SystemSet::new()
-                .with_run_criteria(FixedTimestep::step(TIME_STEP as f64))
-                .with_system(check_for_collisions)
-                .with_system(move_player.before(check_for_collisions))
-                .with_system(move_projectiles.before(check_for_collisions))
-                .with_system(destroy_projectiles.before(check_for_collisions))
-                .with_system(play_projectile_sound.before(check_for_collisions))
-                .with_system(update_player_score.before(play_enemy_death_sound))
-                .with_system(play_enemy_death_sound.before(check_for_collisions))
-                .with_system(shoot_projectile.before(check_for_collisions)),
-        )
-        .add_system(start_game)
-        .add_system(pause_game)
-        .add_system(play_intro)
-        .add_system(display_start_screen)
-        .add_system(bevy::window::close_on_esc)
-        .run();
-}
-
-// The Player object
-#[derive(Component)]
-struct Player;
-
-// The Enemy object
-#[derive(Component)]
-struct Enemy;
-
-// The projectile spawned by Player firing weapon
-#[derive(Component)]
-struct Projectile;
-
-// Timer used to limit player shooting every frame per second
-#[derive(Resource)]
-struct ProjectileTimer(Timer);
-
-// The speed of an object
-#[derive(Component, Deref, DerefMut)]
-struct Velocity(Vec2);
-
-// Signifies an object is collidable
-#[derive(Component)]
-struct Collider;
-
-// Events
-// Enemy Death
-#[derive(Default)]
-struct EnemyDeathEvent(usize);
-
-// Projectile has been fired
-#[derive(Default)]
-struct ProjectileEvent;
-
-#[derive(Default)]
-struct GameStartEvent;
+    let launch_options = LaunchOptions::from_env();
 
-// Sounds
-#[derive(Resource)]
-struct EnemyDeathSound(Handle<AudioSource>);
-#[derive(Resource)]
-struct ProjectileSound(Handle<AudioSource>);
-#[derive(Resource)]
-struct GameIntroSound(Handle<AudioSource>);
-
-// Resources
-// The players current score
-#[derive(Resource)]
-struct PlayerScore {
-    score: usize,
-}
-// The players current score
-#[derive(Resource)]
-struct GameState {
-    // Has game started? (aka user presses "start")
-    started: bool,
-    // Is game paused? Only relevant is game is started
-    paused: bool,
-    // Are we playing game intro? Occurs after initial game start.
-    intro: bool,
-    // The level number (1-99+)
-    level: usize,
-}
-
-#[derive(Resource)]
-struct GameFonts {
-    body: Handle<Font>,
+    let mut app = App::new();
+    build_app(&mut app, launch_options);
+    app.run();
 }
 
-// Timer used to track playback of intro
-#[derive(Resource)]
-struct IntroTimer(Timer);
-
-// UI
-// The player's score (should be alongside a TextBundle)
-#[derive(Component)]
-struct PlayerScoreText;
-
-#[derive(Component)]
-struct HighScoreText;
-
-#[derive(Component)]
-struct PressStartText;
-
-// Defines the amount of time that should elapse between each physics step
-// in this case, 60fps
-const TIME_STEP: f32 = 1.0 / 60.0;
-const SCREEN_WIDTH_DEFAULT: f32 = 1300.0;
-const SCREEN_EDGE_VERTICAL: f32 = 350.0;
-const PROJECTILE_TIME_LIMIT: f32 = 0.1;
-const INTRO_TIME_LIMIT: f32 = 6.0; // seconds
-
-const PLAYER_SIZE: Vec3 = Vec3::new(15.0, 16.0, 0.0);
-const PLAYER_SPEED: f32 = 400.0;
-const PLAYER_STARTING_POSITION: Vec3 = Vec3::new(0.0, -300.0, 1.0);
-const ENEMY_STARTING_POSITION: Vec3 = Vec3::new(0.0, 20.0, 1.0);
-const PROJECTILE_SIZE: Vec3 = Vec3::splat(3.0);
-const PROJECTILE_SPEED: f32 = 400.0;
-const ENEMY_PROJECTILE_DIRECTION: Vec2 = Vec2::new(0.5, -0.5);
-const PLAYER_PROJECTILE_DIRECTION: Vec2 = Vec2::new(0.5, 0.5);
-
-// UI
-const UI_FONT_MEDIUM: f32 = 32.0;
-const UI_COLOR_RED: Color = Color::rgb(0.8, 0.0, 0.0);
-const UI_COLOR_WHITE: Color = Color::rgb(0.95, 0.95, 0.95);
-const UI_PADDING_CENTER_TOP: Val = Val::Px(16.0);
-// We take the screen width and halve it to find center - then subtract a little more to accomodate for text size
-// Ideally we should make the flex 100% width and let it center using align properties, but I couldn't get that working 🤷‍♂️
-const UI_PADDING_CENTER_LEFT: Val = Val::Px(SCREEN_WIDTH_DEFAULT / 2.0 - 30.0);
-const UI_START_PADDING_LEFT: Val = Val::Px(SCREEN_WIDTH_DEFAULT / 2.0 - SCREEN_WIDTH_DEFAULT / 8.0);
-
-fn setup_game(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<CustomMaterial>>,
-    asset_server: Res<AssetServer>,
-) {
-    // Camera
-    commands.spawn(Camera2dBundle::default());
-
-    // Load sound effects
-    let enemy_death_sound = asset_server.load("sounds/enemy-death.mp3");
-    commands.insert_resource(EnemyDeathSound(enemy_death_sound));
-    let projectile_sound = asset_server.load("sounds/projectile.mp3");
-    commands.insert_resource(ProjectileSound(projectile_sound));
-    let game_intro_sound = asset_server.load("sounds/intro.mp3");
-    commands.insert_resource(GameIntroSound(game_intro_sound));
-
-    // Background
-    commands.spawn(MaterialMesh2dBundle {
-        // mesh: meshes.add(shape::Plane { size: 3.0 }.into()).into(),
-        mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
-        transform: Transform::default().with_scale(Vec3::new(
-            SCREEN_WIDTH_DEFAULT,
-            SCREEN_EDGE_VERTICAL * 2.0,
-            0.0,
-        )),
-        // material: materials.add(ColorMaterial::from(Color::TURQUOISE)),
-        material: materials.add(CustomMaterial {
-            color: Color::BLUE,
-            color_texture: Some(asset_server.load("textures/space/space.png")),
-            tile: 1.0,
-            time: 0.0,
-        }),
-        ..default()
-    });
-
-    // Add fonts to system
-    let game_fonts = GameFonts {
-        body: asset_server.load("fonts/VT323-Regular.ttf"),
-    };
-
-    // UI Elements
-    // High Score
-    commands.spawn((
-        TextBundle::from_sections([
-            TextSection::new(
-                "HIGH SCORE\n",
-                TextStyle {
-                    font: game_fonts.body.clone(),
-                    font_size: UI_FONT_MEDIUM,
-                    color: UI_COLOR_RED,
-                },
-            ),
-            TextSection::new(
-                "20000",
-                TextStyle {
-                    font: game_fonts.body.clone(),
-                    font_size: UI_FONT_MEDIUM,
-                    color: UI_COLOR_WHITE,
-                },
-            ),
-        ])
-        .with_text_alignment(TextAlignment::TOP_CENTER)
-        .with_style(Style {
-            // flex_direction: FlexDirection::Row,
-            // align_content: AlignContent::Center,
-            // align_items: AlignItems::Center,
-            // align_self: AlignSelf::Center,
-            position_type: PositionType::Absolute,
-            flex_wrap: FlexWrap::Wrap,
-            // size: Size {
-            //     width: Val::Px(SCREEN_WIDTH_DEFAULT),
-            //     height: Val::Px(200.0),
-            // },
-            position: UiRect {
-                top: UI_PADDING_CENTER_TOP,
-                left: UI_PADDING_CENTER_LEFT,
-                // top: Val::Px(0.0),
-                // left: Val::Px(0.0),
-                ..default()
-            },
-            ..default()
-        }),
-        HighScoreText,
-    ));
-    // Player Score
-    commands.spawn((
-        TextBundle::from_sections([
-            TextSection::new(
-                "1UP\n",
-                TextStyle {
-                    font: asset_server.load("fonts/VT323-Regular.ttf"),
-                    font_size: UI_FONT_MEDIUM,
-                    color: UI_COLOR_RED,
-                },
-            ),
-            TextSection::from_style(TextStyle {
-                font: asset_server.load("fonts/VT323-Regular.ttf"),
-                font_size: UI_FONT_MEDIUM,
-                color: UI_COLOR_WHITE,
-            }),
-        ])
-        .with_style(Style {
-            position_type: PositionType::Absolute,
-            position: UiRect {
-                top: UI_PADDING_CENTER_TOP,
-                left: UI_PADDING_CENTER_TOP,
-                ..default()
-            },
-            ..default()
-        }),
-        PlayerScoreText,
-    ));
-
-    // Now we can insert fonts as a resource after the UI has used it
-    commands.insert_resource(game_fonts);
-
-    // Spawn Player in initial position
-    commands.spawn((
-        MaterialMesh2dBundle {
-            // mesh: meshes.add(shape::Plane { size: 3.0 }.into()).into(),
-            mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
-            transform: Transform {
-                translation: PLAYER_STARTING_POSITION,
-                scale: PLAYER_SIZE,
-                ..default()
-            },
-            material: materials.add(CustomMaterial {
-                color: Color::BLUE,
-                color_texture: Some(asset_server.load("sprites/player_default.png")),
-                tile: 0.0,
-                time: 0.0,
-            }),
+// Everything the game needs.
+// Headless runs skip the window, rendering, audio and gamepads, but all the gameplay still runs.
+fn build_app(app: &mut App, launch_options: LaunchOptions) {
+    let headless = launch_options.headless;
+    if headless {
+        // (no GPU backend means nothing gets rendered)
+        app.insert_resource(WgpuSettings {
+            backends: None,
             ..default()
-        },
-        Player,
-        Collider,
-    ));
-
-    // Spawn enemies
-    commands.spawn((
-        MaterialMesh2dBundle {
-            // mesh: meshes.add(shape::Plane { size: 3.0 }.into()).into(),
-            mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
-            transform: Transform {
-                translation: ENEMY_STARTING_POSITION,
-                scale: PLAYER_SIZE,
-                ..default()
-            },
-            material: materials.add(CustomMaterial {
-                color: Color::BLUE,
-                color_texture: Some(asset_server.load("sprites/enemy_green_bug.png")),
-                tile: 0.0,
-                time: 0.0,
-            }),
-            ..default()
-        },
-        Enemy,
-        Collider,
-    ));
-}
-
-impl Material2d for CustomMaterial {
-    fn fragment_shader() -> ShaderRef {
-        "shaders/custom_material.wgsl".into()
+        })
+        // Without winit something else has to keep the frames coming
+        .insert_resource(ScheduleRunnerSettings::run_loop(Duration::from_secs_f64(
+            HEADLESS_FRAME_TIME,
+        )))
+        .add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    add_primary_window: false,
+                    exit_on_all_closed: false,
+                    ..default()
+                })
+                .set(asset_plugin())
+                .disable::<WinitPlugin>()
+                .disable::<AudioPlugin>()
+                .disable::<GilrsPlugin>(),
+        )
+        .add_plugin(ScheduleRunnerPlugin);
+    } else {
+        app.add_plugins(
+            DefaultPlugins
+                .set(launch_options.window_plugin())
+                .set(asset_plugin()),
+        );
     }
-}
-
-// Background shader material
-#[derive(AsBindGroup, TypeUuid, Debug, Clone)]
-#[uuid = "f690fdae-d598-45ab-8225-97e2a3f056e0"]
-pub struct CustomMaterial {
-    #[uniform(0)]
-    color: Color,
-    // Should we tile this material? 1 = true
-    #[uniform(0)]
-    tile: f32,
-    #[uniform(0)]
-    time: f32,
-    #[texture(1)]
-    #[sampler(2)]
-    color_texture: Option<Handle<Image>>,
-}
-
-fn move_player(
-    keyboard_input: Res<Input<KeyCode>>,
-    mut query: Query<&mut Transform, With<Player>>,
-    game_state: Res<GameState>,
-) {
-    if game_state.started && !game_state.paused && !game_state.intro {
-        let mut player_transform = query.single_mut();
-        let mut direction = 0.0;
-
-        if keyboard_input.pressed(KeyCode::Left) {
-            direction -= 1.0;
-        }
 
-        if keyboard_input.pressed(KeyCode::Right) {
-            direction += 1.0;
-        }
-
-        // Calculate the new horizontal player position based on player input
-        let new_player_position =
-            player_transform.translation.x + direction * PLAYER_SPEED * TIME_STEP;
-        // TODO: make sure player doesn't exceed bounds of game area
-
-        player_transform.translation.x = new_player_position;
+    app.insert_resource(GameConfig::default())
+        .insert_resource(GameRng::new(launch_options.seed))
+        .insert_resource(launch_options)
+        .add_plugin(CustomMaterialPlugin)
+        .add_plugin(TuningPlugin)
+        .add_plugin(SettingsPlugin)
+        .add_plugin(ReplayPlugin)
+        .add_plugin(GamePlugin)
+        .add_plugin(PlayerPlugin)
+        .add_plugin(WavePlugin)
+        .add_plugin(FormationPlugin)
+        .add_plugin(EnemyPlugin)
+        .add_plugin(ProjectilePlugin)
+        .add_plugin(PowerUpPlugin)
+        .add_plugin(CapturePlugin)
+        .add_plugin(CollisionPlugin)
+        .add_plugin(StatsPlugin)
+        .add_plugin(LeaderboardPlugin)
+        .add_plugin(EffectsPlugin)
+        .add_plugin(BulletTimePlugin)
+        .add_plugin(AttractPlugin)
+        .add_plugin(UiPlugin)
+        .add_plugin(DebugPlugin);
+
+    #[cfg(feature = "debug-tools")]
+    app.add_plugin(cheats::CheatsPlugin)
+        .add_plugin(frame_step::FrameStepPlugin);
+
+    if !headless {
+        app.add_plugin(GameAudioPlugin).add_plugin(RumblePlugin);
     }
 }
 
-fn shoot_projectile(
-    time: Res<Time>,
-    mut projectile_timer: ResMut<ProjectileTimer>,
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<CustomMaterial>>,
-    keyboard_input: Res<Input<KeyCode>>,
-    mut query: Query<&Transform, With<Player>>,
-    asset_server: Res<AssetServer>,
-    mut projectile_events: EventWriter<ProjectileEvent>,
-    game_state: Res<GameState>,
-) {
-    if game_state.started && !game_state.paused && !game_state.intro {
-        let player_transform = query.single_mut();
-
-        if keyboard_input.pressed(KeyCode::Space) {
-            // Check if player is allowed to shoot based on internal timer
-            // We have to "tick" the timer to update it with the latest time
-            if projectile_timer.0.tick(time.delta()).finished() {
-                // Reset the timer
-                projectile_timer.0.reset();
-
-                // Fire off a ProjectileEvent to notify other systems
-                projectile_events.send_default();
-
-                // Spawn projectile
-                commands.spawn((
-                    MaterialMesh2dBundle {
-                        // mesh: meshes.add(shape::Plane { size: 3.0 }.into()).into(),
-                        mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
-                        transform: Transform {
-                            translation: player_transform.translation,
-                            scale: PROJECTILE_SIZE,
-                            ..default()
-                        },
-                        material: materials.add(CustomMaterial {
-                            color: Color::BLUE,
-                            color_texture: Some(asset_server.load("sprites/player_projectile.png")),
-                            tile: 0.0,
-                            time: 0.0,
-                        }),
-                        ..default()
-                    },
-                    Projectile,
-                    Velocity(PLAYER_PROJECTILE_DIRECTION.normalize() * PROJECTILE_SPEED),
-                ));
-            }
-        }
-    }
-}
-
-fn move_projectiles(mut query: Query<(&mut Transform, &Velocity), With<Projectile>>) {
-    for (mut collider_transform, velocity) in &mut query {
-        // Calculate the new horizontal player position based on player input
-        let new_projectile_position = collider_transform.translation.y + velocity.y * TIME_STEP;
-        // TODO: make sure player doesn't exceed bounds of game area
-
-        collider_transform.translation.y = new_projectile_position;
+fn asset_plugin() -> AssetPlugin {
+    AssetPlugin {
+        watch_for_changes: WATCH_ASSETS,
+        ..default()
     }
 }
 
-fn destroy_projectiles(
-    mut commands: Commands,
-    query: Query<(Entity, &Transform), With<Projectile>>,
-) {
-    for (collider_entity, collider_transform) in &query {
-        // Check if projectile has passed top or bottom of screen
-        if collider_transform.translation.y > SCREEN_EDGE_VERTICAL
-            || collider_transform.translation.y < -SCREEN_EDGE_VERTICAL
-        {
-            commands.entity(collider_entity).despawn();
-        }
+#[cfg(test)]
+mod tests {
+    use bevy::{time::TimeUpdateStrategy, utils::Instant};
+
+    use super::*;
+    use shared::{Collider, Enemy, GameState, Players, Projectile, PROJECTILE_SIZE};
+
+    // Simulated frames are 20ms, so the clock adds up exactly
+    const FRAME_TIME: Duration = Duration::from_millis(20);
+    // The 6 second intro
+    const INTRO_FRAMES: usize = 300;
+    // How long to wait for something to happen before giving up
+    const MAX_FRAMES: usize = 1000;
+    // Assets load on other threads, so the frames spent loading depend on the machine
+    const MAX_LOADING_FRAMES: usize = 100_000;
+
+    // Drives the app one frame at a time on a simulated clock
+    struct HeadlessGame {
+        app: App,
+        start: Instant,
+        frames: u32,
     }
-}
 
-fn check_for_collisions(
-    mut commands: Commands,
-    projectiles_query: Query<(Entity, &Transform), With<Projectile>>,
-    collider_query: Query<(Entity, &Transform, Option<&Enemy>), With<Collider>>,
-    mut death_events: EventWriter<EnemyDeathEvent>,
-) {
-    // Loop through all the projectiles on screen
-    for (projectile_entity, projectile_transform) in &projectiles_query {
-        // Loop through all collidable elements on the screen
-        // TODO: Figure out how to flatten this - 2 for loops no bueno
-        for (collider_entity, collider_transform, enemy_check) in &collider_query {
-            let collision = collide(
-                projectile_transform.translation,
-                projectile_transform.scale.truncate(),
-                collider_transform.translation,
-                collider_transform.scale.truncate(),
+    impl HeadlessGame {
+        // The same app `--headless --seed 1` runs
+        // (no `--level`, so a headless run starts at level 1 with the intro)
+        fn new() -> Self {
+            let mut app = App::new();
+            build_app(
+                &mut app,
+                LaunchOptions {
+                    headless: true,
+                    seed: Some(1),
+                    level: Some(1),
+                    ..default()
+                },
             );
-
-            if let Some(collision) = collision {
-                // If it's an enemy, destroy!
-                if enemy_check.is_some() {
-                    println!("Collided!");
-                    // Fire off a EnemyDeathEvent to notify other systems
-                    // death_events.send_default();
-                    death_events.send(EnemyDeathEvent(100));
-
-                    // Enemy is destroyed
-                    commands.entity(collider_entity).despawn();
-
-                    // Projectile disappears too? Prevents "cutting through" a line of enemies all at once
-                    commands.entity(projectile_entity).despawn();
-                }
+            HeadlessGame {
+                app,
+                start: Instant::now(),
+                frames: 0,
             }
         }
-    }
-}
-
-fn play_enemy_death_sound(
-    death_events: EventReader<EnemyDeathEvent>,
-    audio: Res<Audio>,
-    sound: Res<EnemyDeathSound>,
-) {
-    // Check for events
-    if !death_events.is_empty() {
-        // Clear all events this frame
-        death_events.clear();
-
-        audio.play(sound.0.clone());
-    }
-}
-
-fn play_projectile_sound(
-    projectile_events: EventReader<ProjectileEvent>,
-    audio: Res<Audio>,
-    sound: Res<ProjectileSound>,
-) {
-    // Check for events
-    if !projectile_events.is_empty() {
-        // Clear all events this frame
-        projectile_events.clear();
-        println!("[AUDIO] Playing projectile sound!");
-
-        audio.play(sound.0.clone());
-    }
-}
-
-fn update_material_time(time: Res<Time>, mut materials: ResMut<Assets<CustomMaterial>>) {
-    materials.iter_mut().for_each(|material| {
-        material.1.time = time.elapsed_seconds();
-    });
-}
 
-fn update_player_score(
-    mut player_score: ResMut<PlayerScore>,
-    mut enemy_death_events: EventReader<EnemyDeathEvent>,
-    mut query: Query<&mut Text, With<PlayerScoreText>>,
-) {
-    // Check for events
-    if !enemy_death_events.is_empty() {
-        println!("[UI] Updating player score");
-
-        enemy_death_events.iter().for_each(|event| {
-            // let EnemyDeathEvent(points) = event;
-            // dbg!(&points);
-            // dbg!(&event.0);
-            player_score.score += &event.0;
-        });
-
-        for mut text in &mut query {
-            text.sections[1].value = player_score.score.to_string();
+        fn update(&mut self) {
+            self.frames += 1;
+            self.app.insert_resource(TimeUpdateStrategy::ManualInstant(
+                self.start + FRAME_TIME * self.frames,
+            ));
+            self.app.update();
         }
-    }
-}
 
-fn start_game(
-    mut game_state: ResMut<GameState>,
-    keyboard_input: Res<Input<KeyCode>>,
-    mut start_events: EventWriter<GameStartEvent>,
-) {
-    // If game hasn't started, detect space/return key to start game
-    if !game_state.started {
-        if keyboard_input.pressed(KeyCode::Space) | keyboard_input.pressed(KeyCode::Return) {
-            println!("[INPUT] Game Started");
-            game_state.started = true;
-
-            // Let other systems know we started (like intro sequence)
-            start_events.send_default();
+        // Run frames until the check passes, returning how many it took
+        fn update_until(
+            &mut self,
+            max_frames: usize,
+            mut check: impl FnMut(&mut App) -> bool,
+        ) -> Option<usize> {
+            (1..=max_frames).find(|_| {
+                self.update();
+                check(&mut self.app)
+            })
         }
-    }
-}
 
-fn pause_game(mut game_state: ResMut<GameState>, keyboard_input: Res<Input<KeyCode>>) {
-    // If game has started, check for P key to pause game
-    if game_state.started {
-        if keyboard_input.pressed(KeyCode::P) {
-            game_state.paused = !game_state.paused;
+        fn game_state(&self) -> &GameState {
+            self.app.world.resource::<GameState>()
         }
-    }
-}
-
-fn play_intro(
-    time: Res<Time>,
-    mut game_state: ResMut<GameState>,
-    audio: Res<Audio>,
-    sound: Res<GameIntroSound>,
-    start_events: EventReader<GameStartEvent>,
-    mut intro_timer: ResMut<IntroTimer>,
-) {
-    // Did the game just start? Play the intro music and reset timer.
-    if !start_events.is_empty() {
-        start_events.clear();
-
-        // Let the app know we're in an intro sequence - doesn't have to be event
-        game_state.intro = true;
-
-        // Play the intro song
-        audio.play(sound.0.clone());
 
-        intro_timer.0.reset();
+        fn score(&self) -> usize {
+            self.app.world.resource::<Players>().current().score
+        }
     }
 
-    // If the intro is playing, we increment it's timer to know if it's done or not
-    if game_state.intro && intro_timer.0.tick(time.delta()).just_finished() {
-        game_state.intro = false;
+    fn first_enemy(app: &mut App) -> Option<Vec3> {
+        app.world
+            .query_filtered::<&Transform, (With<Enemy>, With<Collider>)>()
+            .iter(&app.world)
+            .next()
+            .map(|transform| transform.translation)
     }
-}
 
-fn display_start_screen(
-    mut commands: Commands,
-    game_fonts: Res<GameFonts>,
-    game_state: Res<GameState>,
-    query: Query<Entity, With<PressStartText>>,
-) {
-    let mut start_screen_exists = false;
-    for text_obj in &query {
-        // commands.entity(text_obj).id()
-        start_screen_exists = true;
-        break;
-    }
+    // (only one test builds the whole app, the log plugin can only be set up once per process)
+    #[test]
+    fn headless_game_plays_through_the_intro_to_a_kill() {
+        let mut game = HeadlessGame::new();
 
-    // Game hasn't started and we haven't spawned UI yet
-    if !game_state.started && !start_screen_exists {
-        // Display UI for Start Screen
-        commands.spawn((
-            TextBundle::from_sections([TextSection::new(
-                "Press Spacebar/Return to Start \n".to_uppercase(),
-                TextStyle {
-                    font: game_fonts.body.clone(),
-                    font_size: UI_FONT_MEDIUM,
-                    color: UI_COLOR_RED,
-                },
-            )])
-            .with_text_alignment(TextAlignment::TOP_CENTER)
-            .with_style(Style {
-                position_type: PositionType::Absolute,
-                position: UiRect {
-                    top: Val::Px(SCREEN_EDGE_VERTICAL),
-                    left: UI_START_PADDING_LEFT,
-                    // left: Val::Px(0.0),
-                    ..default()
-                },
-                ..default()
-            }),
-            PressStartText,
-        ));
-    }
-
-    // Game started! Remove any UI.
-    if game_state.started && start_screen_exists {
-        for text_obj in &query {
-            commands.entity(text_obj).despawn();
-        }
+        // A headless run starts a game by itself once everything's loaded
+        game.update_until(MAX_LOADING_FRAMES, |app| {
+            app.world.resource::<GameState>().started
+        })
+        .expect("the game never started");
+        assert!(game.game_state().intro);
+
+        // The intro plays out on the clock, then the first level starts
+        let intro_frames = game
+            .update_until(MAX_FRAMES, |app| !app.world.resource::<GameState>().intro)
+            .expect("the intro never finished");
+        // (it already ticked once on the frame the game started)
+        assert_eq!(intro_frames + 1, INTRO_FRAMES);
+        assert!(game.game_state().started);
+
+        // Enemies fly in (the ones still waiting to enter can't be hit yet)
+        game.update_until(MAX_FRAMES, |app| first_enemy(app).is_some())
+            .expect("no enemies showed up");
+
+        // Drop a shot right on an enemy (again if it moves out of the way, or needs another hit)
+        let starting_score = game.score();
+        game.update_until(MAX_FRAMES, |app| {
+            if app.world.resource::<Players>().current().score > starting_score {
+                return true;
+            }
+            if let Some(translation) = first_enemy(app) {
+                app.world.spawn((
+                    Transform {
+                        translation,
+                        scale: PROJECTILE_SIZE,
+                        ..default()
+                    },
+                    Projectile,
+                ));
+            }
+            false
+        })
+        .expect("shooting an enemy never scored");
     }
 }
@@ -0,0 +1,205 @@
+use bevy::{
+    prelude::*,
+    reflect::TypeUuid,
+    render::render_resource::{AsBindGroup, ShaderRef},
+    sprite::{Material2d, Material2dPlugin},
+};
+
+use crate::shared::{scaled_delta, TimeScale};
+
+pub struct CustomMaterialPlugin;
+
+impl Plugin for CustomMaterialPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(Material2dPlugin::<CustomMaterial>::default())
+            .init_resource::<BackgroundScroll>()
+            .add_system(update_material_time)
+            .add_system(update_hit_flash)
+            .add_system(update_sprite_hit_flash);
+    }
+}
+
+impl Material2d for CustomMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/custom_material.wgsl".into()
+    }
+}
+
+// Background shader material
+#[derive(AsBindGroup, TypeUuid, Debug, Clone)]
+#[uuid = "f690fdae-d598-45ab-8225-97e2a3f056e0"]
+pub struct CustomMaterial {
+    #[uniform(0)]
+    pub color: Color,
+    // Should we tile this material? 1 = true
+    #[uniform(0)]
+    pub tile: f32,
+    #[uniform(0)]
+    pub time: f32,
+    // How far a tiled texture scrolls each second (in tiles), e.g. (0, -1) scrolls down
+    #[uniform(0)]
+    pub scroll_speed: Vec2,
+    // How white the sprite is (0.0 - 1.0), used to flash things when they're hit
+    #[uniform(0)]
+    pub flash: f32,
+    #[texture(1)]
+    #[sampler(2)]
+    pub color_texture: Option<Handle<Image>>,
+}
+
+impl Default for CustomMaterial {
+    fn default() -> Self {
+        CustomMaterial {
+            color: Color::BLUE,
+            tile: 0.0,
+            time: 0.0,
+            scroll_speed: SCROLL_SPEED_DEFAULT,
+            flash: 0.0,
+            color_texture: None,
+        }
+    }
+}
+
+// Space scrolls down past the player, one tile per second
+pub const SCROLL_SPEED_DEFAULT: Vec2 = Vec2::new(0.0, -1.0);
+
+// One of the scrolling background quads (each has its own material, the only ones that use the time uniform)
+#[derive(Component)]
+pub struct BackgroundLayer;
+
+// Flashes the entity white (its CustomMaterial, or its sprite), fading back to normal before the timer finishes
+#[derive(Component)]
+pub struct HitFlash(pub Timer);
+
+impl Default for HitFlash {
+    fn default() -> Self {
+        HitFlash(Timer::from_seconds(HIT_FLASH_TIME, TimerMode::Once))
+    }
+}
+
+const HIT_FLASH_TIME: f32 = 0.15; // seconds
+                                  // Sprites can't mix in white like the material does, so they get overbright instead
+                                  // (the tint multiplies the texture, and anything past 1.0 clips to white)
+const HIT_FLASH_SPRITE_BRIGHTNESS: f32 = 4.0;
+
+// How fast the background scrolls (multiplies every layer's scroll speed, 0 = stopped)
+#[derive(Resource)]
+pub struct BackgroundScroll {
+    pub speed: f32,
+}
+
+impl Default for BackgroundScroll {
+    fn default() -> Self {
+        BackgroundScroll { speed: 1.0 }
+    }
+}
+
+fn update_material_time(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    background_scroll: Res<BackgroundScroll>,
+    query: Query<&Handle<CustomMaterial>, With<BackgroundLayer>>,
+    mut materials: ResMut<Assets<CustomMaterial>>,
+) {
+    // Sprites share CustomMaterial too, but they don't animate, so leave them alone
+    for material_handle in &query {
+        if let Some(material) = materials.get_mut(material_handle) {
+            // Adding up the time (instead of using the elapsed time) lets the speed change without the background jumping
+            material.time +=
+                scaled_delta(&time, &time_scale).as_secs_f32() * background_scroll.speed;
+        }
+    }
+}
+
+fn update_hit_flash(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut materials: ResMut<Assets<CustomMaterial>>,
+    mut query: Query<(Entity, &mut HitFlash, &mut Handle<CustomMaterial>)>,
+) {
+    for (entity, mut hit_flash, mut material_handle) in &mut query {
+        // Materials are shared between sprites, so give this one its own copy before flashing it
+        // (otherwise every enemy using the material would flash)
+        if hit_flash.is_added() {
+            if let Some(material) = materials.get(&material_handle).cloned() {
+                *material_handle = materials.add(material);
+            }
+        }
+
+        hit_flash.0.tick(scaled_delta(&time, &time_scale));
+        let Some(material) = materials.get_mut(&material_handle) else {
+            continue;
+        };
+        material.flash = hit_flash.0.percent_left();
+
+        if hit_flash.0.finished() {
+            commands.entity(entity).remove::<HitFlash>();
+        }
+    }
+}
+
+fn update_sprite_hit_flash(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut query: Query<
+        (Entity, &mut HitFlash, &mut TextureAtlasSprite),
+        Without<Handle<CustomMaterial>>,
+    >,
+) {
+    for (entity, mut hit_flash, mut sprite) in &mut query {
+        hit_flash.0.tick(scaled_delta(&time, &time_scale));
+        let brightness = 1.0 + HIT_FLASH_SPRITE_BRIGHTNESS * hit_flash.0.percent_left();
+        sprite.color = Color::rgb(brightness, brightness, brightness);
+
+        if hit_flash.0.finished() {
+            sprite.color = Color::WHITE;
+            commands.entity(entity).remove::<HitFlash>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    #[test]
+    fn sprites_flash_and_go_back_to_normal() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .insert_resource(TimeScale(1.0))
+            .add_system(update_sprite_hit_flash);
+        let enemy = app
+            .world
+            .spawn((TextureAtlasSprite::default(), HitFlash::default()))
+            .id();
+
+        let start = Instant::now();
+        app.world.resource_mut::<Time>().update_with_instant(start);
+        app.world
+            .resource_mut::<Time>()
+            .update_with_instant(start + Duration::from_secs_f32(HIT_FLASH_TIME / 2.0));
+        app.update();
+        assert!(
+            app.world
+                .get::<TextureAtlasSprite>(enemy)
+                .unwrap()
+                .color
+                .r()
+                > 1.0
+        );
+
+        app.world
+            .resource_mut::<Time>()
+            .update_with_instant(start + Duration::from_secs_f32(HIT_FLASH_TIME));
+        app.update();
+        assert_eq!(
+            app.world.get::<TextureAtlasSprite>(enemy).unwrap().color,
+            Color::WHITE
+        );
+        assert!(app.world.get::<HitFlash>(enemy).is_none());
+    }
+}
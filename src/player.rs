@@ -0,0 +1,662 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    sprite::{Anchor, MaterialMesh2dBundle},
+    time::FixedTimesteps,
+};
+
+use crate::{
+    material::{CustomMaterial, HitFlash},
+    projectile::{Pooled, ProjectilePool},
+    settings::{GameSettingsState, PlayerInput},
+    shared::*,
+};
+
+pub struct PlayerPlugin;
+
+impl Plugin for PlayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ProjectileTimer(Timer::from_seconds(
+            PROJECTILE_TIME_LIMIT,
+            TimerMode::Once,
+        )))
+        .insert_resource(MaxProjectiles(PLAYER_MAX_PROJECTILES))
+        .init_resource::<FireBuffer>()
+        .insert_resource(ChargeState {
+            timer: Timer::from_seconds(CHARGE_TIME, TimerMode::Once),
+        })
+        .add_startup_system(spawn_player)
+        .add_system_set(
+            SystemSet::new()
+                .with_run_criteria(FixedStep)
+                .with_system(
+                    move_player
+                        .after(GameSystem::ReplayInput)
+                        .before(GameSystem::Collision),
+                )
+                .with_system(
+                    shoot_projectile
+                        .after(GameSystem::ReplayInput)
+                        .before(GameSystem::Collision),
+                )
+                .with_system(
+                    kill_player
+                        .label(GameSystem::KillPlayer)
+                        .after(GameSystem::Collision),
+                ),
+        )
+        .add_system(blink_invincible_player)
+        .add_system(update_charge_meter)
+        .add_system(bank_player)
+        .add_system(respawn_player.after(GameSystem::AnimateExplosion));
+    }
+}
+
+// Timer used to limit player shooting every frame per second
+#[derive(Resource)]
+pub struct ProjectileTimer(pub Timer);
+
+// A fire press that couldn't shoot yet (still cooling down, or too many shots in the air).
+// It's remembered until the timer runs out, so a tap just before the cooldown ends still fires.
+#[derive(Resource)]
+struct FireBuffer(Timer);
+
+impl Default for FireBuffer {
+    fn default() -> Self {
+        let mut timer = Timer::from_seconds(FIRE_BUFFER_TIME, TimerMode::Once);
+        // Nothing to fire at first
+        timer.tick(Duration::from_secs_f32(FIRE_BUFFER_TIME));
+        FireBuffer(timer)
+    }
+}
+
+// How many of the player's projectiles can be on screen at once
+#[derive(Resource)]
+pub struct MaxProjectiles(pub usize);
+
+// How long fire has been held down for.
+// Letting go with enough charge fires a charged shot.
+#[derive(Resource)]
+pub struct ChargeState {
+    pub timer: Timer,
+}
+
+impl ChargeState {
+    // 0.0 = no charge, 1.0 = fully charged
+    pub fn level(&self) -> f32 {
+        self.timer.percent()
+    }
+
+    pub fn charging(&self) -> bool {
+        self.timer.elapsed() > Duration::ZERO
+    }
+}
+
+// Everything the player's ship is made of
+#[derive(Bundle)]
+struct PlayerBundle {
+    #[bundle]
+    mesh: MaterialMesh2dBundle<CustomMaterial>,
+    player: Player,
+    collider: Collider,
+    banking: Banking,
+    velocity: Velocity,
+}
+
+impl PlayerBundle {
+    fn new(shared_assets: &SharedAssets, translation: Vec3) -> Self {
+        PlayerBundle {
+            mesh: MaterialMesh2dBundle {
+                mesh: shared_assets.quad.clone().into(),
+                transform: Transform {
+                    translation,
+                    scale: PLAYER_SIZE,
+                    ..default()
+                },
+                material: shared_assets.player.clone(),
+                ..default()
+            },
+            player: Player,
+            collider: Collider,
+            banking: Banking::default(),
+            velocity: Velocity(Vec2::ZERO),
+        }
+    }
+}
+
+// The bar under the ship that fills up while charging
+#[derive(Component)]
+struct ChargeMeter;
+
+const PLAYER_BLINK_TIME: f32 = 0.1; // seconds
+const PLAYER_PROJECTILE_DIRECTION: Vec2 = Vec2::Y;
+// Classic Galaga only lets you have 2 shots in the air
+const PLAYER_MAX_PROJECTILES: usize = 2;
+const DOUBLE_SHOT_GAP: f32 = 10.0;
+// Rapid fire shoots 3x as often, with twice as many shots in the air
+const RAPID_FIRE_COOLDOWN_SCALE: f32 = 1.0 / 3.0;
+const RAPID_FIRE_MAX_PROJECTILES_SCALE: usize = 2;
+const SPREAD_SHOT_ANGLE: f32 = 0.26; // radians (about 15 degrees)
+
+// How long smooth movement takes to get up to full speed, and to stop from it
+const PLAYER_ACCELERATION_TIME: f32 = 0.1; // seconds
+const PLAYER_DECELERATION_TIME: f32 = 0.07; // seconds
+const SCREEN_SHAKE_TRAUMA_PLAYER_DEATH: f32 = 0.8;
+// The player's explosion is bigger and slower than an enemy's (like the arcade)
+const PLAYER_EXPLOSION_SCALE: f32 = 2.0;
+const PLAYER_EXPLOSION_FRAME_TIME: f32 = 0.15; // seconds
+const PLAYER_BANK_ANGLE: f32 = 0.17; // radians (about 10 degrees)
+const CHARGE_TIME: f32 = 1.0; // seconds to fully charge
+const CHARGE_MIN_LEVEL: f32 = 0.3; // anything less is just a tap
+const FIRE_BUFFER_TIME: f32 = 0.12; // seconds a press waits for the cooldown
+const CHARGE_SIZE_BONUS: f32 = 2.0; // fully charged = 3x the size
+const CHARGE_SPEED_BONUS: f32 = 1.0; // fully charged = 2x the speed
+const CHARGE_METER_SIZE: Vec2 = Vec2::new(24.0, 3.0);
+const CHARGE_METER_OFFSET: f32 = 14.0; // below the ship
+const CHARGE_METER_COLOR: Color = Color::rgb(0.3, 0.8, 1.0);
+const CHARGE_METER_COLOR_FULL: Color = Color::rgb(1.0, 0.9, 0.2);
+
+fn spawn_player(
+    mut commands: Commands,
+    shared_assets: Res<SharedAssets>,
+    playfield: Res<Playfield>,
+) {
+    // Spawn Player in initial position
+    commands.spawn(PlayerBundle::new(
+        &shared_assets,
+        playfield.player_starting_position(),
+    ));
+
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: CHARGE_METER_COLOR,
+                custom_size: Some(CHARGE_METER_SIZE),
+                anchor: Anchor::CenterLeft,
+                ..default()
+            },
+            visibility: Visibility::INVISIBLE,
+            ..default()
+        },
+        ChargeMeter,
+    ));
+}
+
+fn move_player(
+    fixed_timesteps: Res<FixedTimesteps>,
+    game_config: Res<GameConfig>,
+    game_settings: Res<GameSettingsState>,
+    player_input: Res<PlayerInput>,
+    mut query: Query<
+        (
+            &mut Transform,
+            &mut Velocity,
+            &mut Banking,
+            Option<&DualFighter>,
+        ),
+        (With<Player>, Without<Respawning>),
+    >,
+    game_state: Res<GameState>,
+    game_mode: Res<GameMode>,
+    playfield: Res<Playfield>,
+) {
+    if game_state.started && !game_state.intro {
+        // Player is exploding - nothing to move
+        let Ok((mut player_transform, mut velocity, mut banking, dual_fighter)) =
+            query.get_single_mut()
+        else {
+            return;
+        };
+        banking.0 = player_input.direction;
+        let step = fixed_step_seconds(&fixed_timesteps);
+
+        // Only modern mode moves up and down
+        let vertical = if *game_mode == GameMode::Modern {
+            player_input.vertical
+        } else {
+            0.0
+        };
+        let target_velocity =
+            Vec2::new(player_input.direction, vertical) * game_config.player_speed;
+        velocity.0 = Vec2::new(
+            approach_speed(
+                velocity.0.x,
+                target_velocity.x,
+                game_config.player_speed,
+                game_settings.smooth_movement,
+                step,
+            ),
+            approach_speed(
+                velocity.0.y,
+                target_velocity.y,
+                game_config.player_speed,
+                game_settings.smooth_movement,
+                step,
+            ),
+        );
+
+        // Calculate the new player position based on its velocity
+        let new_player_position = player_transform.translation.truncate() + velocity.0 * step;
+
+        // Make sure player doesn't exceed bounds of game area
+        // (leaving room on the right for a docked ship)
+        let bound = playfield.edge_horizontal() - PLAYER_SIZE.x / 2.0;
+        let right_bound = if dual_fighter.is_some() {
+            bound - DUAL_FIGHTER_GAP
+        } else {
+            bound
+        };
+        // (classic mode keeps the ship on the bottom row)
+        let bottom = playfield.player_starting_position().y;
+        let top = if *game_mode == GameMode::Modern {
+            playfield.player_ceiling()
+        } else {
+            bottom
+        };
+        let clamped_position =
+            new_player_position.clamp(Vec2::new(-bound, bottom), Vec2::new(right_bound, top));
+        player_transform.translation.x = clamped_position.x;
+        player_transform.translation.y = clamped_position.y;
+
+        // (so the ship doesn't keep pushing into the wall)
+        if clamped_position.x != new_player_position.x {
+            velocity.0.x = 0.0;
+        }
+        if clamped_position.y != new_player_position.y {
+            velocity.0.y = 0.0;
+        }
+    }
+}
+
+// Instant movement goes straight to the speed the input asks for,
+// smooth movement speeds up (or slows down) towards it
+fn approach_speed(current: f32, target: f32, full_speed: f32, smooth: bool, step: f32) -> f32 {
+    if !smooth {
+        return target;
+    }
+
+    let speeding_up = target.abs() > current.abs() && target * current >= 0.0;
+    let time = if speeding_up {
+        PLAYER_ACCELERATION_TIME
+    } else {
+        PLAYER_DECELERATION_TIME
+    };
+    let max_change = full_speed / time * step;
+    current + (target - current).clamp(-max_change, max_change)
+}
+
+fn shoot_projectile(
+    game_config: Res<GameConfig>,
+    fixed_timesteps: Res<FixedTimesteps>,
+    (mut projectile_timer, mut fire_buffer): (ResMut<ProjectileTimer>, ResMut<FireBuffer>),
+    mut charge_state: ResMut<ChargeState>,
+    mut shot_stats: ResMut<ShotStats>,
+    mut commands: Commands,
+    shared_assets: Res<SharedAssets>,
+    mut projectile_pool: ResMut<ProjectilePool>,
+    player_input: Res<PlayerInput>,
+    max_projectiles: Res<MaxProjectiles>,
+    weapon_mode: Res<WeaponMode>,
+    query: Query<
+        (&Transform, Option<&DualFighter>, Option<&RapidFire>),
+        (With<Player>, Without<Respawning>),
+    >,
+    projectile_query: Query<(), (With<Projectile>, Without<EnemyProjectile>, Without<Pooled>)>,
+    mut projectile_events: EventWriter<ProjectileEvent>,
+    mut sound_events: EventWriter<PlaySoundEvent>,
+    game_state: Res<GameState>,
+) {
+    // We have to "tick" the timer to update it with the latest time
+    let step = Duration::from_secs_f32(fixed_step_seconds(&fixed_timesteps));
+    projectile_timer.0.tick(step);
+    fire_buffer.0.tick(step);
+    if !game_state.started || game_state.intro {
+        charge_state.timer.reset();
+        return;
+    }
+
+    // Player is exploding - can't shoot (and loses any charge)
+    let Ok((player_transform, dual_fighter, rapid_fire)) = query.get_single() else {
+        charge_state.timer.reset();
+        return;
+    };
+
+    // Weapons that fire more at once get to have more in the air
+    // (and with two ships, both of them fire)
+    let mut volley = weapon_volley(*weapon_mode);
+    if dual_fighter.is_some() {
+        let docked_volley: Vec<(f32, Vec2)> = volley
+            .iter()
+            .map(|(offset, direction)| (offset + DUAL_FIGHTER_GAP, *direction))
+            .collect();
+        volley.extend(docked_volley);
+    }
+
+    let (cooldown, max_shots) = if rapid_fire.is_some() {
+        (
+            game_config.projectile_cooldown * RAPID_FIRE_COOLDOWN_SCALE,
+            max_projectiles.0 * RAPID_FIRE_MAX_PROJECTILES_SCALE,
+        )
+    } else {
+        (game_config.projectile_cooldown, max_projectiles.0)
+    };
+
+    // Not while we already have as many shots in the air as we're allowed, or the last one was too recent
+    // (goes for charged shots too)
+    let can_fire =
+        projectile_query.iter().count() < max_shots * volley.len() && projectile_timer.0.finished();
+
+    // Let go of fire - release the charge
+    // (a charge let go when we can't fire is lost, so mashing it can't get around the limits)
+    if !player_input.fire {
+        if charge_state.charging() {
+            let charge = charge_state.level();
+            charge_state.timer.reset();
+            if charge >= CHARGE_MIN_LEVEL && can_fire {
+                println!("[PLAYER] Charged shot at {:.0}%", charge * 100.0);
+                projectile_timer
+                    .0
+                    .set_duration(Duration::from_secs_f32(cooldown));
+                projectile_timer.0.reset();
+                shot_stats.shots_fired += 1;
+                projectile_events.send_default();
+                sound_events.send(PlaySoundEvent(SoundId::ProjectileFired));
+                projectile_pool.fire_charged(
+                    &mut commands,
+                    &shared_assets,
+                    player_transform.translation,
+                    PLAYER_PROJECTILE_DIRECTION
+                        * game_config.projectile_speed
+                        * (1.0 + charge * CHARGE_SPEED_BONUS),
+                    PROJECTILE_SIZE * (1.0 + charge * CHARGE_SIZE_BONUS),
+                );
+                // (a press that's waiting fires once the cooldown is up)
+                return;
+            }
+        }
+    } else {
+        // Holding fire builds up charge.
+        // Only the first press asks for a normal shot - after that we're charging.
+        if !charge_state.charging() {
+            fire_buffer.0.reset();
+        }
+        charge_state.timer.tick(step);
+    }
+
+    // (a press that's waiting still fires after letting go)
+    if fire_buffer.0.finished() {
+        return;
+    }
+
+    // Check if player is allowed to shoot based on internal timer
+    if can_fire {
+        // The press is used up
+        fire_buffer
+            .0
+            .tick(Duration::from_secs_f32(FIRE_BUFFER_TIME));
+
+        // Reset the timer
+        projectile_timer
+            .0
+            .set_duration(Duration::from_secs_f32(cooldown));
+        projectile_timer.0.reset();
+
+        // Fire off a ProjectileEvent to notify other systems
+        projectile_events.send_default();
+        sound_events.send(PlaySoundEvent(SoundId::ProjectileFired));
+
+        // Fire projectiles from the pool
+        shot_stats.shots_fired += volley.len();
+        for (offset, direction) in volley {
+            projectile_pool.fire(
+                &mut commands,
+                &shared_assets,
+                player_transform.translation + Vec3::new(offset, 0.0, 0.0),
+                direction * game_config.projectile_speed,
+            );
+        }
+    }
+}
+
+// Lean the ship into the direction it's moving, easing back to level when it stops.
+// Only visual - projectiles still fire straight up.
+fn bank_player(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    game_state: Res<GameState>,
+    game_config: Res<GameConfig>,
+    mut query: Query<(&mut Transform, &Banking), (With<Player>, Without<Respawning>)>,
+) {
+    if game_state.paused {
+        return;
+    }
+
+    for (mut player_transform, banking) in &mut query {
+        // Moving right leans right (clockwise)
+        let target = -banking.0 * PLAYER_BANK_ANGLE;
+        let (_, _, current) = player_transform.rotation.to_euler(EulerRot::XYZ);
+        let t = (game_config.player_bank_speed * scaled_delta(&time, &time_scale).as_secs_f32())
+            .min(1.0);
+        player_transform.rotation = Quat::from_rotation_z(current + (target - current) * t);
+    }
+}
+
+// Keep the charge meter under the ship, filling it up as the shot charges
+fn update_charge_meter(
+    charge_state: Res<ChargeState>,
+    player_query: Query<(&Transform, &Visibility), (With<Player>, Without<Respawning>)>,
+    mut query: Query<
+        (&mut Transform, &mut Sprite, &mut Visibility),
+        (With<ChargeMeter>, Without<Player>),
+    >,
+) {
+    let Ok((mut meter_transform, mut sprite, mut visibility)) = query.get_single_mut() else {
+        return;
+    };
+    let charge = charge_state.level();
+
+    // Nothing to show until there's enough charge for a charged shot
+    let Ok((player_transform, player_visibility)) = player_query.get_single() else {
+        visibility.is_visible = false;
+        return;
+    };
+    visibility.is_visible = player_visibility.is_visible && charge >= CHARGE_MIN_LEVEL;
+
+    meter_transform.translation = player_transform.translation
+        + Vec3::new(-CHARGE_METER_SIZE.x / 2.0, -CHARGE_METER_OFFSET, 0.0);
+    sprite.custom_size = Some(Vec2::new(CHARGE_METER_SIZE.x * charge, CHARGE_METER_SIZE.y));
+    sprite.color = if charge >= 1.0 {
+        CHARGE_METER_COLOR_FULL
+    } else {
+        CHARGE_METER_COLOR
+    };
+}
+
+// Every projectile the weapon fires at once: how far from the center of the ship it starts, and which way it flies
+fn weapon_volley(weapon_mode: WeaponMode) -> Vec<(f32, Vec2)> {
+    match weapon_mode {
+        WeaponMode::Single => vec![(0.0, PLAYER_PROJECTILE_DIRECTION)],
+        WeaponMode::Double => vec![
+            (-DOUBLE_SHOT_GAP / 2.0, PLAYER_PROJECTILE_DIRECTION),
+            (DOUBLE_SHOT_GAP / 2.0, PLAYER_PROJECTILE_DIRECTION),
+        ],
+        WeaponMode::Spread => [-SPREAD_SHOT_ANGLE, 0.0, SPREAD_SHOT_ANGLE]
+            .iter()
+            .map(|angle| {
+                (
+                    0.0,
+                    Vec2::from_angle(*angle).rotate(PLAYER_PROJECTILE_DIRECTION),
+                )
+            })
+            .collect(),
+    }
+}
+
+// Lose a life when the player is hit and blow up their ship.
+// Once the explosion finishes they respawn (or the game ends if they're out of lives)
+fn kill_player(
+    mut commands: Commands,
+    mut player_death_events: EventReader<PlayerDeathEvent>,
+    mut sound_events: EventWriter<PlaySoundEvent>,
+    mut players: ResMut<Players>,
+    mut game_state: ResMut<GameState>,
+    mut screen_shake: ResMut<ScreenShake>,
+    textures: Res<Textures>,
+    mut query: Query<(Entity, &Transform, &mut Visibility, Option<&Shield>), With<Player>>,
+    docked_fighter_query: Query<Entity, With<DockedFighter>>,
+    shield_ring_query: Query<Entity, With<ShieldRing>>,
+    mut power_up_events: EventWriter<PowerUpEvent>,
+) {
+    // Read every hit, but several hits in the same frame only cost one life
+    let mut hit = false;
+    let mut captured = false;
+    for player_death_event in player_death_events.iter() {
+        hit = true;
+        captured |= player_death_event.captured;
+    }
+    if !hit {
+        return;
+    }
+    let (player_entity, player_transform, mut visibility, shield) = query.single_mut();
+
+    // The shield takes the hit instead (but can't stop a tractor beam)
+    let shielded = shield.is_some();
+    commands.entity(player_entity).remove::<Shield>();
+    for shield_ring_entity in &shield_ring_query {
+        commands.entity(shield_ring_entity).despawn_recursive();
+    }
+    if shielded {
+        power_up_events.send(PowerUpEvent::ShieldBroken);
+        commands.entity(player_entity).insert(HitFlash::default());
+        if !captured {
+            return;
+        }
+    }
+
+    sound_events.send(PlaySoundEvent(SoundId::PlayerDeath));
+
+    let lives = players.current().lives.saturating_sub(1);
+    players.current_mut().lives = lives;
+    println!(
+        "[GAME] Player {} died, {} lives left",
+        players.active + 1,
+        lives
+    );
+
+    // Hide the ship and blow it up (with a big shake).
+    // A captured ship doesn't explode - the tractor beam carries it off instead.
+    visibility.is_visible = false;
+
+    // Back to a single ship (and any rapid fire is lost)
+    commands
+        .entity(player_entity)
+        .remove::<DualFighter>()
+        .remove::<RapidFire>();
+    for docked_fighter_entity in &docked_fighter_query {
+        commands.entity(docked_fighter_entity).despawn_recursive();
+    }
+    if !captured {
+        screen_shake.add_trauma(SCREEN_SHAKE_TRAUMA_PLAYER_DEATH);
+        commands.spawn((
+            SpriteSheetBundle {
+                texture_atlas: textures.explosion_player.clone(),
+                transform: Transform {
+                    translation: player_transform.translation,
+                    scale: Vec3::splat(PLAYER_EXPLOSION_SCALE),
+                    ..default()
+                },
+                ..default()
+            },
+            AnimationTimer(Timer::from_seconds(
+                PLAYER_EXPLOSION_FRAME_TIME,
+                TimerMode::Repeating,
+            )),
+            AnimationFrame(0),
+            PlayerExploding,
+        ));
+    }
+
+    // Keep track of where they got to (for their next turn, or the game over screen)
+    players.current_mut().level = game_state.level;
+
+    // Two players take turns - hand over to the next player in line who has ships left
+    let Some(next_player) = players.next_turn() else {
+        println!("[GAME] Out of ships");
+        game_state.started = false;
+        game_state.intro = false;
+        // Game over wins if the last enemy died at the same time
+        game_state.stage_clear = false;
+        game_state.continue_offer = true;
+        sound_events.send(PlaySoundEvent(SoundId::GameOver));
+        return;
+    };
+    if next_player != players.active {
+        println!("[GAME] Player {}'s turn", next_player + 1);
+        players.active = next_player;
+        game_state.level = players.current().level;
+        game_state.turn_change = true;
+    }
+
+    commands.entity(player_entity).insert(Respawning);
+}
+
+// Bring the player back once their explosion has finished,
+// at the starting position with a short window of invincibility
+fn respawn_player(
+    mut commands: Commands,
+    game_config: Res<GameConfig>,
+    game_state: Res<GameState>,
+    playfield: Res<Playfield>,
+    explosion_query: Query<(), With<PlayerExploding>>,
+    mut query: Query<(Entity, &mut Transform), (With<Player>, With<Respawning>)>,
+) {
+    // (the next player's ship waits for their turn to start)
+    if !explosion_query.is_empty() || game_state.turn_change {
+        return;
+    }
+
+    for (player_entity, mut player_transform) in &mut query {
+        player_transform.translation = playfield.player_starting_position();
+        // Come back level, not leaning the way we were going when we died
+        player_transform.rotation = Quat::IDENTITY;
+        commands
+            .entity(player_entity)
+            .remove::<Respawning>()
+            .insert(Banking::default())
+            .insert(Velocity(Vec2::ZERO))
+            .insert(Invincible(Timer::from_seconds(
+                game_config.player_invincible_time,
+                TimerMode::Once,
+            )));
+    }
+}
+
+// Blink the player while they're invincible, then make them vulnerable again
+fn blink_invincible_player(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    game_state: Res<GameState>,
+    mut query: Query<(Entity, &mut Invincible, &mut Visibility), With<Player>>,
+) {
+    if game_state.paused {
+        return;
+    }
+
+    for (player_entity, mut invincible, mut visibility) in &mut query {
+        if invincible
+            .0
+            .tick(scaled_delta(&time, &time_scale))
+            .finished()
+        {
+            visibility.is_visible = true;
+            commands.entity(player_entity).remove::<Invincible>();
+            continue;
+        }
+
+        // Visible for one blink, hidden for the next
+        visibility.is_visible =
+            (invincible.0.elapsed_secs() / (PLAYER_BLINK_TIME * 2.0)).fract() < 0.5;
+    }
+}
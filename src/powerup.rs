@@ -0,0 +1,292 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, sprite::collide_aabb::collide, time::FixedTimesteps};
+use rand::seq::SliceRandom;
+
+use crate::{material::HitFlash, shared::*};
+
+pub struct PowerUpPlugin;
+
+impl Plugin for PowerUpPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WeaponMode>()
+            .insert_resource(WeaponTimer(Timer::from_seconds(
+                POWER_UP_DURATION,
+                TimerMode::Once,
+            )))
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(FixedStep)
+                    .with_system(move_power_ups.before(GameSystem::Collision))
+                    .with_system(collect_power_ups.before(GameSystem::Collision)),
+            )
+            .add_system(announce_dropped_power_ups)
+            .add_system(expire_weapon)
+            .add_system(expire_rapid_fire)
+            .add_system(expire_shield);
+    }
+}
+
+// Timer used to switch back to the normal weapon after a power-up
+#[derive(Resource)]
+struct WeaponTimer(Timer);
+
+const POWER_UP_KINDS: [PowerUpKind; 3] = [
+    PowerUpKind::Weapon(WeaponMode::Double),
+    PowerUpKind::Weapon(WeaponMode::Spread),
+    PowerUpKind::RapidFire,
+];
+const RAPID_FIRE_DURATION: f32 = 8.0; // seconds
+const POWER_UP_SPREAD_COLOR: Color = Color::CYAN;
+// Shields are rare, so they fall a little slower to give the player a chance to grab them
+const SHIELD_FALL_SPEED: f32 = 60.0;
+const SHIELD_RING_SIZE: f32 = 1.6; // times the size of the ship
+const SHIELD_RING_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.6);
+const SHIELD_DURATION: f32 = 20.0; // seconds
+const SHIELD_WARNING_TIME: f32 = 3.0; // seconds left when the ring starts blinking
+const SHIELD_BLINK_TIME: f32 = 0.15; // seconds (on for this long, then off for this long)
+
+// Spawn a random power-up that slowly falls from where an enemy was destroyed
+pub fn spawn_power_up(
+    commands: &mut Commands,
+    textures: &Textures,
+    game_rng: &mut GameRng,
+    translation: Vec3,
+) {
+    let kind = *POWER_UP_KINDS
+        .choose(game_rng)
+        .unwrap_or(&PowerUpKind::Weapon(WeaponMode::Double));
+    let color = match kind {
+        PowerUpKind::Weapon(WeaponMode::Spread) => POWER_UP_SPREAD_COLOR,
+        PowerUpKind::RapidFire => POWER_UP_RAPID_FIRE_COLOR,
+        _ => Color::WHITE,
+    };
+
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite { color, ..default() },
+            texture: textures.power_up.clone(),
+            transform: Transform::from_translation(translation),
+            ..default()
+        },
+        PowerUp(kind),
+        Velocity(Vec2::new(0.0, -POWER_UP_FALL_SPEED)),
+    ));
+}
+
+// Spawn a shield that slowly falls from where a boss was destroyed
+pub fn spawn_shield(commands: &mut Commands, textures: &Textures, translation: Vec3) {
+    commands.spawn((
+        SpriteBundle {
+            texture: textures.shield.clone(),
+            transform: Transform::from_translation(translation),
+            ..default()
+        },
+        PowerUp(PowerUpKind::Shield),
+        Velocity(Vec2::new(0.0, -SHIELD_FALL_SPEED)),
+    ));
+}
+
+// Let everyone know when a power-up shows up
+fn announce_dropped_power_ups(
+    query: Query<&PowerUp, Added<PowerUp>>,
+    mut power_up_events: EventWriter<PowerUpEvent>,
+) {
+    for power_up in &query {
+        power_up_events.send(PowerUpEvent::Dropped(power_up.0));
+    }
+}
+
+fn move_power_ups(
+    mut commands: Commands,
+    fixed_timesteps: Res<FixedTimesteps>,
+    playfield: Res<Playfield>,
+    mut query: Query<(Entity, &mut Transform, &Velocity, &PowerUp)>,
+    mut power_up_events: EventWriter<PowerUpEvent>,
+) {
+    for (power_up_entity, mut power_up_transform, velocity, power_up) in &mut query {
+        power_up_transform.translation +=
+            (velocity.0 * fixed_step_seconds(&fixed_timesteps)).extend(0.0);
+
+        // Player missed it
+        if power_up_transform.translation.y < -playfield.edge_vertical() {
+            commands.entity(power_up_entity).despawn();
+            power_up_events.send(PowerUpEvent::Missed(power_up.0));
+        }
+    }
+}
+
+// Power up the player when they fly into a power-up
+fn collect_power_ups(
+    mut commands: Commands,
+    game_config: Res<GameConfig>,
+    textures: Res<Textures>,
+    mut weapon_mode: ResMut<WeaponMode>,
+    mut weapon_timer: ResMut<WeaponTimer>,
+    power_up_query: Query<(Entity, &Transform, &PowerUp)>,
+    player_query: Query<
+        (Entity, &Transform, Option<&DualFighter>, Option<&Shield>),
+        (With<Player>, Without<Respawning>),
+    >,
+    mut power_up_events: EventWriter<PowerUpEvent>,
+) {
+    let Ok((player_entity, player_transform, dual_fighter, shield)) = player_query.get_single()
+    else {
+        return;
+    };
+    let (player_translation, player_size) = player_hitbox(player_transform, dual_fighter.is_some());
+
+    for (power_up_entity, power_up_transform, power_up) in &power_up_query {
+        let collision = collide(
+            power_up_transform.translation,
+            POWER_UP_SIZE,
+            player_translation,
+            player_size,
+        );
+
+        if collision.is_none() {
+            continue;
+        }
+
+        commands.entity(power_up_entity).despawn();
+        commands.entity(player_entity).insert(HitFlash::default());
+        power_up_events.send(PowerUpEvent::Collected(power_up.0));
+        match power_up.0 {
+            PowerUpKind::Weapon(new_weapon_mode) => {
+                // Picking up another one while it's active starts the timer over
+                *weapon_mode = new_weapon_mode;
+                weapon_timer
+                    .0
+                    .set_duration(Duration::from_secs_f32(game_config.power_up_duration));
+                weapon_timer.0.reset();
+            }
+            // Another one while it's active starts the timer over
+            PowerUpKind::RapidFire => {
+                commands
+                    .entity(player_entity)
+                    .insert(RapidFire(Timer::from_seconds(
+                        RAPID_FIRE_DURATION,
+                        TimerMode::Once,
+                    )));
+            }
+            // Shields don't stack, another one while it's active just starts the timer over
+            PowerUpKind::Shield if shield.is_some() => {
+                commands
+                    .entity(player_entity)
+                    .insert(Shield(Timer::from_seconds(
+                        SHIELD_DURATION,
+                        TimerMode::Once,
+                    )));
+            }
+            PowerUpKind::Shield => {
+                // (the ring is a child of the player, so its size is in the ship's scaled space)
+                commands
+                    .entity(player_entity)
+                    .insert(Shield(Timer::from_seconds(
+                        SHIELD_DURATION,
+                        TimerMode::Once,
+                    )))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            SpriteBundle {
+                                sprite: Sprite {
+                                    color: SHIELD_RING_COLOR,
+                                    custom_size: Some(Vec2::splat(SHIELD_RING_SIZE)),
+                                    ..default()
+                                },
+                                texture: textures.shield.clone(),
+                                ..default()
+                            },
+                            ShieldRing,
+                        ));
+                    });
+            }
+        }
+    }
+}
+
+// Go back to the normal weapon once the power-up runs out
+fn expire_weapon(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    game_state: Res<GameState>,
+    mut weapon_mode: ResMut<WeaponMode>,
+    mut weapon_timer: ResMut<WeaponTimer>,
+) {
+    if game_state.paused || *weapon_mode == WeaponMode::Single {
+        return;
+    }
+
+    if weapon_timer
+        .0
+        .tick(scaled_delta(&time, &time_scale))
+        .just_finished()
+    {
+        println!("[POWER UP] {:?} expired", *weapon_mode);
+        *weapon_mode = WeaponMode::Single;
+    }
+}
+
+// Take away rapid fire once it runs out
+fn expire_rapid_fire(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    game_state: Res<GameState>,
+    mut query: Query<(Entity, &mut RapidFire)>,
+) {
+    if game_state.paused {
+        return;
+    }
+
+    for (player_entity, mut rapid_fire) in &mut query {
+        if rapid_fire
+            .0
+            .tick(scaled_delta(&time, &time_scale))
+            .just_finished()
+        {
+            println!("[POWER UP] Rapid fire expired");
+            commands.entity(player_entity).remove::<RapidFire>();
+        }
+    }
+}
+
+// Take the shield away if it goes unused for too long.
+// The ring blinks for the last few seconds as a warning.
+fn expire_shield(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    game_state: Res<GameState>,
+    mut query: Query<(Entity, &mut Shield)>,
+    mut shield_ring_query: Query<(Entity, &mut Visibility), With<ShieldRing>>,
+    mut power_up_events: EventWriter<PowerUpEvent>,
+) {
+    if game_state.paused {
+        return;
+    }
+
+    for (player_entity, mut shield) in &mut query {
+        if shield
+            .0
+            .tick(scaled_delta(&time, &time_scale))
+            .just_finished()
+        {
+            commands.entity(player_entity).remove::<Shield>();
+            for (shield_ring_entity, _) in &shield_ring_query {
+                commands.entity(shield_ring_entity).despawn_recursive();
+            }
+            power_up_events.send(PowerUpEvent::ShieldExpired);
+            continue;
+        }
+
+        let remaining = shield.0.remaining_secs();
+        let visible = remaining >= SHIELD_WARNING_TIME
+            || ((remaining / SHIELD_BLINK_TIME) as usize).is_multiple_of(2);
+        for (_, mut visibility) in &mut shield_ring_query {
+            if visibility.is_visible != visible {
+                visibility.is_visible = visible;
+            }
+        }
+    }
+}
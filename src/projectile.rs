@@ -0,0 +1,414 @@
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle, time::FixedTimesteps};
+
+use crate::{material::CustomMaterial, shared::*};
+
+pub struct ProjectilePlugin;
+
+impl Plugin for ProjectilePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ProjectilePool>()
+            .add_startup_system(fill_projectile_pool)
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(FixedStep)
+                    .with_system(steer_homing_projectiles.before(move_projectiles))
+                    .with_system(move_projectiles.before(GameSystem::Collision))
+                    .with_system(destroy_projectiles.before(GameSystem::Collision)),
+            );
+    }
+}
+
+// A projectile waiting in the ProjectilePool to be fired (hidden, and ignored by movement and collisions)
+#[derive(Component)]
+pub struct Pooled;
+
+// Projectiles are reused instead of spawning and despawning one every shot.
+// Holds every projectile that isn't flying right now.
+#[derive(Resource, Default)]
+pub struct ProjectilePool(Vec<Entity>);
+
+impl ProjectilePool {
+    // Take a projectile out of the pool and send it flying
+    // (if they're all in use we spawn another one, which joins the pool once it's released)
+    pub fn fire(
+        &mut self,
+        commands: &mut Commands,
+        shared_assets: &SharedAssets,
+        translation: Vec3,
+        velocity: Vec2,
+    ) -> Entity {
+        let projectile = self.launch(
+            commands,
+            shared_assets,
+            translation,
+            velocity,
+            PROJECTILE_SIZE,
+        );
+        commands
+            .entity(projectile)
+            .remove::<(EnemyProjectile, Sprite, Handle<Image>)>()
+            .insert(shared_assets.projectile.clone());
+        projectile
+    }
+
+    // Fire an enemy's projectile.
+    // They're drawn as a sprite with the enemy projectile texture instead of the player's material.
+    pub fn fire_enemy(
+        &mut self,
+        commands: &mut Commands,
+        shared_assets: &SharedAssets,
+        textures: &Textures,
+        translation: Vec3,
+        velocity: Vec2,
+    ) -> Entity {
+        let projectile = self.launch(
+            commands,
+            shared_assets,
+            translation,
+            velocity,
+            PROJECTILE_SIZE,
+        );
+        commands
+            .entity(projectile)
+            .remove::<Handle<CustomMaterial>>()
+            .insert((
+                EnemyProjectile,
+                // (sized to the quad, so the transform scales it the same)
+                Sprite {
+                    custom_size: Some(Vec2::ONE),
+                    ..default()
+                },
+                textures.enemy_projectile.clone(),
+            ));
+        projectile
+    }
+
+    // Fire a player's charged shot - a bigger projectile that pierces through enemies
+    pub fn fire_charged(
+        &mut self,
+        commands: &mut Commands,
+        shared_assets: &SharedAssets,
+        translation: Vec3,
+        velocity: Vec2,
+        size: Vec3,
+    ) {
+        let projectile = self.launch(commands, shared_assets, translation, velocity, size);
+        commands
+            .entity(projectile)
+            .remove::<(EnemyProjectile, Sprite, Handle<Image>)>()
+            .insert((
+                Piercing,
+                HitEnemies::default(),
+                shared_assets.projectile.clone(),
+            ));
+    }
+
+    fn launch(
+        &mut self,
+        commands: &mut Commands,
+        shared_assets: &SharedAssets,
+        translation: Vec3,
+        velocity: Vec2,
+        size: Vec3,
+    ) -> Entity {
+        let projectile = match self.0.pop() {
+            Some(projectile) => projectile,
+            None => {
+                println!("[PROJECTILE] Pool is empty, spawning a new projectile");
+                spawn_pooled_projectile(commands, shared_assets)
+            }
+        };
+
+        commands.entity(projectile).remove::<Pooled>().insert((
+            Transform {
+                translation,
+                scale: size,
+                ..default()
+            },
+            Velocity(velocity),
+            Visibility::VISIBLE,
+        ));
+        projectile
+    }
+
+    // Hide a projectile and put it back in the pool (instead of despawning it)
+    pub fn release(&mut self, commands: &mut Commands, projectile: Entity) {
+        // Might get released twice in one frame (e.g. hits an enemy and leaves the screen)
+        if self.0.contains(&projectile) {
+            return;
+        }
+        commands
+            .entity(projectile)
+            .remove::<Piercing>()
+            .remove::<HitEnemies>()
+            .remove::<Homing>()
+            .remove::<Wiggle>()
+            .insert((Pooled, Visibility::INVISIBLE));
+        self.0.push(projectile);
+    }
+}
+
+const PROJECTILE_POOL_SIZE: usize = 64;
+
+// Spawn an inactive projectile, ready to be fired from the pool
+fn spawn_pooled_projectile(commands: &mut Commands, shared_assets: &SharedAssets) -> Entity {
+    commands
+        .spawn((
+            MaterialMesh2dBundle {
+                mesh: shared_assets.quad.clone().into(),
+                transform: Transform::from_scale(PROJECTILE_SIZE),
+                material: shared_assets.projectile.clone(),
+                visibility: Visibility::INVISIBLE,
+                ..default()
+            },
+            Projectile,
+            Velocity(Vec2::ZERO),
+            Pooled,
+        ))
+        .id()
+}
+
+fn fill_projectile_pool(
+    mut commands: Commands,
+    shared_assets: Res<SharedAssets>,
+    mut projectile_pool: ResMut<ProjectilePool>,
+) {
+    for _ in 0..PROJECTILE_POOL_SIZE {
+        let projectile = spawn_pooled_projectile(&mut commands, &shared_assets);
+        projectile_pool.0.push(projectile);
+    }
+}
+
+// Turn homing projectiles towards the player, but only a little at a time so they can be dodged.
+// Once they're past the player they give up and fly straight.
+fn steer_homing_projectiles(
+    fixed_timesteps: Res<FixedTimesteps>,
+    mut query: Query<(&Transform, &mut Velocity, &Homing), Without<Pooled>>,
+    player_query: Query<&Transform, (With<Player>, Without<Respawning>, Without<Homing>)>,
+) {
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+    let max_turn = fixed_step_seconds(&fixed_timesteps);
+
+    for (projectile_transform, mut velocity, homing) in &mut query {
+        let to_player =
+            (player_transform.translation - projectile_transform.translation).truncate();
+        if to_player.y >= 0.0 {
+            continue;
+        }
+        let angle = velocity.0.angle_between(to_player);
+        let turn = angle.clamp(-homing.turn_rate * max_turn, homing.turn_rate * max_turn);
+        velocity.0 = Vec2::from_angle(turn).rotate(velocity.0);
+    }
+}
+
+impl Wiggle {
+    // How far to the side of its straight path it is right now
+    fn offset(&self) -> f32 {
+        self.amplitude * (std::f32::consts::TAU * self.frequency * self.age).sin()
+    }
+}
+
+fn move_projectiles(
+    fixed_timesteps: Res<FixedTimesteps>,
+    mut query: Query<
+        (&mut Transform, &Velocity, Option<&mut Wiggle>),
+        (With<Projectile>, Without<Pooled>),
+    >,
+) {
+    let step = fixed_step_seconds(&fixed_timesteps);
+    for (mut collider_transform, velocity, wiggle) in &mut query {
+        // (projectiles past the edges of the screen get cleaned up by destroy_projectiles)
+        collider_transform.translation += (velocity.0 * step).extend(0.0);
+
+        // Wiggling ones swing side to side as they go
+        if let Some(mut wiggle) = wiggle {
+            let last_offset = wiggle.offset();
+            wiggle.age += step;
+            collider_transform.translation.x += wiggle.offset() - last_offset;
+        }
+    }
+}
+
+fn destroy_projectiles(
+    mut commands: Commands,
+    mut projectile_pool: ResMut<ProjectilePool>,
+    query: Query<(Entity, &Transform), (With<Projectile>, Without<Pooled>)>,
+    playfield: Res<Playfield>,
+) {
+    for (collider_entity, collider_transform) in &query {
+        // Check if projectile has passed any edge of the screen
+        if collider_transform.translation.y.abs() > playfield.edge_vertical()
+            || collider_transform.translation.x.abs() > playfield.edge_horizontal()
+        {
+            projectile_pool.release(&mut commands, collider_entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use bevy::time::FixedTimestep;
+
+    use super::*;
+
+    // How many shots fire_volley fires each update
+    #[derive(Resource)]
+    struct Volley(usize);
+
+    // Just the quad mesh - the materials aren't needed to fire projectiles
+    fn setup_shared_assets(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+        commands.insert_resource(SharedAssets {
+            quad: meshes.add(Mesh::from(shape::Quad::default())),
+            player: default(),
+            projectile: default(),
+        });
+    }
+
+    // Put last update's shots back in the pool, then fire a new volley
+    fn fire_volley(
+        mut commands: Commands,
+        shared_assets: Res<SharedAssets>,
+        volley: Res<Volley>,
+        mut projectile_pool: ResMut<ProjectilePool>,
+        query: Query<Entity, (With<Projectile>, Without<Pooled>)>,
+    ) {
+        for projectile in &query {
+            projectile_pool.release(&mut commands, projectile);
+        }
+        for _ in 0..volley.0 {
+            projectile_pool.fire(
+                &mut commands,
+                &shared_assets,
+                Vec3::ZERO,
+                Vec2::Y * PROJECTILE_SPEED,
+            );
+        }
+    }
+
+    fn test_app(volley: usize) -> App {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin::default())
+            .add_asset::<Mesh>()
+            .init_resource::<ProjectilePool>()
+            .insert_resource(Volley(volley))
+            .add_startup_system_to_stage(StartupStage::PreStartup, setup_shared_assets)
+            .add_startup_system(fill_projectile_pool)
+            .add_system(fire_volley);
+        app
+    }
+
+    // Run move_projectiles on a fixed step of `step` seconds for one simulated second,
+    // and see how far a projectile got
+    fn distance_in_one_second(step: f64) -> f32 {
+        position_after_one_second(step, None).y
+    }
+
+    fn position_after_one_second(step: f64, wiggle: Option<Wiggle>) -> Vec3 {
+        // (64 frames a second, so the frame and step lengths add up exactly)
+        let frame_time = Duration::from_secs_f64(1.0 / 64.0);
+
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<FixedTimesteps>()
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(FixedTimestep::step(step).with_label(FIXED_STEP_NAME))
+                    .with_system(move_projectiles),
+            );
+        let projectile = app
+            .world
+            .spawn((
+                Transform::default(),
+                Velocity(Vec2::Y * PROJECTILE_SPEED),
+                Projectile,
+            ))
+            .id();
+        if let Some(wiggle) = wiggle {
+            app.world.entity_mut(projectile).insert(wiggle);
+        }
+
+        let start = Instant::now();
+        app.world.resource_mut::<Time>().update_with_instant(start);
+        for frame in 1..=64 {
+            app.world
+                .resource_mut::<Time>()
+                .update_with_instant(start + frame_time * frame);
+            app.update();
+        }
+        app.world.get::<Transform>(projectile).unwrap().translation
+    }
+
+    #[test]
+    fn projectiles_move_the_same_at_any_step_rate() {
+        let at_64hz = distance_in_one_second(1.0 / 64.0);
+        let at_128hz = distance_in_one_second(1.0 / 128.0);
+        assert!((at_64hz - PROJECTILE_SPEED).abs() < 0.01, "{}", at_64hz);
+        assert!((at_128hz - PROJECTILE_SPEED).abs() < 0.01, "{}", at_128hz);
+    }
+
+    #[test]
+    fn wiggling_projectiles_swing_back_to_their_path() {
+        let wiggle = |frequency| Wiggle {
+            amplitude: 40.0,
+            frequency,
+            age: 0.0,
+        };
+        // A quarter of the way through a swing it's all the way out to the side
+        let quarter_swing = position_after_one_second(1.0 / 64.0, Some(wiggle(0.25)));
+        assert!((quarter_swing.x - 40.0).abs() < 0.01, "{}", quarter_swing);
+        // After a whole swing it's back in line, and it never slowed down
+        let whole_swing = position_after_one_second(1.0 / 64.0, Some(wiggle(1.0)));
+        assert!(whole_swing.x.abs() < 0.01, "{}", whole_swing);
+        assert!(
+            (whole_swing.y - PROJECTILE_SPEED).abs() < 0.01,
+            "{}",
+            whole_swing
+        );
+    }
+
+    #[test]
+    fn firing_does_not_add_meshes() {
+        let mut app = test_app(1);
+        app.update();
+        let meshes = app.world.resource::<Assets<Mesh>>().len();
+
+        for _ in 0..100 {
+            app.update();
+        }
+        assert_eq!(app.world.resource::<Assets<Mesh>>().len(), meshes);
+    }
+
+    fn count_projectiles(app: &mut App) -> usize {
+        app.world
+            .query_filtered::<(), With<Projectile>>()
+            .iter(&app.world)
+            .count()
+    }
+
+    #[test]
+    fn pool_stops_growing_once_it_covers_a_volley() {
+        // More at once than the pool starts with, so it has to spawn extras the first time
+        let volley = PROJECTILE_POOL_SIZE + 16;
+        let mut app = test_app(volley);
+        app.update();
+        assert_eq!(count_projectiles(&mut app), volley);
+
+        // Thousands more shots, all reusing the same entities
+        for _ in 0..100 {
+            app.update();
+        }
+        assert_eq!(count_projectiles(&mut app), volley);
+        assert_eq!(
+            app.world
+                .query_filtered::<(), (With<Projectile>, Without<Pooled>)>()
+                .iter(&app.world)
+                .count(),
+            volley
+        );
+    }
+}
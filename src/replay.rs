@@ -0,0 +1,259 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{cli::LaunchOptions, settings::PlayerInput, shared::*};
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        let launch_options = app.world.resource::<LaunchOptions>().clone();
+        let mut replay = Replay::default();
+
+        if let Some(path) = launch_options.record {
+            println!("[REPLAY] Recording to {}", path.display());
+            replay.mode = ReplayMode::Recording(path);
+        }
+
+        // Play back with the same seed and starting level the recording had
+        if let Some(path) = launch_options.replay {
+            let file = ReplayFile::load(&path).unwrap_or_else(|error| {
+                println!("[REPLAY] {}", error);
+                std::process::exit(1);
+            });
+            println!(
+                "[REPLAY] Playing {} ({} steps)",
+                path.display(),
+                file.inputs.len()
+            );
+            *app.world.resource_mut::<GameRng>() = GameRng::new(Some(file.seed));
+            let mut launch_options = app.world.resource_mut::<LaunchOptions>();
+            launch_options.level = Some(file.level);
+            launch_options.skip_intro = file.skip_intro;
+            app.insert_resource(file.mode);
+            replay.file = file;
+            replay.mode = ReplayMode::Playing;
+        }
+
+        app.insert_resource(replay)
+            .add_system(start_replay)
+            .add_system_set(
+                SystemSet::new().with_run_criteria(FixedStep).with_system(
+                    replay_input
+                        .label(GameSystem::ReplayInput)
+                        .before(GameSystem::Collision),
+                ),
+            );
+    }
+}
+
+// Replays only play back on the version they were recorded with
+// (gameplay changes between versions would throw them off)
+const REPLAY_VERSION: &str = env!("CARGO_PKG_VERSION");
+// How often the player's position is checked against the recording (in fixed steps)
+const CHECKSUM_STEPS: usize = 60;
+// How often a recording is written out (in fixed steps), so closing the game doesn't lose much of it
+const SAVE_STEPS: usize = 600;
+
+// Input bits for each step (see pack_input)
+const INPUT_LEFT: u8 = 1;
+const INPUT_RIGHT: u8 = 2;
+const INPUT_FIRE: u8 = 4;
+const INPUT_UP: u8 = 8;
+const INPUT_DOWN: u8 = 16;
+
+// A recorded game: everything needed to play it back the same way
+#[derive(Serialize, Deserialize, Default)]
+struct ReplayFile {
+    version: String,
+    seed: u64,
+    level: usize,
+    skip_intro: bool,
+    #[serde(default)]
+    mode: GameMode,
+    // The player's input for every fixed step from the start of the game
+    inputs: Vec<u8>,
+    // Where the player was every CHECKSUM_STEPS steps, to spot when playback drifts
+    checksums: Vec<u32>,
+}
+
+impl ReplayFile {
+    fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|error| format!("Couldn't read {}: {}", path.display(), error))?;
+        let file = ron::from_str::<ReplayFile>(&contents)
+            .map_err(|error| format!("Couldn't parse {}: {}", path.display(), error))?;
+        if file.version != REPLAY_VERSION {
+            return Err(format!(
+                "{} was recorded with version {}, this is version {}",
+                path.display(),
+                file.version,
+                REPLAY_VERSION
+            ));
+        }
+        Ok(file)
+    }
+
+    fn save(&self, path: &Path) {
+        match ron::to_string(self) {
+            Ok(contents) => {
+                if let Err(error) = fs::write(path, contents) {
+                    println!("[REPLAY] Couldn't save {}: {}", path.display(), error);
+                }
+            }
+            Err(error) => println!("[REPLAY] Couldn't serialize replay: {}", error),
+        }
+    }
+}
+
+#[derive(Default)]
+enum ReplayMode {
+    #[default]
+    Off,
+    Recording(PathBuf),
+    Playing,
+}
+
+#[derive(Resource, Default)]
+struct Replay {
+    mode: ReplayMode,
+    file: ReplayFile,
+    // Fixed steps since the game started
+    step: usize,
+    // Has playback drifted from the recording? (only warn once)
+    desynced: bool,
+}
+
+// Start from the top every game, and write out the recording once the game ends
+fn start_replay(
+    mut replay: ResMut<Replay>,
+    game_state: Res<GameState>,
+    game_rng: Res<GameRng>,
+    game_mode: Res<GameMode>,
+    launch_options: Res<LaunchOptions>,
+    mut start_events: EventReader<GameStartEvent>,
+    mut was_started: Local<bool>,
+) {
+    if start_events.iter().count() > 0 {
+        replay.step = 0;
+        replay.desynced = false;
+        if let ReplayMode::Recording(_) = replay.mode {
+            replay.file = ReplayFile {
+                version: REPLAY_VERSION.to_string(),
+                seed: game_rng.seed,
+                level: game_state.level,
+                skip_intro: launch_options.skip_intro,
+                mode: *game_mode,
+                ..default()
+            };
+        }
+    }
+
+    if *was_started && !game_state.started {
+        if let ReplayMode::Recording(ref path) = replay.mode {
+            println!("[REPLAY] Saved {} steps to {}", replay.step, path.display());
+            replay.file.save(path);
+        }
+    }
+    *was_started = game_state.started;
+}
+
+// Record the player's input every fixed step, or feed the recorded input back in
+fn replay_input(
+    mut replay: ResMut<Replay>,
+    mut player_input: ResMut<PlayerInput>,
+    game_state: Res<GameState>,
+    player_query: Query<&Transform, With<Player>>,
+) {
+    if !game_state.started || game_state.demo {
+        return;
+    }
+
+    let checksum = replay.step.is_multiple_of(CHECKSUM_STEPS).then(|| {
+        player_query
+            .get_single()
+            .map_or(0, |transform| position_checksum(transform.translation))
+    });
+    let replay = &mut *replay;
+    let step = replay.step;
+    replay.step += 1;
+
+    match replay.mode {
+        ReplayMode::Off => {}
+        ReplayMode::Recording(ref path) => {
+            replay.file.inputs.push(pack_input(&player_input));
+            if let Some(checksum) = checksum {
+                replay.file.checksums.push(checksum);
+            }
+            if replay.step.is_multiple_of(SAVE_STEPS) {
+                replay.file.save(path);
+            }
+        }
+        ReplayMode::Playing => {
+            let Some(input) = replay.file.inputs.get(step).copied() else {
+                println!("[REPLAY] Replay finished after {} steps", step);
+                replay.mode = ReplayMode::Off;
+                return;
+            };
+            unpack_input(input, &mut player_input);
+
+            let recorded = replay.file.checksums.get(step / CHECKSUM_STEPS).copied();
+            if let (Some(checksum), Some(recorded)) = (checksum, recorded) {
+                if checksum != recorded && !replay.desynced {
+                    println!("[REPLAY] !!! DESYNC at step {} !!!", step);
+                    println!("[REPLAY] The player isn't where the recording had them, playback won't match from here on");
+                    replay.desynced = true;
+                }
+            }
+        }
+    }
+}
+
+fn pack_input(player_input: &PlayerInput) -> u8 {
+    let mut input = 0;
+    if player_input.direction < 0.0 {
+        input |= INPUT_LEFT;
+    }
+    if player_input.direction > 0.0 {
+        input |= INPUT_RIGHT;
+    }
+    if player_input.vertical > 0.0 {
+        input |= INPUT_UP;
+    }
+    if player_input.vertical < 0.0 {
+        input |= INPUT_DOWN;
+    }
+    if player_input.fire {
+        input |= INPUT_FIRE;
+    }
+    input
+}
+
+fn unpack_input(input: u8, player_input: &mut PlayerInput) {
+    let mut direction = 0.0;
+    if input & INPUT_LEFT != 0 {
+        direction -= 1.0;
+    }
+    if input & INPUT_RIGHT != 0 {
+        direction += 1.0;
+    }
+    let mut vertical = 0.0;
+    if input & INPUT_DOWN != 0 {
+        vertical -= 1.0;
+    }
+    if input & INPUT_UP != 0 {
+        vertical += 1.0;
+    }
+    player_input.direction = direction;
+    player_input.vertical = vertical;
+    player_input.fire = input & INPUT_FIRE != 0;
+}
+
+fn position_checksum(translation: Vec3) -> u32 {
+    translation.x.to_bits() ^ translation.y.to_bits().rotate_left(16)
+}
@@ -0,0 +1,136 @@
+use bevy::prelude::*;
+use gilrs::{
+    ff::{BaseEffect, BaseEffectType, Effect, EffectBuilder, Repeat, Replay, Ticks},
+    GamepadId, Gilrs,
+};
+
+use crate::{settings::GameSettingsState, shared::*};
+
+pub struct RumblePlugin;
+
+impl Plugin for RumblePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(rumble_gamepads);
+    }
+}
+
+// A big jolt when the player loses a ship
+const PLAYER_DEATH_RUMBLE_STRENGTH: f32 = 1.0; // 0.0 - 1.0
+const PLAYER_DEATH_RUMBLE_TIME: u32 = 400; // milliseconds
+
+// A light buzz for every enemy destroyed, a little stronger when several go at once
+const ENEMY_DEATH_RUMBLE_STRENGTH: f32 = 0.25;
+const ENEMY_DEATH_RUMBLE_STRENGTH_PER_EXTRA: f32 = 0.15;
+const ENEMY_DEATH_RUMBLE_STRENGTH_MAX: f32 = 0.7;
+const ENEMY_DEATH_RUMBLE_TIME: u32 = 80; // milliseconds
+
+// The rumble playing right now.
+// gilrs stops an effect once it's dropped, so we hold on to it until it's done.
+struct ActiveRumble {
+    // (never read, just kept alive)
+    _effect: Effect,
+    strength: f32,
+    timer: Timer,
+}
+
+// Rumble every connected gamepad when the player is hit or enemies are destroyed
+fn rumble_gamepads(
+    time: Res<Time>,
+    game_state: Res<GameState>,
+    game_settings: Res<GameSettingsState>,
+    // (missing if gilrs couldn't start, e.g. no gamepad support on this system)
+    gilrs: Option<NonSendMut<Gilrs>>,
+    mut player_death_events: EventReader<PlayerDeathEvent>,
+    mut enemy_death_events: EventReader<EnemyDeathEvent>,
+    mut active: Local<Option<ActiveRumble>>,
+) {
+    let player_died = player_death_events.iter().count() > 0;
+    let enemies_destroyed = enemy_death_events
+        .iter()
+        .filter(|event| !event.shot_cancelled)
+        .count();
+
+    // Let go of the last rumble once it's finished
+    let finished = active
+        .as_mut()
+        .is_some_and(|rumble| rumble.timer.tick(frame_delta(&time)).finished());
+    if finished {
+        *active = None;
+    }
+
+    let Some(mut gilrs) = gilrs else {
+        return;
+    };
+    // (the demo plays itself, nobody's holding the controller)
+    if !game_settings.rumble || game_state.demo {
+        *active = None;
+        return;
+    }
+
+    // (gamepads have a strong, low rumble motor and a weak, high one)
+    let (strong, strength, duration) = if player_died {
+        (true, PLAYER_DEATH_RUMBLE_STRENGTH, PLAYER_DEATH_RUMBLE_TIME)
+    } else if enemies_destroyed > 0 {
+        let extra = (enemies_destroyed - 1) as f32 * ENEMY_DEATH_RUMBLE_STRENGTH_PER_EXTRA;
+        (
+            false,
+            (ENEMY_DEATH_RUMBLE_STRENGTH + extra).min(ENEMY_DEATH_RUMBLE_STRENGTH_MAX),
+            ENEMY_DEATH_RUMBLE_TIME,
+        )
+    } else {
+        return;
+    };
+
+    // Don't cut a bigger rumble short
+    if active
+        .as_ref()
+        .is_some_and(|rumble| rumble.strength > strength)
+    {
+        return;
+    }
+
+    let gamepads: Vec<GamepadId> = gilrs
+        .gamepads()
+        .filter(|(_, gamepad)| gamepad.is_ff_supported())
+        .map(|(id, _)| id)
+        .collect();
+    if gamepads.is_empty() {
+        return;
+    }
+
+    let magnitude = (strength * u16::MAX as f32) as u16;
+    let kind = if strong {
+        BaseEffectType::Strong { magnitude }
+    } else {
+        BaseEffectType::Weak { magnitude }
+    };
+    let effect = EffectBuilder::new()
+        .add_effect(BaseEffect {
+            kind,
+            scheduling: Replay {
+                play_for: Ticks::from_ms(duration),
+                ..default()
+            },
+            ..default()
+        })
+        .repeat(Repeat::For(Ticks::from_ms(duration)))
+        .gamepads(&gamepads)
+        .finish(&mut gilrs);
+    let effect = match effect {
+        Ok(effect) => effect,
+        Err(error) => {
+            println!("[RUMBLE] Couldn't create rumble: {}", error);
+            return;
+        }
+    };
+    if let Err(error) = effect.play() {
+        println!("[RUMBLE] Couldn't play rumble: {}", error);
+        return;
+    }
+
+    *active = Some(ActiveRumble {
+        _effect: effect,
+        strength,
+        timer: Timer::from_seconds(duration as f32 / 1000.0, TimerMode::Once),
+    });
+}
@@ -0,0 +1,459 @@
+use std::{fs, path::PathBuf};
+
+use bevy::{input::InputSystem, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audio::step_volume,
+    cli::LaunchOptions,
+    shared::{GameState, GameSystem},
+};
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = load_settings();
+
+        app.insert_resource(settings.key_bindings)
+            .insert_resource(settings.game_settings)
+            .init_resource::<PlayerInput>()
+            .init_resource::<PauseMenu>()
+            .add_startup_system(apply_launch_options)
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                collect_player_input
+                    .label(GameSystem::CollectInput)
+                    .after(InputSystem),
+            )
+            .add_system(
+                update_pause_menu
+                    .label(GameSystem::UpdatePauseMenu)
+                    .after(GameSystem::PauseGame),
+            );
+    }
+}
+
+// The keys assigned to each player action. Loaded from (and saved to) the settings file.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub move_left: KeyCode,
+    pub move_right: KeyCode,
+    // Only used in modern mode
+    pub move_up: KeyCode,
+    pub move_down: KeyCode,
+    pub fire: KeyCode,
+    pub pause: KeyCode,
+    pub start: KeyCode,
+    pub restart: KeyCode,
+    // Adds a credit (only used when free play is off)
+    pub coin: KeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            move_left: KeyCode::Left,
+            move_right: KeyCode::Right,
+            move_up: KeyCode::Up,
+            move_down: KeyCode::Down,
+            fire: KeyCode::Space,
+            pause: KeyCode::P,
+            start: KeyCode::Return,
+            restart: KeyCode::R,
+            // Like MAME
+            coin: KeyCode::Key5,
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn get(&self, action: InputAction) -> KeyCode {
+        match action {
+            InputAction::MoveLeft => self.move_left,
+            InputAction::MoveRight => self.move_right,
+            InputAction::MoveUp => self.move_up,
+            InputAction::MoveDown => self.move_down,
+            InputAction::Fire => self.fire,
+            InputAction::Pause => self.pause,
+            InputAction::Start => self.start,
+            InputAction::Restart => self.restart,
+            InputAction::Coin => self.coin,
+        }
+    }
+
+    pub fn set(&mut self, action: InputAction, key: KeyCode) {
+        match action {
+            InputAction::MoveLeft => self.move_left = key,
+            InputAction::MoveRight => self.move_right = key,
+            InputAction::MoveUp => self.move_up = key,
+            InputAction::MoveDown => self.move_down = key,
+            InputAction::Fire => self.fire = key,
+            InputAction::Pause => self.pause = key,
+            InputAction::Start => self.start = key,
+            InputAction::Restart => self.restart = key,
+            InputAction::Coin => self.coin = key,
+        }
+    }
+
+    // Which action (if any) is already using this key
+    pub fn action_for(&self, key: KeyCode) -> Option<InputAction> {
+        INPUT_ACTIONS
+            .iter()
+            .copied()
+            .find(|action| self.get(*action) == key)
+    }
+}
+
+// Every action the player can rebind
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InputAction {
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    Fire,
+    Pause,
+    Start,
+    Restart,
+    Coin,
+}
+
+const INPUT_ACTIONS: [InputAction; 9] = [
+    InputAction::MoveLeft,
+    InputAction::MoveRight,
+    InputAction::MoveUp,
+    InputAction::MoveDown,
+    InputAction::Fire,
+    InputAction::Pause,
+    InputAction::Start,
+    InputAction::Restart,
+    InputAction::Coin,
+];
+
+impl InputAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            InputAction::MoveLeft => "MOVE LEFT",
+            InputAction::MoveRight => "MOVE RIGHT",
+            InputAction::MoveUp => "MOVE UP",
+            InputAction::MoveDown => "MOVE DOWN",
+            InputAction::Fire => "FIRE",
+            InputAction::Pause => "PAUSE",
+            InputAction::Start => "START",
+            InputAction::Restart => "RESTART",
+            InputAction::Coin => "COIN",
+        }
+    }
+}
+
+// The gameplay input for this frame, collected from the keyboard using the KeyBindings
+#[derive(Resource, Default)]
+pub struct PlayerInput {
+    // -1 = left, 1 = right, 0 = not moving
+    pub direction: f32,
+    // -1 = down, 1 = up, 0 = not moving (only used in modern mode)
+    pub vertical: f32,
+    pub fire: bool,
+}
+
+// A row in the settings menu on the pause screen
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PauseMenuItem {
+    KeyBinding(InputAction),
+    MusicVolume,
+    SfxVolume,
+    ScreenShake,
+    Rumble,
+    ScoringMode,
+    Movement,
+    BulletTime,
+    ShotCancelling,
+}
+
+pub const PAUSE_MENU_ITEMS: [PauseMenuItem; 17] = [
+    PauseMenuItem::KeyBinding(InputAction::MoveLeft),
+    PauseMenuItem::KeyBinding(InputAction::MoveRight),
+    PauseMenuItem::KeyBinding(InputAction::MoveUp),
+    PauseMenuItem::KeyBinding(InputAction::MoveDown),
+    PauseMenuItem::KeyBinding(InputAction::Fire),
+    PauseMenuItem::KeyBinding(InputAction::Pause),
+    PauseMenuItem::KeyBinding(InputAction::Start),
+    PauseMenuItem::KeyBinding(InputAction::Restart),
+    PauseMenuItem::KeyBinding(InputAction::Coin),
+    PauseMenuItem::MusicVolume,
+    PauseMenuItem::SfxVolume,
+    PauseMenuItem::ScreenShake,
+    PauseMenuItem::Rumble,
+    PauseMenuItem::ScoringMode,
+    PauseMenuItem::Movement,
+    PauseMenuItem::BulletTime,
+    PauseMenuItem::ShotCancelling,
+];
+
+// State of the settings menu on the pause screen
+#[derive(Resource, Default)]
+pub struct PauseMenu {
+    // Index into PAUSE_MENU_ITEMS
+    pub selected: usize,
+    // Are we waiting for the player to press the new key?
+    pub listening: bool,
+    // Feedback shown under the menu (e.g. conflicts)
+    pub message: String,
+}
+
+// Player adjustable game settings (like audio volume)
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct GameSettingsState {
+    // Volume for music like the intro (0.0 - 1.0)
+    pub music_volume: f32,
+    // Volume for sound effects like projectiles (0.0 - 1.0)
+    pub sfx_volume: f32,
+    // Skip all audio playback
+    pub muted: bool,
+    // Shake the camera when things explode (some players find it uncomfortable)
+    pub screen_shake: bool,
+    // Rumble gamepads when the player is hit or enemies are destroyed
+    pub rumble: bool,
+    // Start games without credits.
+    // Turn it off for an arcade cabinet, where every player needs a coin (see KeyBindings::coin).
+    pub freeplay: bool,
+    // Quick kills in a row multiply their points (off = classic arcade scoring).
+    // Takes effect from the next game.
+    pub combo_scoring: bool,
+    // The ship speeds up and slows down instead of moving at full speed straight away
+    pub smooth_movement: bool,
+    // Slow motion for a moment when an enemy shot only just misses
+    pub bullet_time: bool,
+    // The player's shots can knock out enemy shots (off = arcade rules)
+    pub shot_cancelling: bool,
+}
+
+impl Default for GameSettingsState {
+    fn default() -> Self {
+        GameSettingsState {
+            music_volume: VOLUME_DEFAULT,
+            sfx_volume: VOLUME_DEFAULT,
+            muted: false,
+            screen_shake: true,
+            rumble: true,
+            freeplay: true,
+            combo_scoring: false,
+            smooth_movement: false,
+            bullet_time: true,
+            shot_cancelling: false,
+        }
+    }
+}
+
+// Everything we persist to the settings file
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct SettingsFile {
+    key_bindings: KeyBindings,
+    game_settings: GameSettingsState,
+}
+
+// Lives next to the executable
+const SETTINGS_FILE: &str = "settings.ron";
+const VOLUME_DEFAULT: f32 = 0.1;
+
+// The volume passed on the command line wins over the settings file
+// (only saved if the settings get changed in game)
+fn apply_launch_options(
+    launch_options: Res<LaunchOptions>,
+    mut game_settings: ResMut<GameSettingsState>,
+) {
+    if let Some(volume) = launch_options.volume {
+        game_settings.music_volume = volume;
+        game_settings.sfx_volume = volume;
+    }
+    if launch_options.mute {
+        game_settings.muted = true;
+    }
+}
+
+// Translates the raw keyboard state into gameplay input using the player's key bindings
+fn collect_player_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    key_bindings: Res<KeyBindings>,
+    mut player_input: ResMut<PlayerInput>,
+) {
+    let mut direction = 0.0;
+
+    if keyboard_input.pressed(key_bindings.move_left) {
+        direction -= 1.0;
+    }
+
+    if keyboard_input.pressed(key_bindings.move_right) {
+        direction += 1.0;
+    }
+
+    let mut vertical = 0.0;
+
+    if keyboard_input.pressed(key_bindings.move_down) {
+        vertical -= 1.0;
+    }
+
+    if keyboard_input.pressed(key_bindings.move_up) {
+        vertical += 1.0;
+    }
+
+    player_input.direction = direction;
+    player_input.vertical = vertical;
+    player_input.fire = keyboard_input.pressed(key_bindings.fire);
+}
+
+// The settings menu on the pause screen.
+// Up/Down selects a row. On a key binding, Return starts listening and the next key pressed becomes the binding.
+// On a volume slider, Left/Right changes the volume.
+fn update_pause_menu(
+    game_state: Res<GameState>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut key_bindings: ResMut<KeyBindings>,
+    mut menu: ResMut<PauseMenu>,
+    mut game_settings: ResMut<GameSettingsState>,
+) {
+    if !game_state.paused {
+        // Reset the menu so it's fresh next time we pause
+        if menu.listening || !menu.message.is_empty() {
+            *menu = PauseMenu::default();
+        }
+        return;
+    }
+
+    let item = PAUSE_MENU_ITEMS[menu.selected];
+
+    if menu.listening {
+        // Wait for the next key press
+        if let (PauseMenuItem::KeyBinding(action), Some(key)) =
+            (item, keyboard_input.get_just_pressed().next())
+        {
+            menu.listening = false;
+
+            // Don't let two actions share the same key
+            match key_bindings.action_for(*key) {
+                Some(existing) if existing != action => {
+                    menu.message = format!("{:?} IS ALREADY BOUND TO {}", key, existing.label())
+                        .to_uppercase();
+                }
+                _ => {
+                    key_bindings.set(action, *key);
+                    save_settings(&key_bindings, &game_settings);
+                    menu.message = format!("{} BOUND TO {:?}", action.label(), key).to_uppercase();
+                }
+            }
+        }
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        menu.selected = (menu.selected + PAUSE_MENU_ITEMS.len() - 1) % PAUSE_MENU_ITEMS.len();
+    }
+    if keyboard_input.just_pressed(KeyCode::Down) {
+        menu.selected = (menu.selected + 1) % PAUSE_MENU_ITEMS.len();
+    }
+
+    match item {
+        PauseMenuItem::KeyBinding(action) => {
+            if keyboard_input.just_pressed(KeyCode::Return) {
+                menu.listening = true;
+                menu.message = format!("PRESS NEW KEY FOR {}", action.label());
+            }
+        }
+        PauseMenuItem::MusicVolume | PauseMenuItem::SfxVolume => {
+            let mut steps = 0.0;
+            if keyboard_input.just_pressed(KeyCode::Left) {
+                steps -= 1.0;
+            }
+            if keyboard_input.just_pressed(KeyCode::Right) {
+                steps += 1.0;
+            }
+            if steps == 0.0 {
+                return;
+            }
+
+            if item == PauseMenuItem::MusicVolume {
+                game_settings.music_volume = step_volume(game_settings.music_volume, steps);
+            } else {
+                game_settings.sfx_volume = step_volume(game_settings.sfx_volume, steps);
+            }
+            save_settings(&key_bindings, &game_settings);
+        }
+        PauseMenuItem::ScreenShake => {
+            if keyboard_input.any_just_pressed([KeyCode::Left, KeyCode::Right, KeyCode::Return]) {
+                game_settings.screen_shake = !game_settings.screen_shake;
+                save_settings(&key_bindings, &game_settings);
+            }
+        }
+        PauseMenuItem::Rumble => {
+            if keyboard_input.any_just_pressed([KeyCode::Left, KeyCode::Right, KeyCode::Return]) {
+                game_settings.rumble = !game_settings.rumble;
+                save_settings(&key_bindings, &game_settings);
+            }
+        }
+        PauseMenuItem::ScoringMode => {
+            if keyboard_input.any_just_pressed([KeyCode::Left, KeyCode::Right, KeyCode::Return]) {
+                game_settings.combo_scoring = !game_settings.combo_scoring;
+                save_settings(&key_bindings, &game_settings);
+            }
+        }
+        PauseMenuItem::Movement => {
+            if keyboard_input.any_just_pressed([KeyCode::Left, KeyCode::Right, KeyCode::Return]) {
+                game_settings.smooth_movement = !game_settings.smooth_movement;
+                save_settings(&key_bindings, &game_settings);
+            }
+        }
+        PauseMenuItem::BulletTime => {
+            if keyboard_input.any_just_pressed([KeyCode::Left, KeyCode::Right, KeyCode::Return]) {
+                game_settings.bullet_time = !game_settings.bullet_time;
+                save_settings(&key_bindings, &game_settings);
+            }
+        }
+        PauseMenuItem::ShotCancelling => {
+            if keyboard_input.any_just_pressed([KeyCode::Left, KeyCode::Right, KeyCode::Return]) {
+                game_settings.shot_cancelling = !game_settings.shot_cancelling;
+                save_settings(&key_bindings, &game_settings);
+            }
+        }
+    }
+}
+
+// Where a saved file lives (next to the executable)
+pub fn save_file_path(file_name: &str) -> PathBuf {
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(file_name)))
+        .unwrap_or_else(|| PathBuf::from(file_name))
+}
+
+// Load settings from the settings file, falling back to defaults if it's missing or malformed
+fn load_settings() -> SettingsFile {
+    let path = save_file_path(SETTINGS_FILE);
+    match fs::read_to_string(&path) {
+        Ok(contents) => ron::from_str::<SettingsFile>(&contents).unwrap_or_else(|error| {
+            println!("[SETTINGS] Couldn't parse {}: {}", path.display(), error);
+            SettingsFile::default()
+        }),
+        Err(_) => SettingsFile::default(),
+    }
+}
+
+pub fn save_settings(key_bindings: &KeyBindings, game_settings: &GameSettingsState) {
+    let path = save_file_path(SETTINGS_FILE);
+    let settings = SettingsFile {
+        key_bindings: *key_bindings,
+        game_settings: *game_settings,
+    };
+
+    match ron::ser::to_string_pretty(&settings, ron::ser::PrettyConfig::default()) {
+        Ok(contents) => {
+            if let Err(error) = fs::write(&path, contents) {
+                println!("[SETTINGS] Couldn't save {}: {}", path.display(), error);
+            }
+        }
+        Err(error) => println!("[SETTINGS] Couldn't serialize settings: {}", error),
+    }
+}
@@ -0,0 +1,922 @@
+use std::time::Duration;
+
+use bevy::{
+    asset::{Asset, HandleId, LoadState},
+    prelude::*,
+    time::FixedTimesteps,
+};
+
+use rand::{
+    distributions::uniform::{SampleRange, SampleUniform},
+    rngs::StdRng,
+    Rng, RngCore, SeedableRng,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{material::CustomMaterial, wave::EnemyKind};
+
+// The Player object
+#[derive(Component)]
+pub struct Player;
+
+// The Enemy object
+#[derive(Component)]
+pub struct Enemy;
+
+// A Boss Galaga - takes more than one hit and is worth more points
+#[derive(Component)]
+pub struct Boss;
+
+// How many more hits an enemy can take
+#[derive(Component)]
+pub struct Health(pub usize);
+
+// An enemy that breaks apart into smaller ships when it's destroyed (what kind, and how many)
+#[derive(Component, Deserialize, Clone, Copy, Debug)]
+pub struct Splits(pub EnemyKind, pub u8);
+
+impl Default for Health {
+    fn default() -> Self {
+        Health(1)
+    }
+}
+
+// The projectile spawned by Player firing weapon
+#[derive(Component)]
+pub struct Projectile;
+
+// The projectile spawned by an Enemy firing at the player
+#[derive(Component)]
+pub struct EnemyProjectile;
+
+// A charged shot - keeps flying after it hits an enemy
+#[derive(Component)]
+pub struct Piercing;
+
+// Enemies a piercing shot has already hit
+// (it overlaps an enemy for a few steps on the way through, but only hits it once)
+#[derive(Component, Default)]
+pub struct HitEnemies(pub Vec<Entity>);
+
+// Player can't be hit until the timer finishes (e.g. right after respawning)
+#[derive(Component)]
+pub struct Invincible(pub Timer);
+
+// Player was destroyed and is waiting for their explosion to finish before respawning
+#[derive(Component)]
+pub struct Respawning;
+
+// Which way the player's ship is moving (-1 = left, 1 = right, 0 = not moving),
+// so it can lean into the turn
+#[derive(Component, Default)]
+pub struct Banking(pub f32);
+
+// Explosions
+// Timer used to step through each frame of a sprite sheet animation
+#[derive(Component, Deref, DerefMut)]
+pub struct AnimationTimer(pub Timer);
+
+// The current frame of a sprite sheet animation
+#[derive(Component, Deref, DerefMut)]
+pub struct AnimationFrame(pub usize);
+
+// The explosion left behind when the player is destroyed
+#[derive(Component)]
+pub struct PlayerExploding;
+
+// The speed of an object
+#[derive(Component, Deref, DerefMut)]
+pub struct Velocity(pub Vec2);
+
+// A pickup dropped by an enemy that falls towards the player
+#[derive(Component)]
+pub struct PowerUp(pub PowerUpKind);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PowerUpKind {
+    // Switches the player's weapon for a while
+    Weapon(WeaponMode),
+    // Takes the next hit instead of the player (only dropped by bosses)
+    Shield,
+    // Shoots faster, with more shots in the air, for a while
+    RapidFire,
+}
+
+// The player picked up rapid fire (runs out when the timer finishes)
+#[derive(Component)]
+pub struct RapidFire(pub Timer);
+
+// The player's ship has a shield that takes the next hit (wears off when the timer finishes)
+#[derive(Component)]
+pub struct Shield(pub Timer);
+
+// The ring drawn around a shielded ship (a child of the player)
+#[derive(Component)]
+pub struct ShieldRing;
+
+// A formation enemy that left its slot to attack the player
+// (the slot stays reserved in the FormationGrid until it's back, or destroyed)
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Sortie {
+    // Swooping down at the player
+    Diving,
+    // Looped around from the bottom of the screen, flying back to its slot
+    Returning,
+}
+
+// A diving boss's escorts (the enemies still flying with it)
+#[derive(Component)]
+pub struct Escorted(pub Vec<Entity>);
+
+// An enemy flying alongside a diving boss, keeping the same offset from it as in the formation
+#[derive(Component)]
+pub struct Escort {
+    pub boss: Entity,
+    pub offset: Vec2,
+}
+
+// An enemy projectile that steers towards the player (turn rate in radians per second)
+#[derive(Component)]
+pub struct Homing {
+    pub turn_rate: f32,
+}
+
+// An enemy projectile that weaves side to side as it falls
+// (move_projectiles shifts it along a sine wave on top of its velocity)
+#[derive(Component)]
+pub struct Wiggle {
+    // How far it swings to either side (in pixels)
+    pub amplitude: f32,
+    // Swings per second
+    pub frequency: f32,
+    // Seconds since it was fired
+    pub age: f32,
+}
+
+// Signifies an object is collidable
+#[derive(Component)]
+pub struct Collider;
+
+// Points awarded text that floats up from a destroyed enemy
+#[derive(Component)]
+pub struct ScorePopup {
+    pub lifetime: Timer,
+    pub velocity: Vec2,
+}
+
+// A boss's tractor beam, reaching down to catch the player's ship
+#[derive(Component)]
+pub struct TractorBeam {
+    pub boss: Entity,
+    pub timer: Timer,
+}
+
+// The player's ship after it was caught by a tractor beam.
+// Held above the boss that caught it, until that boss is destroyed and it's rescued.
+#[derive(Component)]
+pub struct CapturedFighter {
+    pub boss: Entity,
+    pub rescued: bool,
+}
+
+// A rescued ship flying alongside the player
+#[derive(Component)]
+pub struct DockedFighter;
+
+// The player is flying two ships side by side (their own, plus a DockedFighter to the right)
+#[derive(Component)]
+pub struct DualFighter;
+
+// The area of the player's ship that can get hit (twice as wide when flying two ships)
+pub fn player_hitbox(player_transform: &Transform, dual_fighter: bool) -> (Vec3, Vec2) {
+    if dual_fighter {
+        (
+            player_transform.translation + Vec3::new(DUAL_FIGHTER_GAP / 2.0, 0.0, 0.0),
+            player_transform.scale.truncate() + Vec2::new(DUAL_FIGHTER_GAP, 0.0),
+        )
+    } else {
+        (
+            player_transform.translation,
+            player_transform.scale.truncate(),
+        )
+    }
+}
+
+// A fading copy of a player's projectile, left behind as a trail
+#[derive(Component)]
+pub struct Afterimage(pub Timer);
+
+// Events
+// Enemy Death
+#[derive(Default)]
+pub struct EnemyDeathEvent {
+    pub points: usize,
+    pub kind: EnemyKind,
+    pub translation: Vec3,
+    // Set if the enemy breaks apart into more enemies
+    pub splits: Option<Splits>,
+    // Not an enemy at all - a player's shot knocked out an enemy's (only with shot cancelling turned on).
+    // Worth a few points, but it isn't a kill.
+    pub shot_cancelled: bool,
+}
+
+// Something happened to a power-up
+pub enum PowerUpEvent {
+    Dropped(PowerUpKind),
+    // Fell off the bottom of the screen
+    Missed(PowerUpKind),
+    Collected(PowerUpKind),
+    // The player's shield took a hit
+    ShieldBroken,
+    // The player's shield wore off without taking a hit
+    ShieldExpired,
+}
+
+// Projectile has been fired
+#[derive(Default)]
+pub struct ProjectileEvent;
+
+#[derive(Default)]
+pub struct GameStartEvent;
+
+// The attract mode demo finished, and we're back on the start screen
+#[derive(Default)]
+pub struct DemoEndEvent;
+
+// Wipe the current run and start a fresh one (from the title screen, or restarting mid-game)
+#[derive(Default)]
+pub struct ResetGameEvent;
+
+// An enemy projectile only just missed the player
+#[derive(Default)]
+pub struct NearMissEvent;
+
+// Player got hit (or caught in a tractor beam)
+#[derive(Default)]
+pub struct PlayerDeathEvent {
+    pub captured: bool,
+}
+
+// Play a sound from the SoundLibrary
+pub struct PlaySoundEvent(pub SoundId);
+
+// A player's score went up (or was reset), with their new total
+pub struct ScoreChangedEvent {
+    pub player: usize,
+    pub score: usize,
+}
+
+// A level is starting (the intro finished, or every enemy in the last level was destroyed)
+#[derive(Default)]
+pub struct NewLevelEvent;
+
+// Sounds
+// Every sound the game can play
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SoundId {
+    ProjectileFired,
+    EnemyProjectileFired,
+    EnemyDeath,
+    PlayerDeath,
+    Intro,
+    StageClear,
+    GameOver,
+    PowerUp,
+    ShieldBreak,
+    CountdownTick,
+    BulletTime,
+    ShotCancelled,
+}
+
+// Resources
+// Score, ships left, and level for everyone playing.
+// Two players take turns, switching whenever the active player loses a ship.
+#[derive(Resource)]
+pub struct Players {
+    // How many people are playing (picked on the start screen)
+    pub count: usize,
+    // Whose turn it is
+    pub active: usize,
+    pub progress: [PlayerProgress; MAX_PLAYERS],
+}
+
+// Coins inserted but not played yet (when free play is off)
+#[derive(Resource, Default)]
+pub struct Credits(pub usize);
+
+// Picked on the start screen. Classic keeps the ship on the bottom row like the arcade,
+// modern lets it move up and down too (within the bottom quarter of the playfield).
+// Each mode keeps its own leaderboard.
+#[derive(Resource, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameMode {
+    #[default]
+    Classic,
+    Modern,
+}
+
+impl GameMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            GameMode::Classic => "CLASSIC",
+            GameMode::Modern => "MODERN",
+        }
+    }
+}
+
+// Kills in quick succession for combo scoring.
+// Each kill inside the window bumps the multiplier, and it drops back once the window runs out.
+#[derive(Resource)]
+pub struct ComboState {
+    // Is this game using combo scoring? (picked from the settings when the game starts)
+    pub enabled: bool,
+    // 0 = no chain going
+    pub multiplier: usize,
+    // Time left to get the next kill
+    pub timer: Timer,
+}
+
+impl Default for ComboState {
+    fn default() -> Self {
+        ComboState {
+            enabled: false,
+            multiplier: 0,
+            timer: Timer::from_seconds(COMBO_WINDOW, TimerMode::Once),
+        }
+    }
+}
+
+// A fade to black and back between screens (like the start screen and the intro).
+// The action happens once the screen is fully black.
+#[derive(Resource)]
+pub struct Transition {
+    // Runs for the fade out and the fade in
+    pub timer: Timer,
+    // What to do once it's black (None once it's done)
+    pub action: Option<TransitionAction>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TransitionAction {
+    StartGame,
+    StartDemo,
+    EndDemo,
+}
+
+impl Default for Transition {
+    fn default() -> Self {
+        let duration = TRANSITION_FADE_TIME * 2.0;
+        let mut timer = Timer::from_seconds(duration, TimerMode::Once);
+        // Nothing to fade at first
+        timer.tick(Duration::from_secs_f32(duration));
+        Transition {
+            timer,
+            action: None,
+        }
+    }
+}
+
+impl Transition {
+    pub fn start(&mut self, action: TransitionAction) {
+        self.timer.reset();
+        self.action = Some(action);
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.timer.finished()
+    }
+
+    // Has it faded all the way out yet?
+    pub fn is_black(&self) -> bool {
+        self.timer.elapsed_secs() >= TRANSITION_FADE_TIME
+    }
+
+    // How dark the screen is (0 = clear, 1 = black)
+    pub fn darkness(&self) -> f32 {
+        if !self.is_active() {
+            return 0.0;
+        }
+        1.0 - (self.timer.percent() * 2.0 - 1.0).abs()
+    }
+}
+
+// All the gameplay randomness (enemy fire, dives, drops) comes from here,
+// so a game can be played again by starting it with the same seed (`--seed <number>`)
+#[derive(Resource)]
+pub struct GameRng {
+    // Passed in on the command line, otherwise every game picks a new seed
+    pub fixed_seed: Option<u64>,
+    // The seed the current game started with
+    pub seed: u64,
+    rng: StdRng,
+}
+
+impl GameRng {
+    pub fn new(fixed_seed: Option<u64>) -> Self {
+        let seed = fixed_seed.unwrap_or_else(rand::random);
+        GameRng {
+            fixed_seed,
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    // Start the sequence over for a new game
+    pub fn restart(&mut self) {
+        *self = GameRng::new(self.fixed_seed);
+    }
+
+    // A random number in the range, e.g. `range(0..enemy_count)`
+    pub fn range<T: SampleUniform, R: SampleRange<T>>(&mut self, range: R) -> T {
+        self.rng.gen_range(range)
+    }
+
+    // True this often (0.0 = never, 1.0 = always).
+    // Chances from the config file can be out of range, so they're clamped instead of panicking.
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.rng.gen_bool(probability.clamp(0.0, 1.0))
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+// Developer cheats (only usable when built with the `debug-tools` feature, see cheats.rs)
+#[derive(Resource, Default)]
+pub struct Cheats {
+    // Enemies and their shots go right through the player
+    pub invincible: bool,
+    // Has any cheat been used since the game was launched? (then no scores count for the high score)
+    pub used: bool,
+}
+
+// Debug frame-stepping while paused (only usable with the `debug-tools` feature, see frame_step.rs)
+#[derive(Resource, Default)]
+pub struct FrameStep {
+    // Run a single fixed step this frame, even though the game is paused
+    pub stepping: bool,
+    // Has this frame's fixed step run yet?
+    pub step_taken: bool,
+}
+
+// How fast the game world runs (1.0 = normal, lower = slow motion during bullet time, see bullet_time.rs)
+#[derive(Resource)]
+pub struct TimeScale(pub f32);
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        TimeScale(1.0)
+    }
+}
+
+// The "CONTINUE?" countdown after the last ship is lost
+#[derive(Resource)]
+pub struct ContinueCountdown {
+    // The number on screen (the offer runs out a second after it hits 0)
+    pub seconds_left: usize,
+    pub timer: Timer,
+}
+
+impl Default for ContinueCountdown {
+    fn default() -> Self {
+        ContinueCountdown {
+            seconds_left: CONTINUE_COUNTDOWN_START,
+            timer: Timer::from_seconds(1.0, TimerMode::Repeating),
+        }
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct PlayerProgress {
+    pub score: usize,
+    pub lives: usize,
+    // The level to play on their next turn (GameState has the level being played right now)
+    pub level: usize,
+}
+
+impl Default for Players {
+    fn default() -> Self {
+        Players {
+            count: 1,
+            active: 0,
+            progress: [PlayerProgress::default(); MAX_PLAYERS],
+        }
+    }
+}
+
+impl Players {
+    pub fn current(&self) -> &PlayerProgress {
+        &self.progress[self.active]
+    }
+
+    pub fn current_mut(&mut self) -> &mut PlayerProgress {
+        &mut self.progress[self.active]
+    }
+
+    // Who plays next - the next player in line with ships left
+    // (the active player keeps going if nobody else has any, and it's game over when nobody does)
+    pub fn next_turn(&self) -> Option<usize> {
+        (1..=self.count)
+            .map(|offset| (self.active + offset) % self.count)
+            .find(|player| self.progress[*player].lives > 0)
+    }
+}
+
+// The players current score
+#[derive(Resource)]
+pub struct GameState {
+    // Has game started? (aka user presses "start")
+    pub started: bool,
+    // Is game paused? Only relevant is game is started
+    pub paused: bool,
+    // Are we playing game intro? Occurs after initial game start.
+    pub intro: bool,
+    // Are we between levels? (showing "STAGE N" while the stage clear jingle plays)
+    pub stage_clear: bool,
+    // Are we showing the game over screen? Occurs after the last life is lost, before going back to the start screen.
+    pub game_over: bool,
+    // Are we handing over to the next player? (showing "PLAYER N" before their turn starts)
+    pub turn_change: bool,
+    // Are we counting down to continue? Occurs after the last life is lost, before the game over screen.
+    pub continue_offer: bool,
+    // Are players entering their initials for the leaderboard? Occurs after the game over screen (only for high scores).
+    pub name_entry: bool,
+    // Is the leaderboard up? Opened from the start screen.
+    pub showing_leaderboard: bool,
+    // Is the attract mode demo playing? The computer plays by itself until a key is pressed.
+    pub demo: bool,
+    // The level number (1-99+)
+    pub level: usize,
+    // Are we still waiting on assets? Nothing can start until they're all loaded.
+    pub loading: bool,
+}
+
+// How the player did on the current challenge stage
+// (kept around after it ends so the results can be shown between levels)
+#[derive(Resource, Default)]
+pub struct ChallengeStage {
+    // Is the current (or just finished) level a challenge stage?
+    pub active: bool,
+    pub hits: usize,
+    pub total: usize,
+    // Points awarded once the stage is over
+    pub bonus: usize,
+}
+
+impl ChallengeStage {
+    pub fn perfect(&self) -> bool {
+        self.total > 0 && self.hits == self.total
+    }
+}
+
+// How accurate the player has been this stage (shown between levels, with a bonus)
+#[derive(Resource, Default)]
+pub struct ShotStats {
+    pub shots_fired: usize,
+    pub hits: usize,
+    // Points awarded once the stage is over
+    pub bonus: usize,
+}
+
+impl ShotStats {
+    // Percentage of shots that hit something
+    pub fn hit_ratio(&self) -> f32 {
+        if self.shots_fired == 0 {
+            return 0.0;
+        }
+        // (a charged shot can hit more than one enemy, so don't go over 100%)
+        (self.hits as f32 / self.shots_fired as f32 * 100.0).min(100.0)
+    }
+}
+
+// Gameplay tuning values, read by the systems each time they're used
+// (so they can be changed while the game is running)
+#[derive(Resource)]
+pub struct GameConfig {
+    pub player_speed: f32,
+    pub player_lives: usize,
+    // Seconds the player can't be hit after respawning
+    pub player_invincible_time: f32,
+    pub projectile_speed: f32,
+    // Seconds between player shots
+    pub projectile_cooldown: f32,
+    pub enemy_points: usize,
+    pub boss_points: usize,
+    pub power_up_drop_chance: f64,
+    // Seconds a power-up lasts
+    pub power_up_duration: f32,
+    // Chance a destroyed boss drops a shield
+    pub shield_drop_chance: f64,
+    // How far ahead of the player aimed enemy shots go, in seconds of the player's movement
+    // (0 = straight at where they are now)
+    pub enemy_aim_lead: f32,
+    // Distance between enemies in the formation
+    pub formation_gap: f32,
+    // Seconds between enemy shots on the first level, and the fastest it gets on later levels
+    pub enemy_fire_interval_start: f32,
+    pub enemy_fire_interval_min: f32,
+    // How quickly the ship leans into a turn (higher = snappier)
+    pub player_bank_speed: f32,
+    // How much the camera pans towards the player, as a fraction of the player's distance
+    // from the middle (0 = camera stays still)
+    pub camera_follow_strength: f32,
+    // Points for shooting down an enemy projectile (with shot cancelling turned on)
+    pub shot_cancel_points: usize,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            player_speed: PLAYER_SPEED,
+            player_lives: PLAYER_LIVES,
+            player_invincible_time: PLAYER_INVINCIBLE_TIME,
+            projectile_speed: PROJECTILE_SPEED,
+            projectile_cooldown: PROJECTILE_TIME_LIMIT,
+            enemy_points: ENEMY_POINTS,
+            boss_points: BOSS_POINTS,
+            power_up_drop_chance: POWER_UP_DROP_CHANCE,
+            power_up_duration: POWER_UP_DURATION,
+            shield_drop_chance: SHIELD_DROP_CHANCE,
+            enemy_aim_lead: ENEMY_AIM_LEAD,
+            formation_gap: FORMATION_GAP,
+            enemy_fire_interval_start: ENEMY_FIRE_INTERVAL_START,
+            enemy_fire_interval_min: ENEMY_FIRE_INTERVAL_MIN,
+            player_bank_speed: PLAYER_BANK_SPEED,
+            camera_follow_strength: CAMERA_FOLLOW_STRENGTH,
+            shot_cancel_points: SHOT_CANCEL_POINTS,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct GameFonts {
+    pub body: Handle<Font>,
+}
+
+// Every asset the game needs before it can start (and the paths of any that failed to load)
+#[derive(Resource, Default)]
+pub struct LoadingAssets {
+    pub handles: Vec<HandleUntyped>,
+    // Other files to try for handles that fail to load (see load_with_fallback)
+    pub fallbacks: Vec<AssetFallback>,
+    // How many of the handles have finished loading (for the loading screen's progress)
+    pub loaded: usize,
+    pub failed: Vec<String>,
+}
+
+impl LoadingAssets {
+    // Start loading an asset and keep track of it, so the game waits until it's ready
+    pub fn load<T: Asset>(&mut self, asset_server: &AssetServer, path: &str) -> Handle<T> {
+        let handle = asset_server.load(path);
+        self.handles.push(handle.clone_untyped());
+        handle
+    }
+
+    // Like load, but if `path` fails to load then `fallback_path` gets loaded instead.
+    // Whoever uses the asset copies the fallback over to the original handle (see copy_audio_fallbacks).
+    pub fn load_with_fallback<T: Asset>(
+        &mut self,
+        asset_server: &AssetServer,
+        path: &str,
+        fallback_path: &str,
+    ) -> Handle<T> {
+        let handle = self.load(asset_server, path);
+        self.fallbacks.push(AssetFallback {
+            original: handle.id(),
+            path: fallback_path.to_string(),
+            handle: None,
+        });
+        handle
+    }
+
+    // How far along a handle is, going by its fallback once it's failed over to one
+    pub fn load_state(&self, asset_server: &AssetServer, id: HandleId) -> LoadState {
+        let load_state = asset_server.get_load_state(id);
+        if load_state != LoadState::Failed {
+            return load_state;
+        }
+        self.fallbacks
+            .iter()
+            .find(|fallback| fallback.original == id)
+            .and_then(|fallback| fallback.handle.as_ref())
+            .map_or(load_state, |handle| asset_server.get_load_state(handle.id))
+    }
+}
+
+// A file to load if the first choice fails
+pub struct AssetFallback {
+    // The handle everything else holds on to
+    pub original: HandleId,
+    pub path: String,
+    // Set once the original fails and the fallback starts loading
+    pub handle: Option<HandleUntyped>,
+}
+
+// Sprite sheets (and other textures) shared by spawned entities
+#[derive(Resource, Default)]
+pub struct Textures {
+    pub explosion_enemy: Handle<TextureAtlas>,
+    // Bigger, in two stages
+    pub explosion_boss: Handle<TextureAtlas>,
+    pub explosion_player: Handle<TextureAtlas>,
+    // 2 frame wing flap
+    pub enemy_green_bug: Handle<TextureAtlas>,
+    pub enemy_boss: Handle<TextureAtlas>,
+    pub power_up: Handle<Image>,
+    pub shield: Handle<Image>,
+    pub muzzle_flash: Handle<TextureAtlas>,
+    // 2 frames, where a player's shot knocks out an enemy's
+    pub spark: Handle<TextureAtlas>,
+    // A different color, so enemy fire can be told apart from the player's at a glance
+    pub enemy_projectile: Handle<Image>,
+}
+
+// Mesh and materials shared by every sprite, so spawning doesn't create new assets each time
+#[derive(Resource)]
+pub struct SharedAssets {
+    pub quad: Handle<Mesh>,
+    pub player: Handle<CustomMaterial>,
+    pub projectile: Handle<CustomMaterial>,
+}
+
+// Which weapon the player is firing
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WeaponMode {
+    #[default]
+    Single,
+    // Two projectiles side by side
+    Double,
+    // Three projectiles in a fan
+    Spread,
+}
+
+// Camera shake amount (0.0 - 1.0). Goes up when things explode and decays back to 0.
+#[derive(Resource, Default)]
+pub struct ScreenShake {
+    pub trauma: f32,
+}
+
+impl ScreenShake {
+    pub fn add_trauma(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).min(1.0);
+    }
+}
+
+// The size of the area the game is played in (in world units).
+// It's fixed no matter how big the window is - the camera scales it to fit and letterboxes the rest.
+#[derive(Resource)]
+pub struct Playfield {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for Playfield {
+    fn default() -> Self {
+        Playfield {
+            width: PLAYFIELD_WIDTH,
+            height: PLAYFIELD_HEIGHT,
+        }
+    }
+}
+
+impl Playfield {
+    // Distance from the center of the screen to the left/right edge
+    pub fn edge_horizontal(&self) -> f32 {
+        self.width / 2.0
+    }
+
+    // Distance from the center of the screen to the top/bottom edge
+    pub fn edge_vertical(&self) -> f32 {
+        self.height / 2.0
+    }
+
+    // Where the player's ship sits (centered, a little above the bottom edge)
+    pub fn player_starting_position(&self) -> Vec3 {
+        Vec3::new(0.0, -self.edge_vertical() + PLAYER_BOTTOM_OFFSET, 1.0)
+    }
+
+    // The highest the ship can go in modern mode (the top of the bottom quarter)
+    pub fn player_ceiling(&self) -> f32 {
+        -self.edge_vertical() / 2.0
+    }
+}
+
+// Labels for ordering systems across plugins
+#[derive(SystemLabel, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum GameSystem {
+    CollectInput,
+    ReplayInput,
+    Collision,
+    KillPlayer,
+    AnimateExplosion,
+    PlayIntro,
+    PauseGame,
+    UpdatePauseMenu,
+    SpawnEnemies,
+    CheckLevelComplete,
+    PlayStageClear,
+    EnterInitials,
+    ToggleLeaderboard,
+}
+
+// Fixed timestep shared by all the gameplay systems (movement, shooting, collisions)
+#[derive(RunCriteriaLabel, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct FixedStep;
+
+// Name of the fixed timestep inside FixedTimesteps, so systems can look up its length
+pub const FIXED_STEP_NAME: &str = "fixed_step";
+
+// How long each fixed step lasts (in seconds). Use this instead of TIME_STEP
+// so movement speed doesn't change if the step gets tuned.
+pub fn fixed_step_seconds(fixed_timesteps: &FixedTimesteps) -> f32 {
+    fixed_timesteps
+        .get(FIXED_STEP_NAME)
+        .map_or(0.0, |fixed_step| fixed_step.step() as f32)
+}
+
+// How long the last frame took, capped at MAX_FRAME_TIME. Tick timers with this instead of time.delta().
+pub fn frame_delta(time: &Time) -> Duration {
+    time.delta().min(Duration::from_secs_f32(MAX_FRAME_TIME))
+}
+
+// frame_delta slowed down by the TimeScale, for timers and animations in the game world
+// (fixed step systems get slowed down by running fewer steps instead, see limit_fixed_steps)
+pub fn scaled_delta(time: &Time, time_scale: &TimeScale) -> Duration {
+    frame_delta(time).mul_f32(time_scale.0)
+}
+
+// Move a position up to `distance` closer to the target, returning true once it's there
+pub fn move_towards(translation: &mut Vec3, target: Vec3, distance: f32) -> bool {
+    let offset = target - *translation;
+    if offset.length() <= distance {
+        *translation = target;
+        return true;
+    }
+    *translation += offset.normalize() * distance;
+    false
+}
+
+// Defines the amount of time that should elapse between each physics step
+// in this case, 60fps (only read when creating the timestep - see fixed_step_seconds)
+pub const TIME_STEP: f32 = 1.0 / 60.0;
+// Longest frame the game plays through at once (in seconds). A browser tab coming back from the background
+// can report several seconds in one frame, which would otherwise run out every timer at the same time.
+pub const MAX_FRAME_TIME: f32 = 0.1;
+pub const PLAYFIELD_WIDTH: f32 = 1300.0;
+pub const PLAYFIELD_HEIGHT: f32 = 700.0;
+pub const PLAYER_SIZE: Vec3 = Vec3::new(15.0, 16.0, 0.0);
+// How far right of the player's ship a rescued ship flies
+pub const DUAL_FIGHTER_GAP: f32 = PLAYER_SIZE.x;
+// Enemies are sprites drawn at their natural size, so this is their hitbox (not their scale)
+pub const ENEMY_SIZE: Vec2 = Vec2::new(15.0, 16.0);
+pub const PLAYER_BOTTOM_OFFSET: f32 = 50.0; // distance from the bottom of the playfield
+pub const EXPLOSION_FRAME_TIME: f32 = 0.1; // seconds
+pub const PROJECTILE_SIZE: Vec3 = Vec3::splat(3.0);
+pub const PROJECTILE_SPEED: f32 = 280.0;
+// GameConfig defaults
+pub const PLAYER_SPEED: f32 = 400.0;
+pub const PLAYER_LIVES: usize = 3;
+pub const MAX_PLAYERS: usize = 2;
+pub const PLAYER_INVINCIBLE_TIME: f32 = 2.0; // seconds
+pub const PROJECTILE_TIME_LIMIT: f32 = 0.1; // seconds between player shots
+pub const ENEMY_POINTS: usize = 100;
+pub const BOSS_POINTS: usize = 400;
+pub const SHOT_CANCEL_POINTS: usize = 10;
+pub const POWER_UP_DROP_CHANCE: f64 = 0.1;
+pub const POWER_UP_DURATION: f32 = 10.0; // seconds
+pub const POWER_UP_SIZE: Vec2 = Vec2::new(11.0, 11.0);
+pub const POWER_UP_FALL_SPEED: f32 = 80.0;
+pub const SHIELD_DROP_CHANCE: f64 = 0.15;
+pub const ENEMY_AIM_LEAD: f32 = 0.4; // seconds
+pub const FORMATION_GAP: f32 = 50.0;
+pub const ENEMY_FIRE_INTERVAL_START: f32 = 2.0; // seconds
+pub const ENEMY_FIRE_INTERVAL_MIN: f32 = 0.6; // seconds
+pub const PLAYER_BANK_SPEED: f32 = 12.0;
+pub const CAMERA_FOLLOW_STRENGTH: f32 = 0.08;
+pub const CONTINUE_COUNTDOWN_START: usize = 9;
+pub const TRANSITION_FADE_TIME: f32 = 0.4; // seconds to fade out (and the same to fade back in)
+pub const COMBO_WINDOW: f32 = 2.0; // seconds
+pub const COMBO_MULTIPLIER_MAX: usize = 5;
+pub const POWER_UP_RAPID_FIRE_COLOR: Color = Color::ORANGE;
+
+// UI
+pub const UI_FONT_SMALL: f32 = 24.0;
+pub const UI_FONT_MEDIUM: f32 = 32.0;
+pub const UI_FONT_LARGE: f32 = 96.0;
+pub const UI_COLOR_RED: Color = Color::rgb(0.8, 0.0, 0.0);
+pub const UI_COLOR_WHITE: Color = Color::rgb(0.95, 0.95, 0.95);
+pub const UI_PADDING_CENTER_TOP: Val = Val::Px(16.0);
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{shared::*, wave::EnemyKind};
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SessionStats>()
+            .add_system(reset_session_stats)
+            .add_system(count_shots.after(reset_session_stats))
+            .add_system(count_enemies_destroyed.after(reset_session_stats))
+            .add_system(count_dives_survived.after(reset_session_stats))
+            .add_system(count_power_ups_collected.after(reset_session_stats))
+            .add_system(track_time_played.after(reset_session_stats))
+            .add_system(track_peak_combo.after(reset_session_stats));
+    }
+}
+
+// How the current game is going (ShotStats only covers the current level)
+#[derive(Resource, Default)]
+pub struct SessionStats {
+    pub shots_fired: usize,
+    pub hits: usize,
+    pub destroyed: HashMap<EnemyKind, usize>,
+    // Enemies that dove at the player and made it back to the formation
+    pub dives_survived: usize,
+    pub power_ups_collected: usize,
+    // Seconds of actual play (not counting pauses or menus)
+    pub time_played: f32,
+    pub peak_combo: usize,
+}
+
+impl SessionStats {
+    // Percentage of shots that hit something
+    pub fn hit_ratio(&self) -> f32 {
+        if self.shots_fired == 0 {
+            return 0.0;
+        }
+        // (a charged shot can hit more than one enemy, so don't go over 100%)
+        (self.hits as f32 / self.shots_fired as f32 * 100.0).min(100.0)
+    }
+
+    pub fn destroyed_total(&self) -> usize {
+        self.destroyed.values().sum()
+    }
+
+    // Time played as minutes and seconds, e.g. "3:05"
+    pub fn time_played_text(&self) -> String {
+        let seconds = self.time_played as usize;
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+// Start counting from scratch every game
+fn reset_session_stats(
+    mut start_events: EventReader<GameStartEvent>,
+    mut session_stats: ResMut<SessionStats>,
+) {
+    if start_events.iter().count() > 0 {
+        *session_stats = SessionStats::default();
+    }
+}
+
+// Add up the shots and hits from each level's ShotStats
+// (which start over every level)
+fn count_shots(
+    shot_stats: Res<ShotStats>,
+    mut session_stats: ResMut<SessionStats>,
+    // (shots fired, hits) the last time we looked
+    mut last_counts: Local<(usize, usize)>,
+) {
+    if !shot_stats.is_changed() {
+        return;
+    }
+
+    let counts = (shot_stats.shots_fired, shot_stats.hits);
+    let new_since = |count: usize, last: usize| {
+        // Went down? It was reset for a new level
+        if count < last {
+            count
+        } else {
+            count - last
+        }
+    };
+    session_stats.shots_fired += new_since(counts.0, last_counts.0);
+    session_stats.hits += new_since(counts.1, last_counts.1);
+    *last_counts = counts;
+}
+
+fn count_enemies_destroyed(
+    mut enemy_death_events: EventReader<EnemyDeathEvent>,
+    mut session_stats: ResMut<SessionStats>,
+) {
+    for event in enemy_death_events
+        .iter()
+        .filter(|event| !event.shot_cancelled)
+    {
+        *session_stats.destroyed.entry(event.kind).or_default() += 1;
+    }
+}
+
+// A dive is over once the enemy settles back in the formation
+// (enemies shot down mid-dive are despawned, so they don't count)
+fn count_dives_survived(
+    removed_sorties: RemovedComponents<Sortie>,
+    enemy_query: Query<(), With<Enemy>>,
+    mut session_stats: ResMut<SessionStats>,
+) {
+    for entity in removed_sorties.iter() {
+        if enemy_query.contains(entity) {
+            session_stats.dives_survived += 1;
+        }
+    }
+}
+
+fn count_power_ups_collected(
+    mut power_up_events: EventReader<PowerUpEvent>,
+    mut session_stats: ResMut<SessionStats>,
+) {
+    for power_up_event in power_up_events.iter() {
+        if let PowerUpEvent::Collected(_) = power_up_event {
+            session_stats.power_ups_collected += 1;
+        }
+    }
+}
+
+fn track_time_played(
+    time: Res<Time>,
+    game_state: Res<GameState>,
+    mut session_stats: ResMut<SessionStats>,
+) {
+    if game_state.started && !game_state.paused {
+        session_stats.time_played += frame_delta(&time).as_secs_f32();
+    }
+}
+
+fn track_peak_combo(combo: Res<ComboState>, mut session_stats: ResMut<SessionStats>) {
+    if combo.multiplier > session_stats.peak_combo {
+        session_stats.peak_combo = combo.multiplier;
+    }
+}
@@ -0,0 +1,120 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use crate::shared::*;
+
+// Gameplay numbers that get tweaked a lot, loaded from `assets/tuning.ron`.
+// Debug builds watch the file, so saving it changes the running game
+// (the formation and enemy fire rate pick up changes on the next level).
+pub struct TuningPlugin;
+
+impl Plugin for TuningPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<GameTuning>()
+            .init_asset_loader::<TuningLoader>()
+            .add_startup_system(load_tuning)
+            .add_system(apply_tuning);
+    }
+}
+
+const TUNING_FILE: &str = "tuning.ron";
+
+// Anything left out of the file keeps its default (the GameConfig constants)
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "2b9e5c7a-41d3-4f8e-b6a0-8c1f3e7d9a52"]
+#[serde(default)]
+pub struct GameTuning {
+    pub player_speed: f32,
+    pub player_bank_speed: f32,
+    pub projectile_speed: f32,
+    pub projectile_cooldown: f32,
+    pub formation_gap: f32,
+    pub enemy_fire_interval_start: f32,
+    pub enemy_fire_interval_min: f32,
+    pub camera_follow_strength: f32,
+}
+
+impl Default for GameTuning {
+    fn default() -> Self {
+        GameTuning {
+            player_speed: PLAYER_SPEED,
+            player_bank_speed: PLAYER_BANK_SPEED,
+            projectile_speed: PROJECTILE_SPEED,
+            projectile_cooldown: PROJECTILE_TIME_LIMIT,
+            formation_gap: FORMATION_GAP,
+            enemy_fire_interval_start: ENEMY_FIRE_INTERVAL_START,
+            enemy_fire_interval_min: ENEMY_FIRE_INTERVAL_MIN,
+            camera_follow_strength: CAMERA_FOLLOW_STRENGTH,
+        }
+    }
+}
+
+#[derive(Default)]
+struct TuningLoader;
+
+impl AssetLoader for TuningLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let tuning = ron::de::from_bytes::<GameTuning>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(tuning));
+            Ok(())
+        })
+    }
+
+    // (`.wave.ron` files still go to the wave loader, the longer extension wins)
+    fn extensions(&self) -> &[&str] {
+        &["ron"]
+    }
+}
+
+// Keeps the tuning file loaded, so changes to it come through
+#[derive(Resource)]
+struct TuningHandle(Handle<GameTuning>);
+
+// Not part of LoadingAssets - without the file the game just plays with the defaults
+fn load_tuning(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(TuningHandle(asset_server.load(TUNING_FILE)));
+}
+
+// Copy the tuning into GameConfig whenever the file is loaded or saved
+fn apply_tuning(
+    mut tuning_events: EventReader<AssetEvent<GameTuning>>,
+    tuning_handle: Option<Res<TuningHandle>>,
+    tunings: Res<Assets<GameTuning>>,
+    mut game_config: ResMut<GameConfig>,
+) {
+    let Some(tuning_handle) = tuning_handle else {
+        return;
+    };
+    let changed = tuning_events.iter().any(|event| match event {
+        AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+            *handle == tuning_handle.0
+        }
+        AssetEvent::Removed { .. } => false,
+    });
+    if !changed {
+        return;
+    }
+    let Some(tuning) = tunings.get(&tuning_handle.0) else {
+        return;
+    };
+
+    println!("[TUNING] Loaded {}", TUNING_FILE);
+    game_config.player_speed = tuning.player_speed;
+    game_config.player_bank_speed = tuning.player_bank_speed;
+    game_config.projectile_speed = tuning.projectile_speed;
+    game_config.projectile_cooldown = tuning.projectile_cooldown;
+    game_config.formation_gap = tuning.formation_gap;
+    game_config.enemy_fire_interval_start = tuning.enemy_fire_interval_start;
+    game_config.enemy_fire_interval_min = tuning.enemy_fire_interval_min;
+    game_config.camera_follow_strength = tuning.camera_follow_strength;
+}
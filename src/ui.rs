@@ -0,0 +1,1441 @@
+use bevy::prelude::*;
+
+use crate::{
+    audio::volume_slider,
+    leaderboard::{
+        HighScoreEntry, Leaderboard, LeaderboardEntry, INITIALS_LENGTH, LEADERBOARD_KEY,
+    },
+    settings::{GameSettingsState, KeyBindings, PauseMenu, PauseMenuItem, PAUSE_MENU_ITEMS},
+    shared::*,
+    stats::SessionStats,
+};
+
+pub struct UiPlugin;
+
+impl Plugin for UiPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PressStartTimer(Timer::from_seconds(
+            PRESS_START_BLINK_TIME,
+            TimerMode::Repeating,
+        )))
+        .insert_resource(GameOverTimer(Timer::from_seconds(
+            GAME_OVER_TIME,
+            TimerMode::Once,
+        )))
+        .add_startup_system(setup_ui)
+        .add_system(update_score_text)
+        .add_system(update_high_score_text)
+        .add_system(update_combo_text)
+        .add_system(blink_active_player)
+        .add_system(update_rapid_fire_indicator)
+        .add_system(display_pause_screen.after(GameSystem::UpdatePauseMenu))
+        .add_system(display_loading_screen)
+        .add_system(update_fade_overlay)
+        .add_system(display_start_screen)
+        .add_system(blink_press_start.after(display_start_screen))
+        .add_system(update_start_screen_text.after(display_start_screen))
+        .add_system(update_player_count_text.after(display_start_screen))
+        .add_system(update_game_mode_text.after(display_start_screen))
+        .add_system(update_credit_text)
+        .add_system(display_demo_text)
+        .add_system(display_stage_screen.after(GameSystem::PlayStageClear))
+        .add_system(display_turn_screen.after(GameSystem::KillPlayer))
+        .add_system(display_continue_screen.after(GameSystem::KillPlayer))
+        .add_system(display_game_over.after(GameSystem::KillPlayer))
+        .add_system(display_high_score_entry.after(GameSystem::EnterInitials))
+        .add_system(display_leaderboard.after(GameSystem::ToggleLeaderboard));
+    }
+}
+
+// A player's score, by player number starting from 0 (should be alongside a TextBundle)
+#[derive(Component)]
+struct PlayerScoreText(usize);
+
+#[derive(Component)]
+struct HighScoreText;
+
+// The combo multiplier under the 1UP score (only shown with combo scoring)
+#[derive(Component)]
+struct ComboText;
+
+// Shown in the top right while rapid fire is active
+#[derive(Component)]
+struct RapidFireIndicator;
+
+// Shrinks as rapid fire runs out
+#[derive(Component)]
+struct RapidFireBar;
+
+// Full screen container for the start screen
+#[derive(Component)]
+struct StartScreen;
+
+#[derive(Component)]
+struct PressStartText;
+
+// The 1 or 2 player choice on the start screen
+#[derive(Component)]
+struct PlayerCountText;
+
+// Classic or modern mode, picked with Left/Right on the start screen
+#[derive(Component)]
+struct GameModeText;
+
+// "CREDIT N" in the bottom right (only when free play is off)
+#[derive(Component)]
+struct CreditText;
+
+// "DEMO" along the bottom while the attract mode demo plays
+#[derive(Component)]
+struct DemoText;
+
+// Full screen container for the "PLAYER N" text when players switch turns
+#[derive(Component)]
+struct TurnScreen;
+
+// Full screen black cover for fading between screens (see Transition)
+#[derive(Component)]
+struct FadeOverlay;
+
+// Full screen cover shown until every asset has loaded
+#[derive(Component)]
+struct LoadingScreen;
+
+#[derive(Component)]
+struct LoadingText;
+
+// Full screen container for the "STAGE N" text between levels
+#[derive(Component)]
+struct StageScreen;
+
+// Full screen container for the "CONTINUE?" countdown
+#[derive(Component)]
+struct ContinueScreen;
+
+// The countdown number, and whether it takes a coin to continue
+#[derive(Component)]
+struct ContinueText;
+
+// Full screen container for the game over text
+#[derive(Component)]
+struct GameOverScreen;
+
+#[derive(Component)]
+struct GameOverText;
+
+// Full screen container for entering initials after a high score
+#[derive(Component)]
+struct HighScoreEntryScreen;
+
+// The score, the initials so far, and the leaderboard
+#[derive(Component)]
+struct HighScoreEntryText;
+
+// Full screen container for the leaderboard (opened from the start screen)
+#[derive(Component)]
+struct LeaderboardScreen;
+
+#[derive(Component)]
+struct PauseScreenText;
+
+// Full screen container that centers the pause screen text
+#[derive(Component)]
+struct PauseScreen;
+
+// Timer used to track how long the game over screen is shown
+#[derive(Resource)]
+struct GameOverTimer(Timer);
+
+// Timer used to blink the "Press Start" text on and off
+#[derive(Resource)]
+struct PressStartTimer(Timer);
+
+const GAME_OVER_JINGLE_TIME: f32 = 4.0; // seconds (results show after this)
+const GAME_OVER_TIME: f32 = 8.0; // seconds
+const PRESS_START_BLINK_TIME: f32 = 0.5; // seconds (on for this long, then off for this long)
+const ACTIVE_PLAYER_BLINK_TIME: f32 = 0.4; // seconds (on for this long, then off for this long)
+const HIGH_SCORE_DEFAULT: usize = 20000;
+// Drawn over the rest of the UI
+const LOADING_SCREEN_LAYER: i32 = 100;
+const FADE_OVERLAY_LAYER: i32 = 101;
+// Scores are zero padded to this many digits (like the arcade)
+const SCORE_DIGITS: usize = 6;
+// The multiplier text goes from white to this as the combo grows
+const COMBO_COLOR_MAX: Color = Color::rgb(1.0, 0.3, 0.0);
+const RAPID_FIRE_ICON_SIZE: f32 = 22.0;
+const RAPID_FIRE_BAR_SIZE: Vec2 = Vec2::new(60.0, 6.0);
+const RAPID_FIRE_WARNING_TIME: f32 = 2.0; // seconds left when the indicator starts blinking
+const RAPID_FIRE_BLINK_TIME: f32 = 0.15; // seconds (on for this long, then off for this long)
+
+fn setup_ui(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut loading_assets: ResMut<LoadingAssets>,
+) {
+    // Add fonts to system
+    let game_fonts = GameFonts {
+        body: loading_assets.load(&asset_server, "fonts/VT323-Regular.ttf"),
+    };
+
+    // UI Elements
+    // A bar across the top of the window split into equal columns,
+    // so the high score stays centered no matter how wide the window is
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    top: Val::Px(0.0),
+                    left: Val::Px(0.0),
+                    ..default()
+                },
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                padding: UiRect::all(UI_PADDING_CENTER_TOP),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            // Player Score, and the combo multiplier
+            let mut left_column = hud_column(JustifyContent::FlexStart);
+            left_column.style.flex_direction = FlexDirection::Column;
+            left_column.style.align_items = AlignItems::FlexStart;
+            parent.spawn(left_column).with_children(|column| {
+                column.spawn((
+                    TextBundle::from_sections([
+                        TextSection::new(
+                            player_label(0),
+                            TextStyle {
+                                font: game_fonts.body.clone(),
+                                font_size: UI_FONT_MEDIUM,
+                                color: UI_COLOR_RED,
+                            },
+                        ),
+                        TextSection::new(
+                            format_score(0),
+                            TextStyle {
+                                font: game_fonts.body.clone(),
+                                font_size: UI_FONT_MEDIUM,
+                                color: UI_COLOR_WHITE,
+                            },
+                        ),
+                    ])
+                    .with_text_alignment(TextAlignment::TOP_CENTER),
+                    PlayerScoreText(0),
+                ));
+                column.spawn((
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: game_fonts.body.clone(),
+                            font_size: UI_FONT_MEDIUM,
+                            color: UI_COLOR_WHITE,
+                        },
+                    ),
+                    ComboText,
+                ));
+            });
+
+            // High Score
+            parent
+                .spawn(hud_column(JustifyContent::Center))
+                .with_children(|column| {
+                    column.spawn((
+                        TextBundle::from_sections([
+                            TextSection::new(
+                                "HIGH SCORE\n",
+                                TextStyle {
+                                    font: game_fonts.body.clone(),
+                                    font_size: UI_FONT_MEDIUM,
+                                    color: UI_COLOR_RED,
+                                },
+                            ),
+                            TextSection::new(
+                                format_score(HIGH_SCORE_DEFAULT),
+                                TextStyle {
+                                    font: game_fonts.body.clone(),
+                                    font_size: UI_FONT_MEDIUM,
+                                    color: UI_COLOR_WHITE,
+                                },
+                            ),
+                        ])
+                        .with_text_alignment(TextAlignment::TOP_CENTER),
+                        HighScoreText,
+                    ));
+                });
+
+            // Player 2's score (only shown in a two player game) and the rapid fire indicator
+            let mut right_column = hud_column(JustifyContent::FlexStart);
+            right_column.style.flex_direction = FlexDirection::Column;
+            right_column.style.align_items = AlignItems::FlexEnd;
+            parent.spawn(right_column).with_children(|column| {
+                column.spawn((
+                    TextBundle {
+                        visibility: Visibility::INVISIBLE,
+                        ..TextBundle::from_sections([
+                            TextSection::new(
+                                player_label(1),
+                                TextStyle {
+                                    font: game_fonts.body.clone(),
+                                    font_size: UI_FONT_MEDIUM,
+                                    color: UI_COLOR_RED,
+                                },
+                            ),
+                            TextSection::new(
+                                format_score(0),
+                                TextStyle {
+                                    font: game_fonts.body.clone(),
+                                    font_size: UI_FONT_MEDIUM,
+                                    color: UI_COLOR_WHITE,
+                                },
+                            ),
+                        ])
+                        .with_text_alignment(TextAlignment::TOP_CENTER)
+                    },
+                    PlayerScoreText(1),
+                ));
+                column
+                    .spawn((
+                        NodeBundle {
+                            style: Style {
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            visibility: Visibility::INVISIBLE,
+                            ..default()
+                        },
+                        RapidFireIndicator,
+                    ))
+                    .with_children(|indicator| {
+                        indicator.spawn(ImageBundle {
+                            style: Style {
+                                size: Size::new(
+                                    Val::Px(RAPID_FIRE_ICON_SIZE),
+                                    Val::Px(RAPID_FIRE_ICON_SIZE),
+                                ),
+                                margin: UiRect::right(Val::Px(RAPID_FIRE_BAR_SIZE.y)),
+                                ..default()
+                            },
+                            image: asset_server.load("sprites/power_up.png").into(),
+                            background_color: POWER_UP_RAPID_FIRE_COLOR.into(),
+                            ..default()
+                        });
+                        indicator.spawn((
+                            NodeBundle {
+                                style: Style {
+                                    size: Size::new(
+                                        Val::Px(RAPID_FIRE_BAR_SIZE.x),
+                                        Val::Px(RAPID_FIRE_BAR_SIZE.y),
+                                    ),
+                                    ..default()
+                                },
+                                background_color: POWER_UP_RAPID_FIRE_COLOR.into(),
+                                ..default()
+                            },
+                            RapidFireBar,
+                        ));
+                    });
+            });
+        });
+
+    // Credits
+    commands.spawn((
+        TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    bottom: UI_PADDING_CENTER_TOP,
+                    right: UI_PADDING_CENTER_TOP,
+                    ..default()
+                },
+                ..default()
+            },
+            ..TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: game_fonts.body.clone(),
+                    font_size: UI_FONT_MEDIUM,
+                    color: UI_COLOR_WHITE,
+                },
+            )
+        },
+        CreditText,
+    ));
+
+    // Loading screen
+    // Covers everything (including the HUD) until the assets are ready
+    commands
+        .spawn((
+            NodeBundle {
+                background_color: Color::BLACK.into(),
+                z_index: ZIndex::Global(LOADING_SCREEN_LAYER),
+                ..centered_screen()
+            },
+            LoadingScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "LOADING",
+                    TextStyle {
+                        font: game_fonts.body.clone(),
+                        font_size: UI_FONT_MEDIUM,
+                        color: UI_COLOR_WHITE,
+                    },
+                )
+                .with_text_alignment(TextAlignment::CENTER),
+                LoadingText,
+            ));
+        });
+
+    // Fade overlay
+    // Clear until a Transition darkens it
+    commands.spawn((
+        NodeBundle {
+            background_color: Color::NONE.into(),
+            z_index: ZIndex::Global(FADE_OVERLAY_LAYER),
+            ..centered_screen()
+        },
+        FadeOverlay,
+    ));
+
+    // Now we can insert fonts as a resource after the UI has used it
+    commands.insert_resource(game_fonts);
+}
+
+fn update_score_text(
+    mut score_changed_events: EventReader<ScoreChangedEvent>,
+    mut query: Query<(&PlayerScoreText, &mut Text)>,
+) {
+    for event in score_changed_events.iter() {
+        println!("[UI] Updating player {} score", event.player + 1);
+        for (PlayerScoreText(player), mut text) in &mut query {
+            if *player == event.player {
+                text.sections[1].value = format_score(event.score);
+            }
+        }
+    }
+}
+
+// Whoever has the best score (if it beats the high score).
+// Classic and combo scoring keep separate high scores, since combo scores run much higher
+// (and so do classic and modern mode).
+// Scores from the demo (or with cheats) don't count.
+fn update_high_score_text(
+    game_state: Res<GameState>,
+    cheats: Res<Cheats>,
+    combo: Res<ComboState>,
+    game_mode: Res<GameMode>,
+    leaderboard: Res<Leaderboard>,
+    mut score_changed_events: EventReader<ScoreChangedEvent>,
+    mut query: Query<&mut Text, With<HighScoreText>>,
+    // [game mode][classic, combo]
+    mut high_scores: Local<[[usize; 2]; 2]>,
+) {
+    let high_score = &mut high_scores[*game_mode as usize][combo.enabled as usize];
+    if let Some(best_score) = score_changed_events.iter().map(|event| event.score).max() {
+        if !game_state.demo && !cheats.used {
+            *high_score = (*high_score).max(best_score);
+        }
+    }
+
+    let label = match (*game_mode, combo.enabled) {
+        (GameMode::Classic, false) => "HIGH SCORE\n",
+        (GameMode::Classic, true) => "COMBO HIGH SCORE\n",
+        (GameMode::Modern, false) => "MODERN HIGH SCORE\n",
+        (GameMode::Modern, true) => "MODERN COMBO HIGH SCORE\n",
+    };
+    let score = format_score(
+        HIGH_SCORE_DEFAULT
+            .max(*high_score)
+            .max(leaderboard.best(*game_mode, combo.enabled)),
+    );
+    for mut text in &mut query {
+        if text.sections[0].value != label {
+            text.sections[0].value = label.to_string();
+        }
+        if text.sections[1].value != score {
+            text.sections[1].value = score.clone();
+        }
+    }
+}
+
+// "x2" and up while a combo is going, getting hotter as it grows
+fn update_combo_text(
+    combo: Res<ComboState>,
+    mut query: Query<(&mut Text, &mut Visibility), With<ComboText>>,
+) {
+    let shown = combo.enabled && combo.multiplier > 1;
+    for (mut text, mut visibility) in &mut query {
+        if visibility.is_visible != shown {
+            visibility.is_visible = shown;
+        }
+        if !shown {
+            continue;
+        }
+
+        let heat = (combo.multiplier - 1) as f32 / (COMBO_MULTIPLIER_MAX - 1) as f32;
+        let white = Vec4::from(UI_COLOR_WHITE.as_rgba_f32());
+        let hot = Vec4::from(COMBO_COLOR_MAX.as_rgba_f32());
+        text.sections[0].value = format!("x{}", combo.multiplier);
+        text.sections[0].style.color = white.lerp(hot, heat).to_array().into();
+    }
+}
+
+// Blink the "1UP" or "2UP" of whoever's turn it is (and only show 2UP in a two player game)
+fn blink_active_player(
+    time: Res<Time>,
+    game_state: Res<GameState>,
+    players: Res<Players>,
+    mut query: Query<(&PlayerScoreText, &mut Text, &mut Visibility)>,
+) {
+    let blink_off = ((time.elapsed_seconds() / ACTIVE_PLAYER_BLINK_TIME) as usize) % 2 == 1;
+
+    for (PlayerScoreText(player), mut text, mut visibility) in &mut query {
+        let shown = *player < players.count;
+        if visibility.is_visible != shown {
+            visibility.is_visible = shown;
+        }
+
+        let label_color = if game_state.started && *player == players.active && blink_off {
+            Color::NONE
+        } else {
+            UI_COLOR_RED
+        };
+        if text.sections[0].style.color != label_color {
+            text.sections[0].style.color = label_color;
+        }
+    }
+}
+
+// Show how much rapid fire is left, blinking when it's about to run out
+fn update_rapid_fire_indicator(
+    player_query: Query<&RapidFire, With<Player>>,
+    mut indicator_query: Query<&mut Visibility, With<RapidFireIndicator>>,
+    mut bar_query: Query<&mut Style, With<RapidFireBar>>,
+) {
+    let rapid_fire = player_query.get_single().ok();
+    let visible = match rapid_fire {
+        Some(rapid_fire) if rapid_fire.0.remaining_secs() < RAPID_FIRE_WARNING_TIME => {
+            ((rapid_fire.0.remaining_secs() / RAPID_FIRE_BLINK_TIME) as usize).is_multiple_of(2)
+        }
+        Some(_) => true,
+        None => false,
+    };
+    for mut visibility in &mut indicator_query {
+        if visibility.is_visible != visible {
+            visibility.is_visible = visible;
+        }
+    }
+
+    let Some(rapid_fire) = rapid_fire else {
+        return;
+    };
+    for mut style in &mut bar_query {
+        style.size.width = Val::Px(RAPID_FIRE_BAR_SIZE.x * rapid_fire.0.percent_left());
+    }
+}
+
+// "1UP", "2UP" etc, on their own line above the score
+fn player_label(player: usize) -> String {
+    format!("{}UP\n", player + 1)
+}
+
+// Zero pad the score, e.g. 50 becomes "000050"
+fn format_score(score: usize) -> String {
+    format!("{:0width$}", score, width = SCORE_DIGITS)
+}
+
+fn update_fade_overlay(
+    transition: Res<Transition>,
+    mut query: Query<&mut BackgroundColor, With<FadeOverlay>>,
+) {
+    let color = Color::rgba(0.0, 0.0, 0.0, transition.darkness());
+    for mut background_color in &mut query {
+        if background_color.0 != color {
+            background_color.0 = color;
+        }
+    }
+}
+
+// Remove the loading screen once everything's loaded, or list what went wrong
+fn display_loading_screen(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    loading_assets: Res<LoadingAssets>,
+    screen_query: Query<Entity, With<LoadingScreen>>,
+    mut query: Query<&mut Text, With<LoadingText>>,
+) {
+    if !game_state.loading {
+        for screen_obj in &screen_query {
+            commands.entity(screen_obj).despawn_recursive();
+        }
+        return;
+    }
+
+    if !loading_assets.is_changed() {
+        return;
+    }
+
+    for mut text in &mut query {
+        if loading_assets.failed.is_empty() {
+            text.sections[0].value = format!(
+                "LOADING\n\n{} / {}",
+                loading_assets.loaded,
+                loading_assets.handles.len()
+            );
+        } else {
+            text.sections[0].value =
+                format!("FAILED TO LOAD\n\n{}", loading_assets.failed.join("\n"));
+            text.sections[0].style.color = UI_COLOR_RED;
+        }
+    }
+}
+
+fn display_pause_screen(
+    mut commands: Commands,
+    game_fonts: Res<GameFonts>,
+    game_state: Res<GameState>,
+    key_bindings: Res<KeyBindings>,
+    game_settings: Res<GameSettingsState>,
+    menu: Res<PauseMenu>,
+    screen_query: Query<Entity, With<PauseScreen>>,
+    mut query: Query<&mut Text, With<PauseScreenText>>,
+) {
+    // Game unpaused! Remove any UI.
+    if !game_state.paused {
+        for screen_obj in &screen_query {
+            commands.entity(screen_obj).despawn_recursive();
+        }
+        return;
+    }
+
+    // Build the list of settings, highlighting the selected row
+    let mut bindings_text = String::new();
+    for (index, item) in PAUSE_MENU_ITEMS.iter().enumerate() {
+        let cursor = if index == menu.selected { ">" } else { " " };
+        let row = match item {
+            PauseMenuItem::KeyBinding(action) => {
+                format!("{}: {:?}", action.label(), key_bindings.get(*action))
+            }
+            PauseMenuItem::MusicVolume => {
+                format!("MUSIC {}", volume_slider(game_settings.music_volume))
+            }
+            PauseMenuItem::SfxVolume => {
+                format!("SFX   {}", volume_slider(game_settings.sfx_volume))
+            }
+            PauseMenuItem::ScreenShake => {
+                let setting = if game_settings.screen_shake {
+                    "ON"
+                } else {
+                    "OFF"
+                };
+                format!("SCREEN SHAKE: {}", setting)
+            }
+            PauseMenuItem::Rumble => {
+                let setting = if game_settings.rumble { "ON" } else { "OFF" };
+                format!("RUMBLE: {}", setting)
+            }
+            PauseMenuItem::ScoringMode => {
+                let setting = if game_settings.combo_scoring {
+                    "COMBO"
+                } else {
+                    "CLASSIC"
+                };
+                format!("SCORING: {}", setting)
+            }
+            PauseMenuItem::Movement => {
+                let setting = if game_settings.smooth_movement {
+                    "SMOOTH"
+                } else {
+                    "INSTANT"
+                };
+                format!("MOVEMENT: {}", setting)
+            }
+            PauseMenuItem::BulletTime => {
+                let setting = if game_settings.bullet_time {
+                    "ON"
+                } else {
+                    "OFF"
+                };
+                format!("BULLET TIME: {}", setting)
+            }
+            PauseMenuItem::ShotCancelling => {
+                let setting = if game_settings.shot_cancelling {
+                    "ON"
+                } else {
+                    "OFF"
+                };
+                format!("SHOT CANCELLING: {}", setting)
+            }
+        };
+        bindings_text.push_str(&format!("{} {}\n", cursor, row).to_uppercase());
+    }
+
+    // Already on screen? Just refresh the text.
+    if let Ok(mut text) = query.get_single_mut() {
+        text.sections[1].value = bindings_text;
+        text.sections[2].value = menu.message.clone();
+        return;
+    }
+
+    // Display UI for Pause Screen
+    commands
+        .spawn((centered_screen(), PauseScreen))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_sections([
+                    TextSection::new(
+                        "PAUSED\n\n",
+                        TextStyle {
+                            font: game_fonts.body.clone(),
+                            font_size: UI_FONT_MEDIUM,
+                            color: UI_COLOR_RED,
+                        },
+                    ),
+                    TextSection::new(
+                        bindings_text,
+                        TextStyle {
+                            font: game_fonts.body.clone(),
+                            font_size: UI_FONT_MEDIUM,
+                            color: UI_COLOR_WHITE,
+                        },
+                    ),
+                    TextSection::new(
+                        menu.message.clone(),
+                        TextStyle {
+                            font: game_fonts.body.clone(),
+                            font_size: UI_FONT_MEDIUM,
+                            color: UI_COLOR_RED,
+                        },
+                    ),
+                ]),
+                PauseScreenText,
+            ));
+        });
+}
+
+fn display_start_screen(
+    mut commands: Commands,
+    game_fonts: Res<GameFonts>,
+    game_state: Res<GameState>,
+    query: Query<Entity, With<StartScreen>>,
+) {
+    let start_screen_exists = !query.is_empty();
+
+    // Game hasn't started and we haven't spawned UI yet
+    if !game_state.started
+        && !game_state.continue_offer
+        && !game_state.game_over
+        && !game_state.name_entry
+        && !game_state.showing_leaderboard
+        && !game_state.loading
+        && !start_screen_exists
+    {
+        // Display UI for Start Screen
+        let mut start_screen = centered_screen();
+        start_screen.style.flex_direction = FlexDirection::Column;
+        commands
+            .spawn((start_screen, StartScreen))
+            .with_children(|parent| {
+                // (filled in by update_start_screen_text)
+                parent.spawn((
+                    TextBundle::from_sections([TextSection::new(
+                        "",
+                        TextStyle {
+                            font: game_fonts.body.clone(),
+                            font_size: UI_FONT_MEDIUM,
+                            color: UI_COLOR_RED,
+                        },
+                    )])
+                    .with_text_alignment(TextAlignment::TOP_CENTER),
+                    PressStartText,
+                ));
+                parent.spawn((
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: game_fonts.body.clone(),
+                            font_size: UI_FONT_MEDIUM,
+                            color: UI_COLOR_WHITE,
+                        },
+                    )
+                    .with_text_alignment(TextAlignment::TOP_CENTER),
+                    PlayerCountText,
+                ));
+                parent.spawn((
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: game_fonts.body.clone(),
+                            font_size: UI_FONT_MEDIUM,
+                            color: UI_COLOR_WHITE,
+                        },
+                    )
+                    .with_text_alignment(TextAlignment::TOP_CENTER),
+                    GameModeText,
+                ));
+                parent.spawn(
+                    TextBundle::from_section(
+                        format!("\n{:?} - LEADERBOARD", LEADERBOARD_KEY).to_uppercase(),
+                        TextStyle {
+                            font: game_fonts.body.clone(),
+                            font_size: UI_FONT_SMALL,
+                            color: UI_COLOR_WHITE,
+                        },
+                    )
+                    .with_text_alignment(TextAlignment::TOP_CENTER),
+                );
+            });
+    }
+
+    // Game started (or the leaderboard's up)! Remove any UI.
+    if (game_state.started || game_state.showing_leaderboard) && start_screen_exists {
+        for text_obj in &query {
+            commands.entity(text_obj).despawn_recursive();
+        }
+    }
+}
+
+// "PRESS START", or "INSERT COIN" if there aren't enough credits for everyone
+fn update_start_screen_text(
+    key_bindings: Res<KeyBindings>,
+    game_settings: Res<GameSettingsState>,
+    players: Res<Players>,
+    credits: Res<Credits>,
+    mut query: Query<(&mut Text, ChangeTrackers<PressStartText>)>,
+) {
+    let changed = key_bindings.is_changed()
+        || game_settings.is_changed()
+        || players.is_changed()
+        || credits.is_changed();
+
+    for (mut text, tracker) in &mut query {
+        if !changed && !tracker.is_added() {
+            continue;
+        }
+        text.sections[0].value = if game_settings.freeplay || credits.0 >= players.count {
+            format!(
+                "Press {:?}/{:?} to Start \n",
+                key_bindings.fire, key_bindings.start
+            )
+            .to_uppercase()
+        } else {
+            "INSERT COIN\n".to_string()
+        };
+    }
+}
+
+// Point at the number of players picked (Up/Down on the start screen)
+fn update_player_count_text(
+    players: Res<Players>,
+    mut query: Query<(&mut Text, ChangeTrackers<PlayerCountText>)>,
+) {
+    // (filled in as soon as the start screen appears too)
+    for (mut text, tracker) in &mut query {
+        if !players.is_changed() && !tracker.is_added() {
+            continue;
+        }
+        text.sections[0].value = (1..=MAX_PLAYERS)
+            .map(|count| {
+                let pointer = if count == players.count { ">" } else { " " };
+                let plural = if count > 1 { "S" } else { " " };
+                format!("{} {} PLAYER{}", pointer, count, plural)
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+    }
+}
+
+fn update_game_mode_text(
+    game_mode: Res<GameMode>,
+    mut query: Query<(&mut Text, ChangeTrackers<GameModeText>)>,
+) {
+    for (mut text, tracker) in &mut query {
+        if !game_mode.is_changed() && !tracker.is_added() {
+            continue;
+        }
+        text.sections[0].value = format!("\n< {} MODE >", game_mode.label());
+    }
+}
+
+fn update_credit_text(
+    game_settings: Res<GameSettingsState>,
+    credits: Res<Credits>,
+    mut query: Query<(&mut Text, &mut Visibility), With<CreditText>>,
+) {
+    if !game_settings.is_changed() && !credits.is_changed() {
+        return;
+    }
+
+    for (mut text, mut visibility) in &mut query {
+        visibility.is_visible = !game_settings.freeplay;
+        text.sections[0].value = format!("CREDIT {}", credits.0);
+    }
+}
+
+// Blink the "Press Start" text like an arcade attract screen
+fn blink_press_start(
+    time: Res<Time>,
+    mut blink_timer: ResMut<PressStartTimer>,
+    mut query: Query<&mut Visibility, With<PressStartText>>,
+) {
+    // Text is gone (game started) - reset so it starts visible next time it's shown
+    if query.is_empty() {
+        blink_timer.0.reset();
+        return;
+    }
+
+    if blink_timer.0.tick(frame_delta(&time)).just_finished() {
+        for mut visibility in &mut query {
+            visibility.is_visible = !visibility.is_visible;
+        }
+    }
+}
+
+// "STAGE N" text between levels
+// (or how many enemies were hit, after a challenge stage)
+fn display_stage_screen(
+    mut commands: Commands,
+    game_fonts: Res<GameFonts>,
+    game_state: Res<GameState>,
+    challenge_stage: Res<ChallengeStage>,
+    shot_stats: Res<ShotStats>,
+    query: Query<Entity, With<StageScreen>>,
+) {
+    let stage_screen_exists = !query.is_empty();
+
+    if game_state.stage_clear && !stage_screen_exists {
+        let message = if !challenge_stage.active {
+            format!("STAGE {}", game_state.level)
+        } else if challenge_stage.perfect() {
+            format!("PERFECT!\nSPECIAL BONUS {}", challenge_stage.bonus)
+        } else {
+            format!(
+                "NUMBER OF HITS {}\nBONUS {}",
+                challenge_stage.hits, challenge_stage.bonus
+            )
+        };
+
+        commands
+            .spawn((centered_screen(), StageScreen))
+            .with_children(|parent| {
+                parent.spawn(
+                    TextBundle::from_sections([
+                        TextSection::new(
+                            message,
+                            TextStyle {
+                                font: game_fonts.body.clone(),
+                                font_size: UI_FONT_MEDIUM,
+                                color: UI_COLOR_RED,
+                            },
+                        ),
+                        TextSection::new(
+                            format!(
+                                "\n\nSHOTS FIRED: {}  HIT: {}  HIT-MISS RATIO: {:.1}%\nBONUS {}",
+                                shot_stats.shots_fired,
+                                shot_stats.hits,
+                                shot_stats.hit_ratio(),
+                                shot_stats.bonus
+                            ),
+                            TextStyle {
+                                font: game_fonts.body.clone(),
+                                font_size: UI_FONT_SMALL,
+                                color: UI_COLOR_WHITE,
+                            },
+                        ),
+                    ])
+                    .with_text_alignment(TextAlignment::CENTER),
+                );
+            });
+    }
+
+    if !game_state.stage_clear && stage_screen_exists {
+        for screen_obj in &query {
+            commands.entity(screen_obj).despawn_recursive();
+        }
+    }
+}
+
+// "PLAYER N" and the stage they're on when players switch turns
+// (once the last player's explosion has finished)
+fn display_turn_screen(
+    mut commands: Commands,
+    game_fonts: Res<GameFonts>,
+    game_state: Res<GameState>,
+    players: Res<Players>,
+    explosion_query: Query<(), With<PlayerExploding>>,
+    query: Query<Entity, With<TurnScreen>>,
+) {
+    let turn_screen_exists = !query.is_empty();
+    let showing = game_state.turn_change && explosion_query.is_empty();
+
+    if showing && !turn_screen_exists {
+        commands
+            .spawn((centered_screen(), TurnScreen))
+            .with_children(|parent| {
+                parent.spawn(
+                    TextBundle::from_section(
+                        format!(
+                            "PLAYER {}\n\nSTAGE {}",
+                            players.active + 1,
+                            game_state.level
+                        ),
+                        TextStyle {
+                            font: game_fonts.body.clone(),
+                            font_size: UI_FONT_MEDIUM,
+                            color: UI_COLOR_RED,
+                        },
+                    )
+                    .with_text_alignment(TextAlignment::CENTER),
+                );
+            });
+    }
+
+    if !showing && turn_screen_exists {
+        for screen_obj in &query {
+            commands.entity(screen_obj).despawn_recursive();
+        }
+    }
+}
+
+fn display_demo_text(
+    mut commands: Commands,
+    game_fonts: Res<GameFonts>,
+    game_state: Res<GameState>,
+    query: Query<Entity, With<DemoText>>,
+) {
+    let demo_text_exists = !query.is_empty();
+
+    if game_state.demo && !demo_text_exists {
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: UiRect {
+                            bottom: UI_PADDING_CENTER_TOP,
+                            left: Val::Px(0.0),
+                            ..default()
+                        },
+                        size: Size::new(Val::Percent(100.0), Val::Auto),
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    ..default()
+                },
+                DemoText,
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    "DEMO",
+                    TextStyle {
+                        font: game_fonts.body.clone(),
+                        font_size: UI_FONT_MEDIUM,
+                        color: UI_COLOR_RED,
+                    },
+                ));
+            });
+    }
+
+    if !game_state.demo && demo_text_exists {
+        for text_obj in &query {
+            commands.entity(text_obj).despawn_recursive();
+        }
+    }
+}
+
+// "CONTINUE?" with the seconds left to press start
+fn display_continue_screen(
+    mut commands: Commands,
+    game_fonts: Res<GameFonts>,
+    game_state: Res<GameState>,
+    game_settings: Res<GameSettingsState>,
+    players: Res<Players>,
+    credits: Res<Credits>,
+    countdown: Res<ContinueCountdown>,
+    screen_query: Query<Entity, With<ContinueScreen>>,
+    mut text_query: Query<&mut Text, With<ContinueText>>,
+) {
+    let continue_screen_exists = !screen_query.is_empty();
+
+    if game_state.continue_offer && !continue_screen_exists {
+        commands
+            .spawn((centered_screen(), ContinueScreen))
+            .with_children(|parent| {
+                parent.spawn((
+                    TextBundle::from_sections([
+                        TextSection::new(
+                            "CONTINUE?\n",
+                            TextStyle {
+                                font: game_fonts.body.clone(),
+                                font_size: UI_FONT_MEDIUM,
+                                color: UI_COLOR_RED,
+                            },
+                        ),
+                        // (filled in below)
+                        TextSection::from_style(TextStyle {
+                            font: game_fonts.body.clone(),
+                            font_size: UI_FONT_LARGE,
+                            color: UI_COLOR_WHITE,
+                        }),
+                        TextSection::from_style(TextStyle {
+                            font: game_fonts.body.clone(),
+                            font_size: UI_FONT_SMALL,
+                            color: UI_COLOR_WHITE,
+                        }),
+                    ])
+                    .with_text_alignment(TextAlignment::TOP_CENTER),
+                    ContinueText,
+                ));
+            });
+    }
+
+    if !game_state.continue_offer {
+        if continue_screen_exists {
+            for screen_obj in &screen_query {
+                commands.entity(screen_obj).despawn_recursive();
+            }
+        }
+        return;
+    }
+
+    for mut text in &mut text_query {
+        text.sections[1].value = format!("{}\n", countdown.seconds_left);
+        text.sections[2].value = if game_settings.freeplay || credits.0 >= players.count {
+            "PRESS START".to_string()
+        } else {
+            "INSERT COIN".to_string()
+        };
+    }
+}
+
+// Show "GAME OVER" while the jingle plays, then the results, then go back to the start screen
+fn display_game_over(
+    mut commands: Commands,
+    time: Res<Time>,
+    game_fonts: Res<GameFonts>,
+    mut game_state: ResMut<GameState>,
+    players: Res<Players>,
+    session_stats: Res<SessionStats>,
+    high_score_entry: Res<HighScoreEntry>,
+    mut game_over_timer: ResMut<GameOverTimer>,
+    screen_query: Query<Entity, With<GameOverScreen>>,
+    mut text_query: Query<&mut Text, With<GameOverText>>,
+) {
+    if !game_state.game_over {
+        return;
+    }
+
+    if screen_query.is_empty() {
+        game_over_timer.0.reset();
+        commands
+            .spawn((centered_screen(), GameOverScreen))
+            .with_children(|parent| {
+                parent.spawn((
+                    TextBundle::from_sections([
+                        TextSection::new(
+                            "GAME OVER\n",
+                            TextStyle {
+                                font: game_fonts.body.clone(),
+                                font_size: UI_FONT_MEDIUM,
+                                color: UI_COLOR_RED,
+                            },
+                        ),
+                        TextSection::from_style(TextStyle {
+                            font: game_fonts.body.clone(),
+                            font_size: UI_FONT_MEDIUM,
+                            color: UI_COLOR_WHITE,
+                        }),
+                        TextSection::from_style(TextStyle {
+                            font: game_fonts.body.clone(),
+                            font_size: UI_FONT_SMALL,
+                            color: UI_COLOR_WHITE,
+                        }),
+                    ])
+                    .with_text_alignment(TextAlignment::TOP_CENTER),
+                    GameOverText,
+                ));
+            });
+    }
+
+    game_over_timer.0.tick(frame_delta(&time));
+
+    // Jingle's done - show how the run went
+    if game_over_timer.0.elapsed_secs() >= GAME_OVER_JINGLE_TIME {
+        for mut text in &mut text_query {
+            if text.sections[1].value.is_empty() {
+                text.sections[1].value = if players.count == 1 {
+                    let progress = players.current();
+                    format!(
+                        "\nSCORE {}\nSTAGE {}",
+                        format_score(progress.score),
+                        progress.level
+                    )
+                } else {
+                    players.progress[..players.count]
+                        .iter()
+                        .enumerate()
+                        .map(|(player, progress)| {
+                            format!(
+                                "\n{}SCORE {}  STAGE {}",
+                                player_label(player),
+                                format_score(progress.score),
+                                progress.level
+                            )
+                        })
+                        .collect()
+                };
+                text.sections[2].value = format!(
+                    "\n\nSHOTS FIRED {}  HIT-MISS RATIO {:.1}%\nENEMIES DESTROYED {}  POWER-UPS {}\nTIME {}",
+                    session_stats.shots_fired,
+                    session_stats.hit_ratio(),
+                    session_stats.destroyed_total(),
+                    session_stats.power_ups_collected,
+                    session_stats.time_played_text(),
+                );
+            }
+        }
+    }
+
+    if game_over_timer.0.finished() {
+        game_state.game_over = false;
+        // Anyone who made the leaderboard enters their initials before going back to the start screen
+        game_state.name_entry = !high_score_entry.pending.is_empty();
+        for screen_obj in &screen_query {
+            commands.entity(screen_obj).despawn_recursive();
+        }
+    }
+}
+
+// "NEW HIGH SCORE" with the initials being entered (the selected letter is in brackets),
+// and the leaderboard below
+fn display_high_score_entry(
+    mut commands: Commands,
+    game_fonts: Res<GameFonts>,
+    game_state: Res<GameState>,
+    players: Res<Players>,
+    combo: Res<ComboState>,
+    game_mode: Res<GameMode>,
+    leaderboard: Res<Leaderboard>,
+    high_score_entry: Res<HighScoreEntry>,
+    screen_query: Query<Entity, With<HighScoreEntryScreen>>,
+    mut text_query: Query<&mut Text, With<HighScoreEntryText>>,
+) {
+    let screen_exists = !screen_query.is_empty();
+
+    if !game_state.name_entry {
+        if screen_exists {
+            for screen_obj in &screen_query {
+                commands.entity(screen_obj).despawn_recursive();
+            }
+        }
+        return;
+    }
+
+    if !screen_exists {
+        commands
+            .spawn((centered_screen(), HighScoreEntryScreen))
+            .with_children(|parent| {
+                parent.spawn((
+                    TextBundle::from_sections([
+                        TextSection::new(
+                            "NEW HIGH SCORE\n",
+                            TextStyle {
+                                font: game_fonts.body.clone(),
+                                font_size: UI_FONT_MEDIUM,
+                                color: UI_COLOR_RED,
+                            },
+                        ),
+                        // (filled in below)
+                        TextSection::from_style(TextStyle {
+                            font: game_fonts.body.clone(),
+                            font_size: UI_FONT_MEDIUM,
+                            color: UI_COLOR_WHITE,
+                        }),
+                        TextSection::from_style(TextStyle {
+                            font: game_fonts.body.clone(),
+                            font_size: UI_FONT_LARGE,
+                            color: UI_COLOR_WHITE,
+                        }),
+                        TextSection::new(
+                            "UP/DOWN - LETTER  LEFT/RIGHT - MOVE  FIRE - SAVE\n\n",
+                            TextStyle {
+                                font: game_fonts.body.clone(),
+                                font_size: UI_FONT_SMALL,
+                                color: UI_COLOR_WHITE,
+                            },
+                        ),
+                        TextSection::from_style(TextStyle {
+                            font: game_fonts.body.clone(),
+                            font_size: UI_FONT_SMALL,
+                            color: UI_COLOR_WHITE,
+                        }),
+                    ])
+                    .with_text_alignment(TextAlignment::TOP_CENTER),
+                    HighScoreEntryText,
+                ));
+            });
+    }
+
+    let Some(&(player, score)) = high_score_entry.pending.first() else {
+        return;
+    };
+    let player_text = if players.count > 1 {
+        format!("{}{}\n", player_label(player), format_score(score))
+    } else {
+        format!("{}\n", format_score(score))
+    };
+    let initials: String = high_score_entry
+        .initials()
+        .chars()
+        .enumerate()
+        .map(|(index, letter)| {
+            if index == high_score_entry.cursor {
+                format!("[{}]", letter)
+            } else {
+                format!(" {} ", letter)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("");
+    let leaderboard_text = leaderboard_rows(leaderboard.entries(*game_mode, combo.enabled));
+
+    for mut text in &mut text_query {
+        if text.sections[1].value != player_text {
+            text.sections[1].value = player_text.clone();
+        }
+        let initials_text = format!("{}\n", initials);
+        if text.sections[2].value != initials_text {
+            text.sections[2].value = initials_text;
+        }
+        if text.sections[4].value != leaderboard_text {
+            text.sections[4].value = leaderboard_text.clone();
+        }
+    }
+}
+
+// The top scores (from the start screen), for whichever mode and scoring the next game will use
+fn display_leaderboard(
+    mut commands: Commands,
+    game_fonts: Res<GameFonts>,
+    game_state: Res<GameState>,
+    game_settings: Res<GameSettingsState>,
+    game_mode: Res<GameMode>,
+    key_bindings: Res<KeyBindings>,
+    leaderboard: Res<Leaderboard>,
+    screen_query: Query<Entity, With<LeaderboardScreen>>,
+) {
+    let screen_exists = !screen_query.is_empty();
+
+    if !game_state.showing_leaderboard {
+        if screen_exists {
+            for screen_obj in &screen_query {
+                commands.entity(screen_obj).despawn_recursive();
+            }
+        }
+        return;
+    }
+    if screen_exists {
+        return;
+    }
+
+    let combo = game_settings.combo_scoring;
+    let title = match (*game_mode, combo) {
+        (GameMode::Classic, false) => "LEADERBOARD\n\n",
+        (GameMode::Classic, true) => "COMBO LEADERBOARD\n\n",
+        (GameMode::Modern, false) => "MODERN LEADERBOARD\n\n",
+        (GameMode::Modern, true) => "MODERN COMBO LEADERBOARD\n\n",
+    };
+    let entries = leaderboard.entries(*game_mode, combo);
+    let rows = if entries.is_empty() {
+        "NO SCORES YET".to_string()
+    } else {
+        leaderboard_rows(entries)
+    };
+    let back = format!("\n\nPress {:?} to go back", key_bindings.start).to_uppercase();
+    commands
+        .spawn((centered_screen(), LeaderboardScreen))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_sections([
+                    TextSection::new(
+                        title,
+                        TextStyle {
+                            font: game_fonts.body.clone(),
+                            font_size: UI_FONT_MEDIUM,
+                            color: UI_COLOR_RED,
+                        },
+                    ),
+                    TextSection::new(
+                        rows,
+                        TextStyle {
+                            font: game_fonts.body.clone(),
+                            font_size: UI_FONT_MEDIUM,
+                            color: UI_COLOR_WHITE,
+                        },
+                    ),
+                    TextSection::new(
+                        back,
+                        TextStyle {
+                            font: game_fonts.body.clone(),
+                            font_size: UI_FONT_SMALL,
+                            color: UI_COLOR_WHITE,
+                        },
+                    ),
+                ])
+                .with_text_alignment(TextAlignment::TOP_CENTER),
+            );
+        });
+}
+
+// One line per score, e.g. " 1. ABC 012345"
+fn leaderboard_rows(entries: &[LeaderboardEntry]) -> String {
+    entries
+        .iter()
+        .enumerate()
+        .map(|(rank, entry)| {
+            format!(
+                "{:>2}. {:<width$} {}",
+                rank + 1,
+                entry.name,
+                format_score(entry.score),
+                width = INITIALS_LENGTH
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// Full window container that centers it's children (e.g. start and pause screens).
+// Flexbox keeps it centered when the window resizes.
+fn centered_screen() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(0.0),
+                left: Val::Px(0.0),
+                ..default()
+            },
+            size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        ..default()
+    }
+}
+
+// One of the equal width columns in the top bar
+fn hud_column(justify_content: JustifyContent) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            flex_grow: 1.0,
+            flex_basis: Val::Px(0.0),
+            justify_content,
+            ..default()
+        },
+        ..default()
+    }
+}
@@ -0,0 +1,155 @@
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use crate::{game::is_challenge_stage, shared::*};
+
+pub struct WavePlugin;
+
+impl Plugin for WavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<WaveDefinition>()
+            .init_asset_loader::<WaveLoader>()
+            .add_startup_system(load_waves);
+    }
+}
+
+// Every wave, in the order they're played.
+// Normal levels cycle through the normal waves, challenge stages cycle through the challenge waves.
+const WAVE_FILES: [&str; 4] = [
+    "waves/line.wave.ron",
+    "waves/bosses.wave.ron",
+    "waves/splitters.wave.ron",
+    "waves/challenge_loops.wave.ron",
+];
+
+// The enemies that make up a level, loaded from a `.wave.ron` file in `assets/waves/`
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "6f3c1d2a-8b47-4e0a-9a51-3f2d7c0e9b14"]
+pub struct WaveDefinition {
+    // Challenge stages have no enemy fire, and a bonus for every hit
+    #[serde(default)]
+    pub challenge: bool,
+    pub groups: Vec<WaveGroup>,
+}
+
+// Enemies that enter together, one after another
+#[derive(Deserialize)]
+pub struct WaveGroup {
+    #[serde(default)]
+    pub path: EntrancePath,
+    // Seconds after the level starts before the first enemy appears
+    #[serde(default)]
+    pub delay: f32,
+    // Seconds between each enemy in the group
+    #[serde(default)]
+    pub spacing: f32,
+    // Where enemies that fly a path come in from, in formation gaps from the center of the screen
+    // (negative is left)
+    #[serde(default)]
+    pub entry: f32,
+    pub enemies: Vec<WaveEnemy>,
+}
+
+#[derive(Deserialize)]
+pub struct WaveEnemy {
+    pub kind: EnemyKind,
+    // Their slot in the formation (only used by enemies entering in Formation).
+    // Row 0 is the bottom of the formation, higher rows are further up the screen.
+    // Columns count from the left, and each row is centered on screen.
+    #[serde(default)]
+    pub row: usize,
+    #[serde(default)]
+    pub column: usize,
+    // Breaks apart into more enemies when it's destroyed, e.g. `splits: Some((GreenBug, 3))`
+    #[serde(default)]
+    pub splits: Option<Splits>,
+}
+
+// (also a component on every enemy, so we know what was destroyed)
+#[derive(Deserialize, Component, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum EnemyKind {
+    #[default]
+    GreenBug,
+    Boss,
+}
+
+// How an enemy gets on screen
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum EntrancePath {
+    // Appears right in its spot in the formation
+    #[default]
+    Formation,
+    // Dives in from the top at the group's entry, loops once and leaves out the bottom (challenge stages)
+    LoopLeft,
+    LoopRight,
+}
+
+#[derive(Default)]
+struct WaveLoader;
+
+impl AssetLoader for WaveLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let wave = ron::de::from_bytes::<WaveDefinition>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(wave));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["wave.ron"]
+    }
+}
+
+// Handles to every wave file.
+// The wave is looked up fresh each level, so edits to a wave (when hot reloading) show up on the next level.
+#[derive(Resource)]
+pub struct WaveLibrary(Vec<Handle<WaveDefinition>>);
+
+impl WaveLibrary {
+    // The wave to play for a level
+    pub fn for_level<'a>(
+        &self,
+        level: usize,
+        waves: &'a Assets<WaveDefinition>,
+    ) -> Option<&'a WaveDefinition> {
+        let challenge = is_challenge_stage(level);
+        let matching: Vec<&WaveDefinition> = self
+            .0
+            .iter()
+            .filter_map(|handle| waves.get(handle))
+            .filter(|wave| wave.challenge == challenge)
+            .collect();
+        if matching.is_empty() {
+            return None;
+        }
+
+        // Count how many levels of this type came before, and cycle through the waves
+        let played = (1..level)
+            .filter(|previous| is_challenge_stage(*previous) == challenge)
+            .count();
+        Some(matching[played % matching.len()])
+    }
+}
+
+fn load_waves(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut loading_assets: ResMut<LoadingAssets>,
+) {
+    commands.insert_resource(WaveLibrary(
+        WAVE_FILES
+            .iter()
+            .map(|path| loading_assets.load(&asset_server, path))
+            .collect(),
+    ));
+}